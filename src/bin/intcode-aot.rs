@@ -0,0 +1,142 @@
+//! Translates Intcode programs to Rust source.
+//!
+//! Usage:
+//!
+//! - `intcode-aot <program> [output.rs]` writes the translated module to the output file,
+//!   or to the standard output.
+//! - `intcode-aot --check <program> [input...]` builds the translated module in a scratch
+//!   crate and checks that running it on the inputs agrees with the interpreter.
+
+use aoc_2019::util::computer::{aot, parse_code};
+
+use std::{env, fs, path::Path, process};
+
+const USAGE: &str = "\
+usage: intcode-aot <program> [output.rs]
+       intcode-aot --check <program> [input...]";
+
+/// Entry point of the scratch crate, runs `Translated::check` on the arguments.
+const CHECK_MAIN: &str = "\
+mod program;
+
+fn main() {
+    let inputs: Vec<i64> = std::env::args()
+        .skip(1)
+        .map(|n| n.parse().expect(\"invalid input\"))
+        .collect();
+    match program::new().check(inputs) {
+        Ok(outputs) => println!(\"translated and interpreted runs agree, outputs: {:?}\", outputs),
+        Err(err) => {
+            eprintln!(\"{}\", err);
+            std::process::exit(1);
+        }
+    }
+}
+";
+
+fn load(path: &str) -> Result<Vec<i64>, String> {
+    let source = fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+    parse_code(&source).ok_or_else(|| format!("{}: invalid program", path))
+}
+
+/// Builds and runs the scratch crate, returns whether the runs agree.
+fn check(source: &str, inputs: &[String]) -> Result<bool, String> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let dir = env::temp_dir().join(format!("intcode-aot-{}", process::id()));
+    let manifest = format!(
+        "[package]\nname = \"intcode-aot-check\"\nversion = \"0.0.0\"\nedition = \"2018\"\n\n\
+         [dependencies]\naoc-2019 = {{ path = {:?} }}\n\n[workspace]\n",
+        root
+    );
+    let io_err = |err: std::io::Error| format!("{}: {}", dir.display(), err);
+    fs::create_dir_all(dir.join("src")).map_err(io_err)?;
+    fs::write(dir.join("Cargo.toml"), manifest).map_err(io_err)?;
+    // same dependency versions as this crate, without a registry lookup
+    if root.join("Cargo.lock").exists() {
+        fs::copy(root.join("Cargo.lock"), dir.join("Cargo.lock")).map_err(io_err)?;
+    }
+    fs::write(dir.join("src/main.rs"), CHECK_MAIN).map_err(io_err)?;
+    fs::write(dir.join("src/program.rs"), source).map_err(io_err)?;
+
+    let status = process::Command::new(env::var("CARGO").unwrap_or_else(|_| "cargo".to_owned()))
+        .args(["run", "--release", "--quiet", "--manifest-path"])
+        .arg(dir.join("Cargo.toml"))
+        .arg("--")
+        .args(inputs)
+        .env("CARGO_TARGET_DIR", root.join("target").join("aot-check"))
+        .status()
+        .map_err(|err| format!("cannot run cargo: {}", err));
+    let _ = fs::remove_dir_all(&dir);
+    Ok(status?.success())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let res = match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        ["--check", path, ..] => load(path).and_then(|code| {
+            let source = aot::translate(&code, "aoc_2019");
+            check(&source, &args[2..]).map(|agree| {
+                if !agree {
+                    process::exit(1);
+                }
+            })
+        }),
+        [path] => load(path).map(|code| print!("{}", aot::translate(&code, "aoc_2019"))),
+        [path, output] => load(path).and_then(|code| {
+            fs::write(output, aot::translate(&code, "aoc_2019"))
+                .map_err(|err| format!("{}: {}", output, err))
+        }),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+    if let Err(err) = res {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads two values and outputs their sum.
+    const SUM: &str = "3,11,3,12,1,11,12,13,4,13,99,0,0,0";
+
+    fn temp_file(name: &str, contents: &str) -> String {
+        let path = env::temp_dir().join(format!("intcode-aot-{}-{}", process::id(), name));
+        fs::write(&path, contents).unwrap();
+        path.to_str().unwrap().to_owned()
+    }
+
+    #[test]
+    fn loading() {
+        let path = temp_file("sum.ic", SUM);
+        assert_eq!(load(&path).unwrap()[..5], [3, 11, 3, 12, 1]);
+        fs::remove_file(&path).unwrap();
+        assert!(load(&path).unwrap_err().starts_with(&path));
+
+        let path = temp_file("invalid.ic", "3,11,foo");
+        assert_eq!(load(&path), Err(format!("{}: invalid program", path)));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    #[ignore = "builds a scratch crate with cargo"]
+    fn checking() {
+        let code = parse_code(SUM).unwrap();
+        let source = aot::translate(&code, "aoc_2019");
+        let inputs = ["3".to_owned(), "4".to_owned()];
+        assert_eq!(check(&source, &inputs), Ok(true));
+        // a translation subtracting instead of adding
+        assert!(source.contains("checked_add"));
+        let source = source.replace("checked_add", "checked_sub");
+        assert_eq!(check(&source, &inputs), Ok(false));
+    }
+}
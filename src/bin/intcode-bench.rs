@@ -0,0 +1,105 @@
+//! Compares the speed of the Intcode execution engines.
+//!
+//! Usage: `intcode-bench [<program> [input...]]`. The program runs to completion with
+//! each engine, which must end in the same state. Without a program, a built-in loop
+//! summing the integers up to 10 000 000 is used.
+
+use aoc_2019::util::computer::{parse_code, Computer, Engine};
+
+use std::{
+    env, fs, process,
+    time::{Duration, Instant},
+};
+
+/// Sums the integers from 1 to its input: about four instructions per integer.
+const WORKLOAD: &[i64] = &[
+    3, 100, 1, 100, 101, 101, 1001, 100, -1, 100, 1005, 100, 2, 4, 101, 99,
+];
+const WORKLOAD_INPUT: i64 = 10_000_000;
+
+/// Number of timed runs per engine, the fastest one is kept.
+const RUNS: usize = 5;
+
+/// State of a machine after running a program, compared across engines.
+struct Outcome {
+    elapsed: Duration,
+    stop: String,
+    outputs: Vec<i64>,
+    computer: Computer,
+}
+
+fn run(code: &[i64], inputs: &[i64], engine: Engine) -> Outcome {
+    let mut computer = Computer::new(code, None);
+    computer.set_engine(engine);
+    computer.push_inputs(inputs.iter().copied());
+    let start = Instant::now();
+    let stop = match computer.run_buffered() {
+        Ok(stop) => format!("{:?}", stop),
+        Err(err) => format!("error: {}", err),
+    };
+    let elapsed = start.elapsed();
+    Outcome {
+        elapsed,
+        stop,
+        outputs: computer.drain_outputs().collect(),
+        computer,
+    }
+}
+
+fn bench(code: &[i64], inputs: &[i64], engine: Engine) -> Outcome {
+    (0..RUNS)
+        .map(|_| run(code, inputs, engine))
+        .min_by_key(|outcome| outcome.elapsed)
+        .unwrap()
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (code, inputs) = match args.split_first() {
+        Some((path, inputs)) => {
+            let code = fs::read_to_string(path)
+                .map_err(|err| format!("{}: {}", path, err))
+                .and_then(|source| {
+                    parse_code(&source).ok_or_else(|| format!("{}: invalid program", path))
+                });
+            let inputs = inputs
+                .iter()
+                .map(|n| n.parse().map_err(|_| format!("invalid input: {}", n)))
+                .collect::<Result<Vec<i64>, _>>();
+            match code.and_then(|code| Ok((code, inputs?))) {
+                Ok(program) => program,
+                Err(err) => {
+                    eprintln!("{}", err);
+                    process::exit(1);
+                }
+            }
+        }
+        None => (WORKLOAD.to_vec(), vec![WORKLOAD_INPUT]),
+    };
+
+    let reference = bench(&code, &inputs, Engine::Interpreter);
+    let cached = bench(&code, &inputs, Engine::Cached);
+    for (name, outcome) in &[("interpreter", &reference), ("cached", &cached)] {
+        println!(
+            "{:12} {:10.3} ms",
+            name,
+            outcome.elapsed.as_secs_f64() * 1000.0
+        );
+    }
+    println!(
+        "speedup      {:10.2}x",
+        reference.elapsed.as_secs_f64() / cached.elapsed.as_secs_f64()
+    );
+    println!("stopped: {}", reference.stop);
+    println!("outputs: {:?}", reference.outputs);
+
+    if cached.stop != reference.stop
+        || cached.outputs != reference.outputs
+        || cached.computer != reference.computer
+    {
+        eprintln!("engines disagree:");
+        eprintln!("  cached engine stopped: {}", cached.stop);
+        eprintln!("  cached engine outputs: {:?}", cached.outputs);
+        process::exit(1);
+    }
+}
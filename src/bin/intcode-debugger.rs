@@ -0,0 +1,546 @@
+//! Interactive debugger for Intcode programs.
+//!
+//! Usage: `intcode-debugger <program>`, where the program file holds comma-separated
+//! integers like the puzzle inputs. Type `help` at the prompt for the list of commands.
+
+use aoc_2019::util::computer::{
+    decompiler, disasm::Disasm, Access, Breakpoint, Cfg, Computer, Opcode, Snapshot, Stopped,
+    Watchpoint,
+};
+
+use std::{
+    env, fs,
+    io::{self, prelude::*},
+    process,
+};
+
+/// Number of instructions that can be undone.
+const HISTORY_SIZE: usize = 1_000_000;
+
+const HELP: &str = "\
+commands:
+  s, step [n]                 run n instructions (default 1)
+  c, continue                 run until a breakpoint, watchpoint, input request or halt
+  u, until <addr>             run until the instruction at <addr>
+  bs, back [n]                undo n instructions (default 1)
+  bu, back-until <addr>       undo instructions until ip is <addr>
+  lw, last-write <addr>       undo instructions until the last write to <addr>
+  b, break <addr|mnemonic>    set a breakpoint on an address or opcode
+  d, delete <addr|mnemonic>   remove a breakpoint
+  w, watch <addr> [r|w|rw] [value]
+                              pause after an access to <addr> (default rw)
+  uw, unwatch <addr>          remove the watchpoints on <addr>
+  bl, breakpoints             list breakpoints and watchpoints
+  x, examine <addr> [n]       print n memory cells (default 8)
+  p, poke <addr> <value>      write a value to memory
+  r, regs                     show registers and I/O queues
+  i, input <value>...         queue input values
+  t, text <text>              queue a line of ASCII input
+  save <file>                 save the machine state to a snapshot file
+  load <file>                 restore the machine state from a snapshot file
+  l, list [addr] [n]          disassemble n instructions around addr (default ip)
+  prof, profile start|stop    start or stop counting executed instructions
+  prof, profile report [n]    show opcode counts and the n hottest addresses (default 20)
+  prof, profile folded <file> write the counts in folded stack format
+  cov, coverage start|stop    start or stop recording executed instructions
+  cov, coverage show          disassemble memory with coverage marks
+  cfg <file>                  write the control-flow graph of memory in DOT format
+  decompile                   show memory as structured pseudocode
+  q, quit                     exit the debugger";
+
+struct Debugger {
+    computer: Computer,
+}
+
+impl Debugger {
+    fn print_location(&self) {
+        let ip = self.computer.ip();
+        match Disasm::memory(self.computer.memory())
+            .starting_at(ip.max(0) as usize)
+            .next()
+        {
+            Some(line) => println!("=> {}", line),
+            None => println!("=> {:5}: <end of memory>", ip),
+        }
+    }
+
+    /// Reports why the machine stopped, returns `true` if it should keep running.
+    fn report(&self, stop: Stopped) -> bool {
+        match stop {
+            Stopped::Output(value) if (32..127).contains(&value) || value == 10 => {
+                println!("output: {} ({:?})", value, value as u8 as char);
+                true
+            }
+            Stopped::Output(value) => {
+                println!("output: {}", value);
+                true
+            }
+            Stopped::NeedsInput => {
+                println!("program is waiting for input");
+                false
+            }
+            Stopped::Halted => {
+                println!("program halted");
+                false
+            }
+            Stopped::Breakpoint(breakpoint) => {
+                println!("breakpoint: {}", describe_breakpoint(breakpoint));
+                false
+            }
+            Stopped::Watch(hit) => {
+                println!(
+                    "watchpoint: instruction at {} {} {} at address {}",
+                    hit.ip,
+                    if hit.access == Access::Read {
+                        "read"
+                    } else {
+                        "wrote"
+                    },
+                    hit.value,
+                    hit.watchpoint.addr
+                );
+                false
+            }
+        }
+    }
+
+    fn step(&mut self, count: usize) {
+        for _ in 0..count {
+            match self.computer.step() {
+                Ok(None) => (),
+                Ok(Some(stop)) => {
+                    if !self.report(stop) && !matches!(stop, Stopped::Breakpoint(_)) {
+                        break;
+                    }
+                }
+                Err(err) => {
+                    println!("{}", err);
+                    break;
+                }
+            }
+        }
+        self.print_location();
+    }
+
+    fn cont(&mut self) {
+        loop {
+            match self.computer.run(None) {
+                Ok(stop) => {
+                    if !self.report(stop) {
+                        break;
+                    }
+                }
+                Err(err) => {
+                    println!("{}", err);
+                    break;
+                }
+            }
+        }
+        self.print_location();
+    }
+
+    fn until(&mut self, addr: i64) {
+        let breakpoint = Breakpoint::Address(addr);
+        let temporary = self.computer.add_breakpoint(breakpoint);
+        self.cont();
+        if temporary {
+            self.computer.remove_breakpoint(breakpoint);
+        }
+    }
+
+    fn back(&mut self, count: usize) {
+        for _ in 0..count {
+            if !self.computer.step_back() {
+                println!("reached the start of the history");
+                break;
+            }
+        }
+        self.print_location();
+    }
+
+    fn rewind(&mut self, found: bool) {
+        if !found {
+            println!("reached the start of the history");
+        }
+        self.print_location();
+    }
+
+    fn list(&self, addr: i64, count: usize) {
+        let ip = self.computer.ip();
+        let mem = self.computer.memory();
+        // sweep from a bit before the address, hoping to synchronize on the instructions
+        let context: Vec<_> = Disasm::memory(mem)
+            .starting_at(addr.saturating_sub(16).max(0) as usize)
+            .take_while(|line| (line.addr() as i64) < addr)
+            .collect();
+        let before = context.iter().skip(context.len().saturating_sub(3));
+        let after = Disasm::memory(mem)
+            .starting_at(addr.max(0) as usize)
+            .take(count);
+        for line in before.cloned().chain(after) {
+            let marker = if line.addr() as i64 == ip { "=>" } else { "  " };
+            println!("{} {}", marker, line);
+        }
+    }
+
+    fn profile(&mut self, args: &[&str]) -> Result<(), String> {
+        match args.first().copied() {
+            Some("start") => self.computer.start_profiling(),
+            Some("stop") => {
+                self.computer.stop_profiling();
+            }
+            Some("report") => {
+                let top = match args.get(1) {
+                    Some(n) => n.parse().map_err(|_| format!("not a number: {}", n))?,
+                    None => 20,
+                };
+                let profile = self.computer.profiling_report().ok_or("not profiling")?;
+                print!("{}", profile.report(self.computer.memory(), top));
+            }
+            Some("folded") => {
+                let path = args.get(1).ok_or("missing argument")?;
+                let profile = self.computer.profiling_report().ok_or("not profiling")?;
+                fs::write(path, profile.folded()).map_err(|err| format!("{}: {}", path, err))?;
+            }
+            _ => return Err("expected start, stop, report or folded".to_owned()),
+        }
+        Ok(())
+    }
+
+    fn examine(&self, addr: i64, count: usize) {
+        for addr in addr..addr.saturating_add(count as i64) {
+            match self.computer.read_raw(addr) {
+                Ok(value) => println!("{:5}: {}", addr, value),
+                Err(err) => {
+                    println!("{}", err);
+                    break;
+                }
+            }
+        }
+    }
+
+    fn regs(&self) {
+        println!("ip:  {}", self.computer.ip());
+        println!("rbo: {}", self.computer.rbo());
+        println!("halted: {}", self.computer.is_halted());
+        println!("pending input: {:?}", self.computer.pending_inputs());
+        println!("memory size: {}", self.computer.memory().len());
+        println!("history: {} instructions", self.computer.history_len());
+    }
+
+    fn list_breakpoints(&self) {
+        for &breakpoint in self.computer.breakpoints() {
+            println!("break {}", describe_breakpoint(breakpoint));
+        }
+        for watchpoint in self.computer.watchpoints() {
+            print!("watch {} {:?}", watchpoint.addr, watchpoint.access);
+            match watchpoint.value {
+                Some(value) => println!(" == {}", value),
+                None => println!(),
+            }
+        }
+    }
+
+    /// Runs a command line, returns `false` when the session is over.
+    fn command(&mut self, line: &str) -> Result<bool, String> {
+        let mut args = line.split_whitespace();
+        let command = match args.next() {
+            Some(command) => command,
+            None => return Ok(true),
+        };
+        let args: Vec<&str> = args.collect();
+        let arg = |i: usize| -> Result<i64, String> {
+            let s = args.get(i).ok_or("missing argument")?;
+            s.parse().map_err(|_| format!("not a number: {}", s))
+        };
+        let opt_arg = |i: usize, default: i64| -> Result<i64, String> {
+            if args.len() > i {
+                arg(i)
+            } else {
+                Ok(default)
+            }
+        };
+
+        match command {
+            "s" | "step" => self.step(opt_arg(0, 1)?.max(0) as usize),
+            "c" | "continue" => self.cont(),
+            "u" | "until" => self.until(arg(0)?),
+            "bs" | "back" => self.back(opt_arg(0, 1)?.max(0) as usize),
+            "bu" | "back-until" => {
+                let found = self.computer.run_back_to(arg(0)?);
+                self.rewind(found);
+            }
+            "lw" | "last-write" => {
+                let found = self.computer.run_back_to_write(arg(0)?);
+                self.rewind(found);
+            }
+            "b" | "break" => {
+                let breakpoint = parse_breakpoint(args.first().ok_or("missing argument")?)?;
+                self.computer.add_breakpoint(breakpoint);
+            }
+            "d" | "delete" => {
+                let breakpoint = parse_breakpoint(args.first().ok_or("missing argument")?)?;
+                if !self.computer.remove_breakpoint(breakpoint) {
+                    return Err("no such breakpoint".to_owned());
+                }
+            }
+            "w" | "watch" => {
+                let access = match args.get(1).copied() {
+                    None | Some("rw") => Access::ReadWrite,
+                    Some("r") => Access::Read,
+                    Some("w") => Access::Write,
+                    Some(other) => return Err(format!("invalid access: {}", other)),
+                };
+                let mut watchpoint = Watchpoint::new(arg(0)?, access);
+                if args.len() > 2 {
+                    watchpoint = watchpoint.with_value(arg(2)?);
+                }
+                self.computer.add_watchpoint(watchpoint);
+            }
+            "uw" | "unwatch" => {
+                let addr = arg(0)?;
+                let watchpoints: Vec<_> = self
+                    .computer
+                    .watchpoints()
+                    .iter()
+                    .filter(|w| w.addr == addr)
+                    .copied()
+                    .collect();
+                if watchpoints.is_empty() {
+                    return Err("no such watchpoint".to_owned());
+                }
+                for watchpoint in watchpoints {
+                    self.computer.remove_watchpoint(watchpoint);
+                }
+            }
+            "bl" | "breakpoints" => self.list_breakpoints(),
+            "x" | "examine" => self.examine(arg(0)?, opt_arg(1, 8)?.max(0) as usize),
+            "p" | "poke" => self
+                .computer
+                .write_raw(arg(0)?, arg(1)?)
+                .map_err(|err| err.to_string())?,
+            "r" | "regs" => self.regs(),
+            "i" | "input" => {
+                let values = (0..args.len()).map(arg).collect::<Result<Vec<_>, _>>()?;
+                self.computer.push_inputs(values);
+            }
+            "t" | "text" => {
+                let text = line.trim_start()[command.len()..].trim_start();
+                self.computer.push_str(text);
+                self.computer.push_input(i64::from(b'\n'));
+            }
+            "save" => {
+                let path = args.first().ok_or("missing argument")?;
+                self.computer
+                    .snapshot()
+                    .save(path)
+                    .map_err(|err| format!("{}: {}", path, err))?;
+            }
+            "load" => {
+                let path = args.first().ok_or("missing argument")?;
+                let snapshot = Snapshot::load(path).map_err(|err| format!("{}: {}", path, err))?;
+                self.computer.restore(&snapshot);
+                self.print_location();
+            }
+            "l" | "list" => self.list(
+                opt_arg(0, self.computer.ip())?,
+                opt_arg(1, 8)?.max(0) as usize,
+            ),
+            "prof" | "profile" => self.profile(&args)?,
+            "cov" | "coverage" => match args.first().copied() {
+                Some("start") => self.computer.start_coverage(),
+                Some("stop") => {
+                    self.computer.stop_coverage();
+                }
+                Some("show") => {
+                    let coverage = self.computer.coverage().ok_or("not recording coverage")?;
+                    print!("{}", coverage.annotate(self.computer.disasm()));
+                }
+                _ => return Err("expected start, stop or show".to_owned()),
+            },
+            "cfg" => {
+                let path = args.first().ok_or("missing argument")?;
+                let cfg = Cfg::memory(self.computer.memory());
+                fs::write(path, cfg.to_dot()).map_err(|err| format!("{}: {}", path, err))?;
+                let indirect = cfg.indirect_jumps().count();
+                println!(
+                    "{} blocks, {} edges, {} indirect jumps",
+                    cfg.blocks().count(),
+                    cfg.edges().len(),
+                    indirect
+                );
+            }
+            "decompile" => {
+                print!(
+                    "{}",
+                    decompiler::decompile(&Cfg::memory(self.computer.memory()))
+                );
+            }
+            "h" | "help" => println!("{}", HELP),
+            "q" | "quit" => return Ok(false),
+            _ => return Err(format!("unknown command: {}, try `help`", command)),
+        }
+        Ok(true)
+    }
+}
+
+fn parse_breakpoint(arg: &str) -> Result<Breakpoint, String> {
+    match arg.parse() {
+        Ok(addr) => Ok(Breakpoint::Address(addr)),
+        Err(_) => Opcode::from_mnemonic(arg)
+            .map(Breakpoint::Opcode)
+            .ok_or_else(|| format!("not an address or mnemonic: {}", arg)),
+    }
+}
+
+fn describe_breakpoint(breakpoint: Breakpoint) -> String {
+    match breakpoint {
+        Breakpoint::Address(addr) => format!("address {}", addr),
+        Breakpoint::Opcode(opcode) => format!("opcode {}", opcode.mnemonic()),
+    }
+}
+
+fn load(path: &str) -> Result<Vec<i64>, String> {
+    let source = fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+    source
+        .trim()
+        .split(',')
+        .map(|n| {
+            n.trim()
+                .parse()
+                .map_err(|_| format!("{}: invalid number: {}", path, n))
+        })
+        .collect()
+}
+
+fn main() {
+    let path = match env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: intcode-debugger <program>");
+            process::exit(2);
+        }
+    };
+    let code = load(&path).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        process::exit(1);
+    });
+    let mut debugger = Debugger {
+        computer: Computer::new(&code, None),
+    };
+    debugger.computer.enable_history(HISTORY_SIZE);
+
+    println!(
+        "loaded {} words from {}, type `help` for help",
+        code.len(),
+        path
+    );
+    debugger.print_location();
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("(icdb) ");
+        io::stdout().flush().unwrap();
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => break,
+        };
+        match debugger.command(&line) {
+            Ok(true) => (),
+            Ok(false) => break,
+            Err(err) => println!("error: {}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads a value and outputs it until it reads 0.
+    const ECHO: &[i64] = &[3, 9, 4, 9, 1005, 9, 0, 99, 0, 0];
+
+    fn debugger() -> Debugger {
+        let mut debugger = Debugger {
+            computer: Computer::new(ECHO, None),
+        };
+        debugger.computer.enable_history(HISTORY_SIZE);
+        debugger
+    }
+
+    fn run(debugger: &mut Debugger, lines: &[&str]) {
+        for line in lines {
+            assert_eq!(debugger.command(line), Ok(true), "{}", line);
+        }
+    }
+
+    #[test]
+    fn breakpoints() {
+        assert_eq!(parse_breakpoint("12"), Ok(Breakpoint::Address(12)));
+        assert_eq!(
+            parse_breakpoint("out"),
+            Ok(Breakpoint::Opcode(Opcode::Output))
+        );
+        assert!(parse_breakpoint("foo").is_err());
+
+        let mut debugger = debugger();
+        run(&mut debugger, &["b 4", "b HLT", "i 5 0", "c"]);
+        assert_eq!(debugger.computer.ip(), 4);
+        run(&mut debugger, &["d 4", "c"]);
+        assert_eq!(debugger.computer.ip(), 7);
+        assert!(debugger.command("d 4").is_err());
+    }
+
+    #[test]
+    fn stepping() {
+        let mut debugger = debugger();
+        run(&mut debugger, &["i 5", "s 2", "x 9 1"]);
+        assert_eq!(debugger.computer.ip(), 4);
+        run(&mut debugger, &["bs", "bs 5"]);
+        assert_eq!(debugger.computer.ip(), 0);
+        assert_eq!(debugger.computer.pending_inputs(), &[5]);
+        run(&mut debugger, &["u 4"]);
+        assert_eq!(debugger.computer.ip(), 4);
+        run(&mut debugger, &["lw 9"]);
+        assert_eq!(debugger.computer.ip(), 0);
+    }
+
+    #[test]
+    fn watchpoints() {
+        let mut debugger = debugger();
+        run(&mut debugger, &["w 9 w 7", "i 3 7 0", "c"]);
+        assert_eq!(debugger.computer.ip(), 2);
+        assert_eq!(debugger.computer.read_raw(9).unwrap(), 7);
+        run(&mut debugger, &["uw 9", "c"]);
+        assert!(debugger.computer.is_halted());
+        assert!(debugger.command("uw 9").is_err());
+        assert!(debugger.command("w 9 x").is_err());
+    }
+
+    #[test]
+    fn memory_and_input() {
+        let mut debugger = debugger();
+        run(&mut debugger, &["p 9 42", "t hi"]);
+        assert_eq!(debugger.computer.read_raw(9).unwrap(), 42);
+        assert_eq!(debugger.computer.pending_inputs(), &[104, 105, 10]);
+        assert!(debugger.command("p -1 0").is_err());
+        // ranges reaching past the addresses
+        run(&mut debugger, &["x 9223372036854775800 16"]);
+        run(
+            &mut debugger,
+            &["l -9223372036854775808", "l 9223372036854775807 8"],
+        );
+        assert!(debugger.command("i x").is_err());
+    }
+
+    #[test]
+    fn errors() {
+        let mut debugger = debugger();
+        assert_eq!(debugger.command(""), Ok(true));
+        assert_eq!(debugger.command("q"), Ok(false));
+        assert!(debugger.command("frobnicate").is_err());
+        assert!(debugger.command("b").is_err());
+        assert!(debugger.command("s x").is_err());
+        assert!(debugger.command("prof report").is_err());
+    }
+}
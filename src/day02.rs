@@ -1,4 +1,4 @@
-use crate::util::computer::{self, Computer};
+use crate::util::computer::{self, Computer, Engine, ErrorKind};
 
 #[aoc_generator(day02)]
 pub fn day02_gen(input: &str) -> Vec<i64> {
@@ -20,19 +20,74 @@ pub fn day02_part1(input: &[i64]) -> i64 {
     run_params(&mut Computer::new(input, None), NOUN, VERB).unwrap()
 }
 
+/// Whether `err` is a crash a wrong noun/verb pair may well cause: the program then
+/// runs into garbage, while other errors are worth reporting.
+fn is_crash(err: &computer::Error) -> bool {
+    matches!(
+        err.kind(),
+        ErrorKind::IllegalOpcode(_)
+            | ErrorKind::InvalidParareterMode(_)
+            | ErrorKind::InvalidInstruction(_)
+            | ErrorKind::InvalidRead(_)
+            | ErrorKind::InvalidWrite(..)
+            | ErrorKind::InvalidAddress(_)
+    )
+}
+
+fn find_params(code: &[i64], output: i64) -> computer::Result<Option<i64>> {
+    const BUDGET: u64 = 100_000;
+
+    for noun in 0..100 {
+        for verb in 0..100 {
+            let mut computer = Computer::new(code, None);
+            computer.set_budget(Some(BUDGET));
+            computer.set_engine(Engine::Cached);
+            match run_params(&mut computer, noun, verb) {
+                Ok(out) if out == output => return Ok(Some(noun * 100 + verb)),
+                Err(err) if !is_crash(&err) => return Err(err),
+                _ => {}
+            }
+        }
+    }
+    Ok(None)
+}
+
 #[aoc(day02, part2)]
 pub fn day02_part2(input: &[i64]) -> i64 {
     const OUTPUT: i64 = 19_690_720;
 
-    (0..100)
-        .find_map(|noun| {
-            (0..100).find_map(|verb| {
-                if run_params(&mut Computer::new(input, None), noun, verb).unwrap() == OUTPUT {
-                    Some(noun * 100 + verb)
-                } else {
-                    None
-                }
-            })
-        })
-        .unwrap()
+    find_params(input, OUTPUT).unwrap().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn params() {
+        let code = [1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50];
+        assert_eq!(
+            run_params(&mut Computer::new(&code, None), 9, 10).unwrap(),
+            3500
+        );
+    }
+
+    #[test]
+    fn crashes_are_skipped() {
+        // adds the noun and the verb into the opcode of the next instruction: 0 is
+        // illegal, 1 doubles 1101, 2 squares it and 3 blocks on input
+        let code = [1101, 0, 0, 4, 0, 0, 0, 0, 99];
+        assert_eq!(find_params(&code, 2202).unwrap(), Some(1));
+        assert_eq!(find_params(&code, 1_212_201).unwrap(), Some(2));
+        let err = find_params(&code, 1101).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::NoInput));
+    }
+
+    #[test]
+    fn budget() {
+        // loops forever after the addition
+        let code = [1101, 0, 0, 3, 1105, 1, 4];
+        let err = find_params(&code, 0).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::BudgetExhausted));
+    }
 }
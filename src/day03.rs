@@ -81,9 +81,9 @@ impl Line {
             Some((
                 i.0,
                 i.1,
-                (i.0 - self.from_x).abs() as u32
+                (i.0 - self.from_x).unsigned_abs()
                     + l1.steps
-                    + (i.1 - other.from_y).abs() as u32
+                    + (i.1 - other.from_y).unsigned_abs()
                     + l2.steps,
             ))
         } else if (l2.from_x <= l1.to_x && l2.to_x >= l1.to_x)
@@ -93,9 +93,9 @@ impl Line {
             Some((
                 i.0,
                 i.1,
-                (i.0 - self.from_y).abs() as u32
+                (i.0 - self.from_y).unsigned_abs()
                     + l1.steps
-                    + (i.1 - other.from_x).abs() as u32
+                    + (i.1 - other.from_x).unsigned_abs()
                     + l2.steps,
             ))
         } else {
@@ -110,10 +110,10 @@ fn to_lines(path: &WirePath) -> Vec<Line> {
         .iter()
         .fold((0i32, 0i32, 0u32), |(x, y, steps), (direction, value)| {
             let to = match direction {
-                Direction::Up => (x, y + *value, steps + (value.abs() as u32)),
-                Direction::Down => (x, y - *value, steps + (value.abs() as u32)),
-                Direction::Right => (x + *value, y, steps + (value.abs() as u32)),
-                Direction::Left => (x - *value, y, steps + (value.abs() as u32)),
+                Direction::Up => (x, y + *value, steps + value.unsigned_abs()),
+                Direction::Down => (x, y - *value, steps + value.unsigned_abs()),
+                Direction::Right => (x + *value, y, steps + value.unsigned_abs()),
+                Direction::Left => (x - *value, y, steps + value.unsigned_abs()),
             };
             lines.push(Line::new(steps, x, y, to.0, to.1));
             to
@@ -131,7 +131,7 @@ pub fn day03_part1(input: &(WirePath, WirePath)) -> u32 {
         .map(|(dx, dy, _)| (dx.abs() + dy.abs()) as u32)
         .filter(|&d| d != 0)
         .sorted()
-        .nth(0)
+        .next()
         .expect("intersection not found")
 }
 
@@ -145,6 +145,6 @@ pub fn day03_part2(input: &(WirePath, WirePath)) -> u32 {
         .map(|(_, _, steps)| steps)
         .filter(|&d| d != 0)
         .sorted()
-        .nth(0)
+        .next()
         .expect("intersection not found")
 }
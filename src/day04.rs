@@ -32,14 +32,10 @@ impl Iterator for Digits {
     type Item = u32;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.divisor == 0 {
-            None
-        } else {
-            let v = Some(self.n / self.divisor);
-            self.n %= self.divisor;
-            self.divisor /= 10;
-            v
-        }
+        let v = self.n.checked_div(self.divisor)?;
+        self.n %= self.divisor;
+        self.divisor /= 10;
+        Some(v)
     }
 }
 
@@ -1,4 +1,4 @@
-use crate::util::computer::{self, Computer};
+use crate::util::computer::{self, Computer, Engine};
 use itertools::Itertools;
 
 #[aoc_generator(day07)]
@@ -6,9 +6,16 @@ pub fn day07_gen(input: &str) -> Vec<i64> {
     input.split(',').map(|n| n.parse().unwrap()).collect()
 }
 
+#[inline]
+fn amplifier(code: &[i64]) -> Computer {
+    let mut computer = Computer::new(code, None);
+    computer.set_engine(Engine::Cached);
+    computer
+}
+
 #[inline]
 fn run_amplifier(code: &[i64], phase_setting: i64, input_signal: i64) -> computer::Result<i64> {
-    Computer::new(code, None).resume_get([phase_setting, input_signal].iter().copied())
+    amplifier(code).resume_get([phase_setting, input_signal].iter().copied())
 }
 
 fn get_signal(code: &[i64], inputs: &[i64]) -> computer::Result<i64> {
@@ -30,22 +37,24 @@ pub fn day07_part1(input: &[i64]) -> i64 {
 }
 
 fn get_signal_feedback(code: &[i64], inputs: &[i64]) -> computer::Result<i64> {
-    let mut computers: Vec<Computer> = Vec::new();
+    let mut computers: Vec<Computer> = inputs
+        .iter()
+        .map(|&phase_setting| {
+            let mut computer = amplifier(code);
+            computer.push_input(phase_setting);
+            computer
+        })
+        .collect();
+
+    // every amplifier must answer the first signal, halting or blocking is an error
     let mut signal = 0;
+    for computer in &mut computers {
+        signal = computer.resume_get(Some(signal))?;
+    }
     for i in (0..inputs.len()).cycle() {
-        match computers.get_mut(i) {
-            Some(computer) => match computer.resume(Some(signal))? {
-                Some(s) => signal = s,
-                None => return Ok(signal),
-            },
-            None => {
-                let mut computer = Computer::new(code, None);
-                match computer.resume([inputs[i], signal].iter().copied())? {
-                    Some(s) => signal = s,
-                    None => return Ok(signal),
-                }
-                computers.push(computer)
-            }
+        match computers[i].resume(Some(signal))? {
+            Some(s) => signal = s,
+            None => return Ok(signal),
         }
     }
     unreachable!()
@@ -60,3 +69,41 @@ pub fn day07_part2(input: &[i64]) -> i64 {
         .last()
         .unwrap()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::computer::ErrorKind;
+
+    #[test]
+    fn signal() {
+        let code = [
+            3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
+        ];
+        assert_eq!(get_signal(&code, &[4, 3, 2, 1, 0]).unwrap(), 43210);
+        assert_eq!(day07_part1(&code), 43210);
+    }
+
+    #[test]
+    fn signal_feedback() {
+        let code = [
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5,
+        ];
+        assert_eq!(
+            get_signal_feedback(&code, &[9, 8, 7, 6, 5]).unwrap(),
+            139_629_729
+        );
+        assert_eq!(day07_part2(&code), 139_629_729);
+    }
+
+    #[test]
+    fn signal_feedback_errors() {
+        // halts after reading the phase setting
+        let err = get_signal_feedback(&[3, 0, 99], &[5, 6]).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::NoOutput));
+        // reads the phase setting and the signal, then blocks
+        let err = get_signal_feedback(&[3, 0, 3, 0, 3, 0, 99], &[5, 6]).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::NoInput));
+    }
+}
@@ -35,9 +35,9 @@ pub fn day08_part1(input: &str) -> usize {
         .into_iter()
         .fold(
             Numbers {
-                zeros: usize::max_value(),
-                ones: usize::max_value(),
-                twos: usize::max_value(),
+                zeros: usize::MAX,
+                ones: usize::MAX,
+                twos: usize::MAX,
             },
             |numbers, layer| {
                 let n = count(layer);
@@ -1,47 +1,59 @@
-use crate::util::computer::Computer;
+use crate::util::computer::{Computer, InputSource, OutputSink};
 
 use itertools::Itertools;
-use std::collections::BTreeMap;
-
-const MEMORY_SIZE: usize = 2048;
+use std::{collections::BTreeMap, io};
 
+/// The painting robot, wired to its Intcode brain as an I/O device.
 #[derive(Debug)]
 struct Robot {
-    brain: Computer,
+    hull: Hull,
     facing: Facing,
     pos: (i64, i64),
+    /// Whether the next output of the brain is a turn rather than a color
+    turning: bool,
 }
 
 impl Robot {
-    #[inline]
-    fn new(code: &[i64]) -> Robot {
-        Robot {
-            brain: Computer::new(code, Some(MEMORY_SIZE)),
+    /// Lets the robot driven by `code` paint `hull` until its program halts.
+    fn paint(code: &[i64], hull: Hull) -> Hull {
+        let mut robot = Robot {
+            hull,
             facing: Facing::Up,
             pos: (0, 0),
-        }
+            turning: false,
+        };
+        Computer::new(code, None).run_device(&mut robot).unwrap();
+        robot.hull
+    }
+}
+
+impl InputSource for Robot {
+    #[inline]
+    fn next_input(&mut self) -> io::Result<Option<i64>> {
+        Ok(Some(self.hull.panel_mut(self.pos).color as i64))
     }
+}
 
-    fn next(&mut self, panel: &mut Panel) -> bool {
-        //println!("{:?}", self.pos);
-        match self.brain.resume(Some(panel.color as i64)).unwrap() {
-            Some(new_color) => {
-                panel.color = match new_color {
-                    0 => Color::Black,
-                    1 => Color::White,
-                    c => panic!("Invalid color: {}", c),
-                };
-                panel.painted = true;
-                match self.brain.resume_get(None).unwrap() {
-                    0 => self.facing = self.facing.left(),
-                    1 => self.facing = self.facing.right(),
-                    t => panic!("Invalid turn: {}", t),
-                }
-                self.pos = self.facing.forward(self.pos);
-                true
+impl OutputSink for Robot {
+    fn output(&mut self, value: i64) -> io::Result<()> {
+        if self.turning {
+            match value {
+                0 => self.facing = self.facing.left(),
+                1 => self.facing = self.facing.right(),
+                t => panic!("Invalid turn: {}", t),
             }
-            None => false,
+            self.pos = self.facing.forward(self.pos);
+        } else {
+            let panel = self.hull.panel_mut(self.pos);
+            panel.color = match value {
+                0 => Color::Black,
+                1 => Color::White,
+                c => panic!("Invalid color: {}", c),
+            };
+            panel.painted = true;
         }
+        self.turning = !self.turning;
+        Ok(())
     }
 }
 
@@ -124,7 +136,7 @@ impl Hull {
     fn panel_mut(&mut self, (x, y): (i64, i64)) -> &mut Panel {
         self.panels
             .entry(y)
-            .or_insert_with(BTreeMap::new)
+            .or_default()
             .entry(x)
             .or_insert_with(Panel::black)
     }
@@ -137,9 +149,7 @@ pub fn day10_gen(input: &str) -> Vec<i64> {
 
 #[aoc(day11, part1)]
 pub fn day11_part1(input: &[i64]) -> usize {
-    let mut hull = Hull::new();
-    let mut robot = Robot::new(input);
-    while robot.next(hull.panel_mut(robot.pos)) {}
+    let hull = Robot::paint(input, Hull::new());
     hull.panels
         .values()
         .map(|panels| panels.values().filter(|panel| panel.painted).count())
@@ -149,9 +159,8 @@ pub fn day11_part1(input: &[i64]) -> usize {
 #[aoc(day11, part2)]
 pub fn day11_part2(input: &[i64]) -> String {
     let mut hull = Hull::new();
-    let mut robot = Robot::new(input);
     hull.panel_mut((0, 0)).color = Color::White;
-    while robot.next(hull.panel_mut(robot.pos)) {}
+    let hull = Robot::paint(input, hull);
     let x_min = hull
         .panels
         .values()
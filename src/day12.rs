@@ -22,13 +22,13 @@ pub fn day12_gen(input: &str) -> (Vec<i32>, Vec<i32>, Vec<i32>) {
     (vx, vy, vz)
 }
 
-fn step_axis(positions: &mut Vec<i32>, velocities: &mut Vec<i32>) {
+fn step_axis(positions: &mut [i32], velocities: &mut [i32]) {
     positions
         .iter()
         .zip(velocities.iter_mut())
         .for_each(|(p1, vel)| {
             positions.iter().for_each(|p2| {
-                *vel += match p1.cmp(&p2) {
+                *vel += match p1.cmp(p2) {
                     Ordering::Less => 1,
                     Ordering::Equal => 0,
                     Ordering::Greater => -1,
@@ -1,4 +1,4 @@
-use crate::util::computer::{Computer, ResumeIter};
+use crate::util::computer::{Computer, Stopped};
 
 use itertools::Itertools;
 use std::io::{self, prelude::*};
@@ -25,7 +25,6 @@ impl From<u8> for Tile {
     }
 }
 
-const MEMORY_SIZE: usize = 4096;
 const GRID_WIDTH: usize = 46;
 const GRID_HEIGHT: usize = 26;
 const GRID_SIZE: usize = GRID_WIDTH * GRID_HEIGHT;
@@ -37,18 +36,15 @@ pub fn day13_gen(input: &str) -> Vec<i64> {
 
 #[aoc(day13, part1)]
 pub fn day13_part1(code: &[i64]) -> usize {
-    let grid: Vec<Tile> = Computer::new(code, Some(MEMORY_SIZE))
-        .resume_iter(None.into_iter())
-        .map(|v| v.unwrap())
-        .chunks(3)
-        .into_iter()
-        .fold(vec![Tile::Empty; GRID_SIZE], |mut grid, mut data| {
-            let x = data.next().unwrap() as usize;
-            let y = data.next().unwrap() as usize;
-            let tile = Tile::from(data.next().unwrap() as u8);
-            grid[x + y * GRID_WIDTH] = tile;
+    let mut game = Computer::new(code, None);
+    game.run_buffered().unwrap();
+    let grid: Vec<Tile> = game.drain_outputs().tuples().fold(
+        vec![Tile::Empty; GRID_SIZE],
+        |mut grid, (x, y, tile)| {
+            grid[x as usize + y as usize * GRID_WIDTH] = Tile::from(tile as u8);
             grid
-        });
+        },
+    );
     grid.iter().fold(0, |count, &tile| {
         if tile == Tile::Block {
             count + 1
@@ -82,53 +78,50 @@ fn print_grid(grid: &[Tile]) {
 
 #[aoc(day13, part2)]
 pub fn day13_part2(code: &[i64]) -> usize {
-    let mut game = Computer::new(code, Some(MEMORY_SIZE));
-    let mut input: std::option::IntoIter<i64> = None.into_iter();
+    let mut game = Computer::new(code, None);
     let mut score = 0;
     let mut grid = vec![Tile::Empty; GRID_SIZE];
     let mut ball_pos = (-1, -1);
 
     game.write_raw(0, 2).unwrap();
     loop {
-        if input.len() == 0 {
-            let buf = &mut [0, 0, 0];
-
-            print!("score: {}\n(l/n/r): ", score);
-            io::stdout().flush().unwrap();
-            io::stdin().read_exact(buf).unwrap();
-            input = Some(match buf[0] {
-                b'l' => -1,
-                b'n' => 0,
-                b'r' => 1,
-                i => panic!("unexpected input: {}", i),
-            })
-            .into_iter();
-        }
-        match game.resume(&mut input).unwrap() {
-            Some(o1) => {
-                let o2 = game.resume_get(None.into_iter()).unwrap();
-                let o3 = game.resume_get(None.into_iter()).unwrap();
-
-                if o1 == -1 && o2 == 0 {
-                    println!("score!");
-                    score = o3 as usize;
-                } else {
-                    let tile = Tile::from(o3 as u8);
-                    print!("store: ({}, {}) = {:?}, ", o1, o2, tile);
-                    if ball_pos != (o1, o2) || tile != Tile::Empty {
-                        if tile == Tile::Ball || tile == Tile::Paddle {
-                            print_grid(&grid);
-                            std::thread::sleep(std::time::Duration::from_millis(100));
-                        }
-                        grid[o1 as usize + o2 as usize * GRID_WIDTH] = Tile::from(o3 as u8);
-                    }
-                    match tile {
-                        Tile::Ball => ball_pos = (o1, o2),
-                        _ => ball_pos = (-1, -1),
+        let state = game
+            .run_buffered()
+            .unwrap_or_else(|err| panic!("{}", err.report()));
+        for (o1, o2, o3) in game.drain_outputs().tuples() {
+            if o1 == -1 && o2 == 0 {
+                println!("score!");
+                score = o3 as usize;
+            } else {
+                let tile = Tile::from(o3 as u8);
+                print!("store: ({}, {}) = {:?}, ", o1, o2, tile);
+                if ball_pos != (o1, o2) || tile != Tile::Empty {
+                    if tile == Tile::Ball || tile == Tile::Paddle {
+                        print_grid(&grid);
+                        std::thread::sleep(std::time::Duration::from_millis(100));
                     }
+                    grid[o1 as usize + o2 as usize * GRID_WIDTH] = tile;
+                }
+                match tile {
+                    Tile::Ball => ball_pos = (o1, o2),
+                    _ => ball_pos = (-1, -1),
                 }
             }
-            None => break score,
         }
+        if state == Stopped::Halted {
+            break score;
+        }
+
+        let buf = &mut [0, 0, 0];
+
+        print!("score: {}\n(l/n/r): ", score);
+        io::stdout().flush().unwrap();
+        io::stdin().read_exact(buf).unwrap();
+        game.push_input(match buf[0] {
+            b'l' => -1,
+            b'n' => 0,
+            b'r' => 1,
+            i => panic!("unexpected input: {}", i),
+        });
     }
 }
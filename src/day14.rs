@@ -30,7 +30,7 @@ fn parse_reactions<'a>(input: &'a str) -> HashMap<&'a str, Reaction<'a>> {
                 .next()
                 .unwrap()
                 .split(',')
-                .map(|input| parse_chemical(input))
+                .map(parse_chemical)
                 .collect();
             let output = parse_chemical(split.next().unwrap());
             (
@@ -1,6 +1,9 @@
-use crate::util::computer::Computer;
+use crate::util::computer::{Computer, Stopped};
 
-use std::{collections::BTreeMap, slice::Iter};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    slice::Iter,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 enum Tile {
@@ -39,16 +42,6 @@ impl Direction {
         }
     }
 
-    #[inline]
-    fn opposite(self) -> Direction {
-        match self {
-            Direction::North => Direction::South,
-            Direction::South => Direction::North,
-            Direction::West => Direction::East,
-            Direction::East => Direction::West,
-        }
-    }
-
     #[inline]
     fn iter() -> Iter<'static, Direction> {
         const DIRECTIONS: [Direction; 4] = [
@@ -65,8 +58,8 @@ fn map_to_string(map: &mut BTreeMap<(i64, i64), Tile>, pos: (i64, i64)) -> Strin
     let min_x = map
         .keys()
         .map(|&(_, x)| x)
-        .fold(i64::max_value(), |min, x| if x < min { x } else { min });
-    let mut last_y = i64::min_value();
+        .fold(i64::MAX, |min, x| if x < min { x } else { min });
+    let mut last_y = i64::MIN;
     let mut last_x = min_x;
     let mut res = String::new();
 
@@ -98,39 +91,42 @@ fn map_to_string(map: &mut BTreeMap<(i64, i64), Tile>, pos: (i64, i64)) -> Strin
     res
 }
 
-fn explore(
-    program: &mut Computer,
-    map: &mut BTreeMap<(i64, i64), Tile>,
-    pos: (i64, i64),
-) -> Option<usize> {
-    let mut steps: Option<usize> = None;
+fn move_droid(program: &mut Computer, direction: Direction) -> Tile {
+    match program.run(Some(direction as i64)).unwrap() {
+        Stopped::Output(status) => Tile::from(status),
+        state => panic!("droid did not report its status: {:?}", state),
+    }
+}
 
-    for &direction in Direction::iter() {
-        let to = direction.offset(pos);
+/// Maps the whole area breadth-first, forking the droid program at each move.
+///
+/// Returns the length of the shortest route to the oxygen system.
+fn explore(code: &[i64], map: &mut BTreeMap<(i64, i64), Tile>) -> Option<usize> {
+    let mut route_length = None;
+    let mut queue = VecDeque::new();
 
-        if map.get(&(to.1, to.0)).is_none() {
-            let tile = Tile::from(program.resume_get(Some(direction as i64)).unwrap());
+    map.insert((0, 0), Tile::Empty);
+    queue.push_back((Computer::new(code, None), (0, 0), 0));
+    while let Some((droid, pos, steps)) = queue.pop_front() {
+        for &direction in Direction::iter() {
+            let to = direction.offset(pos);
+
+            if map.contains_key(&(to.1, to.0)) {
+                continue;
+            }
+            let mut forked = droid.clone();
+            let tile = move_droid(&mut forked, direction);
 
             map.insert((to.1, to.0), tile);
-            if tile == Tile::Oxygen {
-                steps = Some(0);
+            if tile == Tile::Oxygen && route_length.is_none() {
+                route_length = Some(steps + 1);
             }
-            if tile == Tile::Empty || tile == Tile::Oxygen {
-                if let Some(nsteps) = explore(program, map, to) {
-                    steps = match steps {
-                        Some(s) if nsteps < s => Some(nsteps),
-                        None => Some(nsteps),
-                        _ => steps,
-                    };
-                }
-                // move back to original pos
-                program
-                    .resume_get(Some(direction.opposite() as i64))
-                    .unwrap();
+            if tile != Tile::Wall {
+                queue.push_back((forked, to, steps + 1));
             }
         }
     }
-    steps.map(|s| s + 1)
+    route_length
 }
 
 fn spread_oxygen(map: &mut BTreeMap<(i64, i64), Tile>) -> usize {
@@ -168,14 +164,10 @@ pub fn day15_gen(input: &str) -> Vec<i64> {
     input.split(',').map(|n| n.parse().unwrap()).collect()
 }
 
-const MEMORY_SIZE: usize = 4096;
-
 #[aoc(day15, part1)]
 pub fn day15_part1(code: &[i64]) -> Option<usize> {
-    let mut program = Computer::new(code, Some(MEMORY_SIZE));
     let mut map = BTreeMap::new();
-    map.insert((0, 0), Tile::Empty);
-    let route_length = explore(&mut program, &mut map, (0, 0));
+    let route_length = explore(code, &mut map);
 
     println!("{}", map_to_string(&mut map, (0, 0)));
     route_length
@@ -183,10 +175,9 @@ pub fn day15_part1(code: &[i64]) -> Option<usize> {
 
 #[aoc(day15, part2)]
 pub fn day15_part2(code: &[i64]) -> usize {
-    let mut program = Computer::new(code, Some(MEMORY_SIZE));
     let mut map = BTreeMap::new();
 
-    explore(&mut program, &mut map, (0, 0));
+    explore(code, &mut map);
     let minutes = fill_with_oxygen(&mut map);
 
     println!("{}", map_to_string(&mut map, (0, 0)));
@@ -139,8 +139,7 @@ pub fn day16_part2(input: &[i64]) -> String {
     let offset = input.iter().take(7).fold(0, |o, &n| o * 10 + n as usize);
 
     // calculate new input
-    let mut nums = std::iter::repeat(input)
-        .take(10000)
+    let mut nums = std::iter::repeat_n(input, 10000)
         .flatten()
         .skip(offset)
         .copied()
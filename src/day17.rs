@@ -1,7 +1,5 @@
 use crate::util::computer::Computer;
 
-const MEMORY_SIZE: usize = 4096;
-
 #[aoc_generator(day17)]
 pub fn day17_gen(input: &str) -> Vec<i64> {
     input.split(',').map(|n| n.parse().unwrap()).collect()
@@ -11,8 +9,8 @@ pub fn day17_gen(input: &str) -> Vec<i64> {
 pub fn day17_part1(code: &[i64]) -> usize {
     let mut width = 0;
     let mut first_line = true;
-    let map: Vec<_> = Computer::new(code, Some(MEMORY_SIZE))
-        .resume_iter(None.into_iter())
+    let map: Vec<_> = Computer::new(code, None)
+        .resume_iter(None)
         .map(|v| {
             let c = v.unwrap() as u8;
             if first_line {
@@ -53,25 +51,13 @@ pub fn day17_part2(code: &[i64]) -> i64 {
     const C: &str = "R,12,L,10,L,10\n";
     const FEED: &str = "n\n";
 
-    let mut computer = Computer::new(code, Some(MEMORY_SIZE));
+    let mut computer = Computer::new(code, None);
     computer.write_raw(0, 2).unwrap();
-    computer
-        .resume_iter(
-            MAIN.bytes()
-                .chain(A.bytes())
-                .chain(B.bytes())
-                .chain(C.bytes())
-                .chain(FEED.bytes())
-                .map(|b| b as i64),
-        )
-        .find_map(|o| {
-            let o = o.unwrap();
-            if o >= 0 && o <= 127 {
-                //print!("{}", o as u8 as char);
-                None
-            } else {
-                Some(o)
-            }
-        })
-        .unwrap()
+    for routine in &[MAIN, A, B, C, FEED] {
+        computer.push_str(routine);
+    }
+    computer.run_buffered().unwrap();
+    let mut outputs = computer.drain_outputs();
+    // everything but the dust count is ASCII video feed
+    outputs.find(|o| !(0..=127).contains(o)).unwrap()
 }
@@ -0,0 +1,822 @@
+//! Ahead-of-time translation of Intcode programs to Rust source.
+//!
+//! [`translate`] turns a program image into a Rust module with one function per basic
+//! block. The module exposes a `new` function returning a [`Translated`] machine, which
+//! has the run, resume and I/O methods of [`Computer`] and runs the translated blocks
+//! instead of decoding instructions.
+//!
+//! The state of a translated machine lives in an embedded [`Computer`], which takes over
+//! whenever native code cannot run:
+//!
+//! - the instruction pointer is not the start of a translated block, for instance after
+//!   an indirect jump into the middle of one,
+//! - the program overwrote the code of the block with different values,
+//! - an instruction fails, so that the error is the one the interpreter reports.
+//!
+//! Both kinds of runs can be compared with [`Translated::check`].
+
+use super::{
+    cfg::{self, Discovery},
+    disasm::Line,
+    Computer, Mode, Opcode, Result, Stopped,
+};
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+};
+
+/// Outcome of a translated block: `Ok` with the reason to stop, if any, or `Err` with the
+/// address of an instruction the interpreter must run.
+pub type Exit = std::result::Result<Option<Stopped>, i64>;
+
+/// Runs the translated block starting at the instruction pointer, `None` if there is no
+/// runnable block there.
+pub type Dispatch = fn(&mut Runtime) -> Option<Exit>;
+
+/// State of a translated machine, as seen by the translated code.
+#[derive(Debug, Clone)]
+pub struct Runtime {
+    computer: Computer,
+    image: &'static [i64],
+    /// Block owning each cell of the image, plus one, zero for none
+    owners: &'static [u32],
+    /// Cells of each block
+    ranges: Vec<(usize, usize)>,
+    /// Blocks whose code was overwritten
+    dirty: Vec<bool>,
+    /// Number of instructions run by the interpreter, whose writes `store` does not see
+    epoch: u64,
+    /// Value of `epoch` when the code of each block was last compared with the image
+    verified: Vec<u64>,
+}
+
+impl Runtime {
+    #[inline]
+    pub fn ip(&self) -> i64 {
+        self.computer.ip
+    }
+
+    #[inline]
+    pub fn set_ip(&mut self, ip: i64) {
+        self.computer.ip = ip;
+    }
+
+    #[inline]
+    pub fn rbo(&self) -> i64 {
+        self.computer.rbo
+    }
+
+    #[inline]
+    pub fn set_rbo(&mut self, rbo: i64) {
+        self.computer.rbo = rbo;
+    }
+
+    /// Address of a relative mode parameter, `None` on overflow.
+    #[inline]
+    pub fn relative(&self, offset: i64) -> Option<i64> {
+        self.computer.rbo.checked_add(offset)
+    }
+
+    /// Reads a cell, `None` if the address is invalid.
+    #[inline]
+    pub fn load(&self, addr: i64) -> Option<i64> {
+        if addr < 0 {
+            return None;
+        }
+        self.computer.mem.get(addr as usize)
+    }
+
+    #[inline]
+    pub fn writable(&self, addr: i64) -> bool {
+        addr >= 0 && self.computer.mem.in_bounds(addr as usize)
+    }
+
+    /// Writes a cell, `None` if the address is invalid.
+    ///
+    /// Returns `Some(true)` if the write changed the code of a translated block, which
+    /// must then stop running.
+    #[inline]
+    pub fn store(&mut self, addr: i64, value: i64) -> Option<bool> {
+        if !self.writable(addr) {
+            return None;
+        }
+        let index = addr as usize;
+        self.computer.mem.set(index, value);
+        self.computer.cache.invalidate(addr);
+        match self.owners.get(index) {
+            Some(&owner) if owner != 0 && value != self.image[index] => {
+                let dirty = &mut self.dirty[owner as usize - 1];
+                let changed = !*dirty;
+                *dirty = true;
+                Some(changed)
+            }
+            _ => Some(false),
+        }
+    }
+
+    /// Takes the next input value.
+    #[inline]
+    pub fn input(&mut self) -> Option<i64> {
+        self.computer.inputs.pop_front()
+    }
+
+    #[inline]
+    pub fn halt(&mut self) {
+        self.computer.halted = true;
+    }
+
+    /// Whether the code of `block` is unchanged.
+    #[inline]
+    pub fn is_clean(&mut self, block: usize) -> bool {
+        if !self.dirty[block] && self.verified[block] != self.epoch {
+            self.verify(block);
+        }
+        !self.dirty[block]
+    }
+
+    /// Compares the code of `block` with the image, after the interpreter ran.
+    fn verify(&mut self, block: usize) {
+        let (start, end) = self.ranges[block];
+        let mem = &self.computer.mem;
+        self.dirty[block] = (start..end).any(|i| mem.get(i) != Some(self.image[i]));
+        self.verified[block] = self.epoch;
+    }
+}
+
+/// A machine running a translated program, see the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct Translated {
+    rt: Runtime,
+    dispatch: Dispatch,
+}
+
+impl Translated {
+    /// Loads `image` in a fresh machine, to run with the blocks of `dispatch`.
+    ///
+    /// Called by translated modules, `owners` maps each cell of the image to the block
+    /// owning it plus one, zero for none.
+    pub fn new(image: &'static [i64], owners: &'static [u32], dispatch: Dispatch) -> Translated {
+        let blocks = owners.iter().copied().max().unwrap_or(0) as usize;
+        let mut ranges = vec![(usize::MAX, 0); blocks];
+        for (i, &owner) in owners.iter().enumerate().filter(|&(_, &owner)| owner != 0) {
+            let range = &mut ranges[owner as usize - 1];
+            *range = (range.0.min(i), i + 1);
+        }
+        Translated {
+            rt: Runtime {
+                computer: Computer::new(image, None),
+                image,
+                owners,
+                ranges,
+                dirty: vec![false; blocks],
+                epoch: 0,
+                verified: vec![0; blocks],
+            },
+            dispatch,
+        }
+    }
+
+    /// The state of the machine.
+    #[inline]
+    pub fn computer(&self) -> &Computer {
+        &self.rt.computer
+    }
+
+    /// Continues running the program with the interpreter.
+    #[inline]
+    pub fn into_computer(self) -> Computer {
+        self.rt.computer
+    }
+
+    #[inline]
+    pub fn ip(&self) -> i64 {
+        self.rt.ip()
+    }
+
+    #[inline]
+    pub fn rbo(&self) -> i64 {
+        self.rt.rbo()
+    }
+
+    #[inline]
+    pub fn is_halted(&self) -> bool {
+        self.rt.computer.halted
+    }
+
+    #[inline]
+    pub fn read_raw(&self, index: i64) -> Result<i64> {
+        self.rt.computer.read_raw(index)
+    }
+
+    pub fn write_raw(&mut self, index: i64, value: i64) -> Result<()> {
+        match self.rt.store(index, value) {
+            Some(_) => Ok(()),
+            None => self.rt.computer.write_raw(index, value),
+        }
+    }
+
+    #[inline]
+    pub fn push_input(&mut self, value: i64) {
+        self.rt.computer.push_input(value);
+    }
+
+    #[inline]
+    pub fn push_inputs<I>(&mut self, values: I)
+    where
+        I: IntoIterator<Item = i64>,
+    {
+        self.rt.computer.push_inputs(values);
+    }
+
+    #[inline]
+    pub fn take_output(&mut self) -> Option<i64> {
+        self.rt.computer.take_output()
+    }
+
+    #[inline]
+    pub fn drain_outputs(&mut self) -> impl Iterator<Item = i64> + '_ {
+        self.rt.computer.drain_outputs()
+    }
+
+    /// Runs the program until it halts, outputs a value or waits for input, like
+    /// [`Computer::run`].
+    pub fn run<I>(&mut self, inputs: I) -> Result<Stopped>
+    where
+        I: IntoIterator<Item = i64>,
+    {
+        self.push_inputs(inputs);
+        if self.is_halted() {
+            return Ok(Stopped::Halted);
+        }
+        loop {
+            let exit = (self.dispatch)(&mut self.rt).unwrap_or_else(|| Err(self.rt.ip()));
+            match exit {
+                Ok(None) => (),
+                Ok(Some(stop)) => return Ok(stop),
+                Err(ip) => {
+                    self.rt.set_ip(ip);
+                    self.rt.epoch += 1;
+                    if let Some(stop) = self.rt.computer.step()? {
+                        return Ok(stop);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Runs the program until it halts or waits for input, storing its outputs in the
+    /// output buffer.
+    pub fn run_buffered(&mut self) -> Result<Stopped> {
+        loop {
+            match self.run(None)? {
+                Stopped::Output(out) => self.rt.computer.outputs.push_back(out),
+                state => return Ok(state),
+            }
+        }
+    }
+
+    /// Like [`Computer::resume`].
+    pub fn resume<I>(&mut self, inputs: I) -> Result<Option<i64>>
+    where
+        I: IntoIterator<Item = i64>,
+    {
+        match self.run(inputs)? {
+            Stopped::Output(out) => Ok(Some(out)),
+            Stopped::Halted => Ok(None),
+            Stopped::NeedsInput => Err(self.rt.computer.error(super::ErrorKind::NoInput)),
+            stop => Err(self.rt.computer.error(super::ErrorKind::Interrupted(stop))),
+        }
+    }
+
+    /// Like [`Computer::resume_get`].
+    pub fn resume_get<I>(&mut self, inputs: I) -> Result<i64>
+    where
+        I: IntoIterator<Item = i64>,
+    {
+        match self.resume(inputs)? {
+            Some(out) => Ok(out),
+            None => Err(self.rt.computer.error(super::ErrorKind::NoOutput)),
+        }
+    }
+
+    /// Runs a copy of the machine and a copy of its embedded [`Computer`] on `inputs`
+    /// until they halt, wait for input or fail, and checks that both runs agree.
+    ///
+    /// Returns the outputs, or a description of the first difference.
+    pub fn check<I>(&self, inputs: I) -> std::result::Result<Vec<i64>, String>
+    where
+        I: IntoIterator<Item = i64>,
+    {
+        let mut translated = self.clone();
+        let mut interpreted = self.rt.computer.clone();
+        translated.push_inputs(inputs);
+        interpreted.push_inputs(translated.rt.computer.inputs.iter().copied());
+
+        let ending = |res: Result<Stopped>| match res {
+            Ok(stop) => format!("{:?}", stop),
+            Err(err) => format!("error: {}", err),
+        };
+        let translated_end = ending(translated.run_buffered());
+        let interpreted_end = ending(interpreted.run_buffered());
+        if translated_end != interpreted_end {
+            return Err(format!(
+                "translated run ended with {}, interpreted run with {}",
+                translated_end, interpreted_end
+            ));
+        }
+        let outputs: Vec<_> = translated.drain_outputs().collect();
+        let expected: Vec<_> = interpreted.drain_outputs().collect();
+        if outputs != expected {
+            return Err(format!(
+                "translated run output {:?}, interpreted run {:?}",
+                outputs, expected
+            ));
+        }
+        if translated.rt.computer != interpreted {
+            return Err(format!(
+                "final states differ: ip {} and {}, rbo {} and {}, memory digest {:x} and {:x}",
+                translated.ip(),
+                interpreted.ip(),
+                translated.rbo(),
+                interpreted.rbo(),
+                translated.rt.computer.mem.digest(),
+                interpreted.mem.digest()
+            ));
+        }
+        Ok(outputs)
+    }
+}
+
+/// Instructions found by [`cfg::discover`], and the addresses starting a translated
+/// block.
+struct Analysis {
+    lines: BTreeMap<usize, Line>,
+    leaders: BTreeSet<usize>,
+}
+
+/// Whether control never falls through to the next block after `opcode`.
+#[inline]
+fn ends_block(opcode: Opcode) -> bool {
+    opcode.is_branch() || opcode == Opcode::Output || opcode == Opcode::Halt
+}
+
+/// Splits the basic blocks of the program further, so that the machine can stop between
+/// blocks. Instructions overlapping a discovered one are left to the interpreter.
+fn analyze(code: &[i64]) -> Analysis {
+    let Discovery {
+        lines, mut leaders, ..
+    } = cfg::discover(code);
+    for (&addr, line) in &lines {
+        let opcode = match line {
+            Line::Instruction { insn, .. } => insn.opcode,
+            Line::Data { .. } => continue,
+        };
+        if opcode == Opcode::Input {
+            // a read may block, the instruction must start a block to resume at it
+            leaders.insert(addr);
+        } else if ends_block(opcode) {
+            leaders.insert(addr + line.size());
+        }
+    }
+    leaders.retain(|addr| lines.contains_key(addr));
+    Analysis { lines, leaders }
+}
+
+/// Rust expression reading a parameter.
+fn read_param(mode: Mode, param: i64, ip: usize) -> String {
+    match mode {
+        Mode::Immediate => param.to_string(),
+        Mode::Position => format!("rt.load({}).ok_or({})?", param, ip),
+        Mode::Relative => format!(
+            "rt.load(rt.relative({}).ok_or({})?).ok_or({})?",
+            param, ip, ip
+        ),
+    }
+}
+
+/// Rust expression of the address written by a destination parameter.
+fn write_param(mode: Mode, param: i64, ip: usize) -> String {
+    match mode {
+        Mode::Immediate | Mode::Position => param.to_string(),
+        Mode::Relative => format!("rt.relative({}).ok_or({})?", param, ip),
+    }
+}
+
+/// Writes the Rust code of one instruction, which ends the block if `last` is set.
+fn translate_instruction(out: &mut String, line: &Line, last: bool) {
+    let (addr, insn, params) = match *line {
+        Line::Instruction { addr, insn, params } => (addr, insn, params),
+        Line::Data { .. } => unreachable!(),
+    };
+    let next = addr + insn.size();
+    let read = |i: usize| read_param(insn.modes[i], params[i], addr);
+    let store = |out: &mut String, value: &str| {
+        let i = insn.opcode.destination().unwrap();
+        let dest = write_param(insn.modes[i], params[i], addr);
+        writeln!(
+            out,
+            "    if rt.store({}, {}).ok_or({})? {{",
+            dest, value, addr
+        )
+        .unwrap();
+        writeln!(out, "        rt.set_ip({});", next).unwrap();
+        writeln!(out, "        return Ok(None);").unwrap();
+        writeln!(out, "    }}").unwrap();
+    };
+
+    writeln!(out, "    // {}", line.to_string().trim_start()).unwrap();
+    match insn.opcode {
+        Opcode::Add | Opcode::Mul => {
+            let op = if insn.opcode == Opcode::Add {
+                "checked_add"
+            } else {
+                "checked_mul"
+            };
+            writeln!(
+                out,
+                "    let v = i64::{}({}, {}).ok_or({})?;",
+                op,
+                read(0),
+                read(1),
+                addr
+            )
+            .unwrap();
+            store(out, "v");
+        }
+        Opcode::LessThan | Opcode::Equals => {
+            let op = if insn.opcode == Opcode::LessThan {
+                "<"
+            } else {
+                "=="
+            };
+            writeln!(out, "    let v = ({} {} {}) as i64;", read(0), op, read(1)).unwrap();
+            store(out, "v");
+        }
+        Opcode::Input => {
+            let dest = write_param(insn.modes[0], params[0], addr);
+            writeln!(out, "    if !rt.writable({}) {{", dest).unwrap();
+            writeln!(out, "        return Err({});", addr).unwrap();
+            writeln!(out, "    }}").unwrap();
+            writeln!(out, "    let v = match rt.input() {{").unwrap();
+            writeln!(out, "        Some(v) => v,").unwrap();
+            writeln!(out, "        None => {{").unwrap();
+            writeln!(out, "            rt.set_ip({});", addr).unwrap();
+            writeln!(out, "            return Ok(Some(Stopped::NeedsInput));").unwrap();
+            writeln!(out, "        }}").unwrap();
+            writeln!(out, "    }};").unwrap();
+            store(out, "v");
+        }
+        Opcode::Output => {
+            writeln!(out, "    let v = {};", read(0)).unwrap();
+            writeln!(out, "    rt.set_ip({});", next).unwrap();
+            writeln!(out, "    Ok(Some(Stopped::Output(v)))").unwrap();
+            return;
+        }
+        Opcode::JumpIfTrue | Opcode::JumpIfFalse => {
+            let test = if insn.opcode == Opcode::JumpIfTrue {
+                "!="
+            } else {
+                "=="
+            };
+            let jump = match insn.modes[0] {
+                Mode::Immediate if (params[0] != 0) == (test == "!=") => None,
+                Mode::Immediate => Some(false),
+                _ => Some(true),
+            };
+            match jump {
+                // always taken
+                None => {
+                    writeln!(out, "    rt.set_ip({});", read(1)).unwrap();
+                    writeln!(out, "    Ok(None)").unwrap();
+                    return;
+                }
+                // never taken
+                Some(false) => (),
+                Some(true) => {
+                    writeln!(out, "    if {} {} 0 {{", read(0), test).unwrap();
+                    writeln!(out, "        rt.set_ip({});", read(1)).unwrap();
+                    writeln!(out, "        return Ok(None);").unwrap();
+                    writeln!(out, "    }}").unwrap();
+                }
+            }
+        }
+        Opcode::AdjustBase => {
+            writeln!(
+                out,
+                "    let rbo = rt.rbo().checked_add({}).ok_or({})?;",
+                read(0),
+                addr
+            )
+            .unwrap();
+            writeln!(out, "    rt.set_rbo(rbo);").unwrap();
+        }
+        Opcode::Halt => {
+            writeln!(out, "    rt.halt();").unwrap();
+            writeln!(out, "    Ok(Some(Stopped::Halted))").unwrap();
+            return;
+        }
+    }
+    if last {
+        writeln!(out, "    rt.set_ip({});", next).unwrap();
+        writeln!(out, "    Ok(None)").unwrap();
+    }
+}
+
+/// Writes `values` as the elements of an array literal, 16 per line.
+fn array<T: ToString>(out: &mut String, values: &[T]) {
+    for chunk in values.chunks(16) {
+        let line: Vec<_> = chunk.iter().map(T::to_string).collect();
+        writeln!(out, "    {},", line.join(", ")).unwrap();
+    }
+}
+
+/// Translates `code` to the source of a Rust module, see the [module
+/// documentation](self).
+///
+/// `krate` is the path of this crate from the generated module: `aoc_2019` from another
+/// crate, `crate` from within this one.
+pub fn translate(code: &[i64], krate: &str) -> String {
+    let Analysis { lines, leaders } = analyze(code);
+
+    // blocks: runs of instructions from a leader to the next leader, a gap or a jump
+    let mut blocks: Vec<Vec<Line>> = Vec::new();
+    for &leader in &leaders {
+        let mut block = Vec::new();
+        let mut addr = leader;
+        while let Some(line @ Line::Instruction { insn, .. }) = lines.get(&addr) {
+            if addr != leader && leaders.contains(&addr) {
+                break;
+            }
+            block.push(line.clone());
+            addr += line.size();
+            if ends_block(insn.opcode) {
+                break;
+            }
+        }
+        blocks.push(block);
+    }
+    let mut owners = vec![0u32; code.len()];
+    for (i, block) in blocks.iter().enumerate() {
+        for line in block {
+            let cells = &mut owners[line.addr()..line.addr() + line.size()];
+            cells.iter_mut().for_each(|owner| *owner = i as u32 + 1);
+        }
+    }
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        "//! Intcode program of {} words translated to Rust: {} instructions in {} blocks.",
+        code.len(),
+        lines.len(),
+        blocks.len()
+    )
+    .unwrap();
+    writeln!(out, "//!\n//! Generated code, do not edit.\n").unwrap();
+    // blocks without I/O or halt do not stop the machine
+    let stops = lines.values().any(|line| match line {
+        Line::Instruction { insn, .. } => {
+            insn.opcode == Opcode::Input
+                || insn.opcode == Opcode::Output
+                || insn.opcode == Opcode::Halt
+        }
+        Line::Data { .. } => false,
+    });
+    if stops {
+        writeln!(
+            out,
+            "use {}::util::computer::{{\n    aot::{{Exit, Runtime, Translated}},\n    Stopped,\n}};\n",
+            krate
+        )
+        .unwrap();
+    } else {
+        writeln!(
+            out,
+            "use {}::util::computer::aot::{{Exit, Runtime, Translated}};\n",
+            krate
+        )
+        .unwrap();
+    }
+    writeln!(out, "/// The program image.").unwrap();
+    writeln!(out, "pub static IMAGE: [i64; {}] = [", code.len()).unwrap();
+    array(&mut out, code);
+    writeln!(out, "];\n").unwrap();
+    writeln!(out, "/// Block owning each cell of the image, plus one.").unwrap();
+    writeln!(out, "static OWNERS: [u32; {}] = [", code.len()).unwrap();
+    array(&mut out, &owners);
+    writeln!(out, "];\n").unwrap();
+    writeln!(out, "/// Loads the program in a fresh machine.").unwrap();
+    writeln!(out, "pub fn new() -> Translated {{").unwrap();
+    writeln!(out, "    Translated::new(&IMAGE, &OWNERS, dispatch)").unwrap();
+    writeln!(out, "}}\n").unwrap();
+
+    writeln!(out, "fn dispatch(rt: &mut Runtime) -> Option<Exit> {{").unwrap();
+    writeln!(out, "    Some(match rt.ip() {{").unwrap();
+    for (i, block) in blocks.iter().enumerate() {
+        let addr = block[0].addr();
+        writeln!(
+            out,
+            "        {} if rt.is_clean({}) => block_{}(rt),",
+            addr, i, addr
+        )
+        .unwrap();
+    }
+    writeln!(out, "        _ => return None,").unwrap();
+    writeln!(out, "    }})").unwrap();
+    writeln!(out, "}}").unwrap();
+
+    for block in &blocks {
+        writeln!(
+            out,
+            "\nfn block_{}(rt: &mut Runtime) -> Exit {{",
+            block[0].addr()
+        )
+        .unwrap();
+        for (i, line) in block.iter().enumerate() {
+            translate_instruction(&mut out, line, i + 1 == block.len());
+        }
+        writeln!(out, "}}").unwrap();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::examples::{self, DAY05, FIB, QUINE, SELF_MODIFYING};
+    use super::super::{asm::assemble, Cfg, ErrorKind};
+    use super::*;
+
+    // modules generated by `translate(code, "crate")`, kept as generated
+    #[rustfmt::skip]
+    mod day05;
+    /// Reads its input `n`, sets the relative base to `n`, outputs the cell at `n`, then
+    /// `n * 2^62` which overflows for `n >= 2`.
+    #[rustfmt::skip]
+    mod errors;
+    #[rustfmt::skip]
+    mod fib;
+    /// Writes `0` to the operand of its output, after an unconditional jump.
+    #[rustfmt::skip]
+    mod patched;
+    #[rustfmt::skip]
+    mod quine;
+    #[rustfmt::skip]
+    mod self_modifying;
+
+    #[test]
+    fn generated() {
+        let fib = assemble(FIB).unwrap();
+        let programs: [(&[i64], &str); 6] = [
+            (DAY05, include_str!("aot/tests/day05.rs")),
+            (&errors::IMAGE, include_str!("aot/tests/errors.rs")),
+            (&fib, include_str!("aot/tests/fib.rs")),
+            (&patched::IMAGE, include_str!("aot/tests/patched.rs")),
+            (QUINE, include_str!("aot/tests/quine.rs")),
+            (SELF_MODIFYING, include_str!("aot/tests/self_modifying.rs")),
+        ];
+        for (code, source) in &programs {
+            // regenerate with `intcode-aot`, replacing `aoc_2019` by `crate`
+            assert_eq!(translate(code, "crate"), *source);
+        }
+    }
+
+    #[test]
+    fn translate_all() {
+        for (name, code) in examples::all() {
+            let source = translate(&code, "aoc_2019");
+            assert!(source.contains("pub fn new() -> Translated"), "{}", name);
+            assert!(source.contains("fn block_0(rt: &mut Runtime)"), "{}", name);
+        }
+    }
+
+    #[test]
+    fn analysis() {
+        for (name, code) in examples::all() {
+            let cfg = Cfg::code(&code);
+            let Analysis { lines, leaders } = analyze(&code);
+            // the translation splits the blocks further at I/O
+            for block in cfg.blocks() {
+                assert!(leaders.contains(&block.start()), "{}", name);
+                for line in &block.lines {
+                    assert_eq!(lines.get(&line.addr()), Some(line), "{}", name);
+                }
+            }
+            assert_eq!(
+                lines.len(),
+                cfg.blocks().map(|block| block.lines.len()).sum(),
+                "{}",
+                name
+            );
+        }
+        // blocks also start at reads and after outputs
+        let Analysis { leaders, .. } = analyze(DAY05);
+        let expected = [0, 9, 16, 22, 28, 31, 33, 36, 42, 46];
+        assert_eq!(leaders.into_iter().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn input() {
+        for (n, out) in [(7, 999), (8, 1000), (9, 1001)] {
+            assert_eq!(day05::new().check(Some(n)), Ok(vec![out]));
+        }
+        assert_eq!(day05::new().check(None), Ok(vec![]));
+
+        let mut machine = day05::new();
+        assert_eq!(machine.run(None).unwrap(), Stopped::NeedsInput);
+        assert_eq!(machine.ip(), 0);
+        assert_eq!(machine.resume_get(Some(8)).unwrap(), 1000);
+        assert_eq!(machine.resume(None).unwrap(), None);
+        assert!(machine.is_halted());
+    }
+
+    #[test]
+    fn relative_base() {
+        let fibs = [0, 1, 1, 2, 3, 5, 8, 13, 21, 34, 55];
+        for (n, &fib_n) in fibs.iter().enumerate() {
+            assert_eq!(fib::new().check(Some(n as i64)), Ok(vec![fib_n]));
+        }
+        assert_eq!(quine::new().check(None), Ok(QUINE.to_vec()));
+    }
+
+    #[test]
+    fn self_modifying() {
+        assert_eq!(self_modifying::new().check(None), Ok(vec![7, 12]));
+
+        let mut machine = self_modifying::new();
+        machine.run_buffered().unwrap();
+        assert_eq!(machine.read_raw(0).unwrap(), 2);
+        // a write to translated code makes its block fall back to the interpreter
+        let mut machine = self_modifying::new();
+        machine.write_raw(1, 25).unwrap();
+        assert_eq!(machine.check(None), Ok(vec![8, 16]));
+        assert_eq!(machine.resume_get(None).unwrap(), 8);
+
+        // so do the writes of the instructions the interpreter runs: the addition of the
+        // patched block writes the operand of the output
+        let mut machine = patched::new();
+        assert_eq!(machine.check(None), Ok(vec![0]));
+        machine.write_raw(2, 7).unwrap();
+        assert_eq!(machine.check(None), Ok(vec![7]));
+        assert_eq!(machine.resume_get(None).unwrap(), 7);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            errors::new().check(Some(1)),
+            Ok(vec![100, 4_611_686_018_427_387_904])
+        );
+        // failing runs agree on the error and the outputs before it
+        assert_eq!(errors::new().check(Some(2)), Ok(vec![9]));
+        assert_eq!(errors::new().check(Some(-1)), Ok(vec![]));
+
+        let mut machine = errors::new();
+        assert_eq!(machine.resume_get(Some(2)).unwrap(), 9);
+        let err = machine.resume(None).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Overflow(2, _)));
+        assert_eq!(err.location(), 6);
+        let err = errors::new().resume(Some(-1)).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::InvalidRead(-1)));
+        assert_eq!(err.location(), 4);
+        assert!(matches!(
+            errors::new().resume(None).unwrap_err().kind(),
+            ErrorKind::NoInput
+        ));
+    }
+
+    #[test]
+    fn disagreements() {
+        // stops without running anything
+        let halt: Dispatch = |rt| {
+            rt.halt();
+            Some(Ok(Some(Stopped::Halted)))
+        };
+        let machine = Translated::new(DAY05, &[0; 47], halt);
+        assert_eq!(
+            machine.check(Some(8)),
+            Err("translated run output [], interpreted run [1000]".to_owned())
+        );
+        let machine = Translated::new(&errors::IMAGE, &[0; 13], halt);
+        assert_eq!(
+            machine.check(Some(2)),
+            Err(
+                "translated run ended with Halted, interpreted run with error: \
+                 IntCode error at 6: arithmetic overflow with operands 2 and 4611686018427387904"
+                    .to_owned()
+            )
+        );
+        // moves the relative base before each interpreted instruction
+        let shift: Dispatch = |rt| {
+            rt.set_rbo(rt.rbo() + 1);
+            None
+        };
+        let machine = Translated::new(DAY05, &[0; 47], shift);
+        let err = machine.check(Some(8)).unwrap_err();
+        assert!(
+            err.starts_with("final states differ: ip 46 and 46, rbo 7 and 0,"),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,153 @@
+//! Intcode program of 47 words translated to Rust: 15 instructions in 10 blocks.
+//!
+//! Generated code, do not edit.
+
+use crate::util::computer::{
+    aot::{Exit, Runtime, Translated},
+    Stopped,
+};
+
+/// The program image.
+pub static IMAGE: [i64; 47] = [
+    3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31,
+    1106, 0, 36, 98, 0, 0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104,
+    999, 1105, 1, 46, 1101, 1000, 1, 20, 4, 20, 1105, 1, 46, 98, 99,
+];
+
+/// Block owning each cell of the image, plus one.
+static OWNERS: [u32; 47] = [
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2,
+    3, 3, 3, 0, 0, 0, 4, 4, 4, 4, 4, 4, 5, 5, 5, 6,
+    6, 7, 7, 7, 8, 8, 8, 8, 8, 8, 9, 9, 9, 0, 10,
+];
+
+/// Loads the program in a fresh machine.
+pub fn new() -> Translated {
+    Translated::new(&IMAGE, &OWNERS, dispatch)
+}
+
+fn dispatch(rt: &mut Runtime) -> Option<Exit> {
+    Some(match rt.ip() {
+        0 if rt.is_clean(0) => block_0(rt),
+        9 if rt.is_clean(1) => block_9(rt),
+        16 if rt.is_clean(2) => block_16(rt),
+        22 if rt.is_clean(3) => block_22(rt),
+        28 if rt.is_clean(4) => block_28(rt),
+        31 if rt.is_clean(5) => block_31(rt),
+        33 if rt.is_clean(6) => block_33(rt),
+        36 if rt.is_clean(7) => block_36(rt),
+        42 if rt.is_clean(8) => block_42(rt),
+        46 if rt.is_clean(9) => block_46(rt),
+        _ => return None,
+    })
+}
+
+fn block_0(rt: &mut Runtime) -> Exit {
+    // 0: IPT [21]
+    if !rt.writable(21) {
+        return Err(0);
+    }
+    let v = match rt.input() {
+        Some(v) => v,
+        None => {
+            rt.set_ip(0);
+            return Ok(Some(Stopped::NeedsInput));
+        }
+    };
+    if rt.store(21, v).ok_or(0)? {
+        rt.set_ip(2);
+        return Ok(None);
+    }
+    // 2: CEQ [21], #8, [20]
+    let v = (rt.load(21).ok_or(2)? == 8) as i64;
+    if rt.store(20, v).ok_or(2)? {
+        rt.set_ip(6);
+        return Ok(None);
+    }
+    // 6: JNZ [20], #22
+    if rt.load(20).ok_or(6)? != 0 {
+        rt.set_ip(22);
+        return Ok(None);
+    }
+    rt.set_ip(9);
+    Ok(None)
+}
+
+fn block_9(rt: &mut Runtime) -> Exit {
+    // 9: CLT #8, [21], [20]
+    let v = (8 < rt.load(21).ok_or(9)?) as i64;
+    if rt.store(20, v).ok_or(9)? {
+        rt.set_ip(13);
+        return Ok(None);
+    }
+    // 13: JPZ [20], #31
+    if rt.load(20).ok_or(13)? == 0 {
+        rt.set_ip(31);
+        return Ok(None);
+    }
+    rt.set_ip(16);
+    Ok(None)
+}
+
+fn block_16(rt: &mut Runtime) -> Exit {
+    // 16: JPZ #0, #36
+    rt.set_ip(36);
+    Ok(None)
+}
+
+fn block_22(rt: &mut Runtime) -> Exit {
+    // 22: MUL [21], #125, [20]
+    let v = i64::checked_mul(rt.load(21).ok_or(22)?, 125).ok_or(22)?;
+    if rt.store(20, v).ok_or(22)? {
+        rt.set_ip(26);
+        return Ok(None);
+    }
+    // 26: OUT [20]
+    let v = rt.load(20).ok_or(26)?;
+    rt.set_ip(28);
+    Ok(Some(Stopped::Output(v)))
+}
+
+fn block_28(rt: &mut Runtime) -> Exit {
+    // 28: JNZ #1, #46
+    rt.set_ip(46);
+    Ok(None)
+}
+
+fn block_31(rt: &mut Runtime) -> Exit {
+    // 31: OUT #999
+    let v = 999;
+    rt.set_ip(33);
+    Ok(Some(Stopped::Output(v)))
+}
+
+fn block_33(rt: &mut Runtime) -> Exit {
+    // 33: JNZ #1, #46
+    rt.set_ip(46);
+    Ok(None)
+}
+
+fn block_36(rt: &mut Runtime) -> Exit {
+    // 36: ADD #1000, #1, [20]
+    let v = i64::checked_add(1000, 1).ok_or(36)?;
+    if rt.store(20, v).ok_or(36)? {
+        rt.set_ip(40);
+        return Ok(None);
+    }
+    // 40: OUT [20]
+    let v = rt.load(20).ok_or(40)?;
+    rt.set_ip(42);
+    Ok(Some(Stopped::Output(v)))
+}
+
+fn block_42(rt: &mut Runtime) -> Exit {
+    // 42: JNZ #1, #46
+    rt.set_ip(46);
+    Ok(None)
+}
+
+fn block_46(rt: &mut Runtime) -> Exit {
+    // 46: HLT
+    rt.halt();
+    Ok(Some(Stopped::Halted))
+}
@@ -0,0 +1,76 @@
+//! Intcode program of 13 words translated to Rust: 6 instructions in 3 blocks.
+//!
+//! Generated code, do not edit.
+
+use crate::util::computer::{
+    aot::{Exit, Runtime, Translated},
+    Stopped,
+};
+
+/// The program image.
+pub static IMAGE: [i64; 13] = [
+    3, 100, 9, 100, 204, 0, 1002, 100, 4611686018427387904, 101, 4, 101, 99,
+];
+
+/// Block owning each cell of the image, plus one.
+static OWNERS: [u32; 13] = [
+    1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 3,
+];
+
+/// Loads the program in a fresh machine.
+pub fn new() -> Translated {
+    Translated::new(&IMAGE, &OWNERS, dispatch)
+}
+
+fn dispatch(rt: &mut Runtime) -> Option<Exit> {
+    Some(match rt.ip() {
+        0 if rt.is_clean(0) => block_0(rt),
+        6 if rt.is_clean(1) => block_6(rt),
+        12 if rt.is_clean(2) => block_12(rt),
+        _ => return None,
+    })
+}
+
+fn block_0(rt: &mut Runtime) -> Exit {
+    // 0: IPT [100]
+    if !rt.writable(100) {
+        return Err(0);
+    }
+    let v = match rt.input() {
+        Some(v) => v,
+        None => {
+            rt.set_ip(0);
+            return Ok(Some(Stopped::NeedsInput));
+        }
+    };
+    if rt.store(100, v).ok_or(0)? {
+        rt.set_ip(2);
+        return Ok(None);
+    }
+    // 2: RBO [100]
+    let rbo = rt.rbo().checked_add(rt.load(100).ok_or(2)?).ok_or(2)?;
+    rt.set_rbo(rbo);
+    // 4: OUT [rb+0]
+    let v = rt.load(rt.relative(0).ok_or(4)?).ok_or(4)?;
+    rt.set_ip(6);
+    Ok(Some(Stopped::Output(v)))
+}
+
+fn block_6(rt: &mut Runtime) -> Exit {
+    // 6: MUL [100], #4611686018427387904, [101]
+    let v = i64::checked_mul(rt.load(100).ok_or(6)?, 4611686018427387904).ok_or(6)?;
+    if rt.store(101, v).ok_or(6)? {
+        rt.set_ip(10);
+        return Ok(None);
+    }
+    // 10: OUT [101]
+    let v = rt.load(101).ok_or(10)?;
+    rt.set_ip(12);
+    Ok(Some(Stopped::Output(v)))
+}
+
+fn block_12(rt: &mut Runtime) -> Exit {
+    // 12: HLT
+    rt.halt();
+    Ok(Some(Stopped::Halted))
+}
@@ -0,0 +1,188 @@
+//! Intcode program of 70 words translated to Rust: 23 instructions in 9 blocks.
+//!
+//! Generated code, do not edit.
+
+use crate::util::computer::{
+    aot::{Exit, Runtime, Translated},
+    Stopped,
+};
+
+/// The program image.
+pub static IMAGE: [i64; 70] = [
+    109, 69, 203, 1, 21101, 11, 0, 0, 1105, 1, 14, 204, 2, 99, 21207, 1,
+    2, 3, 1206, 3, 28, 21201, 1, 0, 2, 2105, 1, 0, 21201, 1, -1, 5,
+    21101, 41, 0, 4, 109, 4, 1105, 1, 14, 109, -4, 21201, 6, 0, 3, 21201,
+    1, -2, 5, 21101, 60, 0, 4, 109, 4, 1105, 1, 14, 109, -4, 22201, 3,
+    6, 2, 2105, 1, 0, 0,
+];
+
+/// Block owning each cell of the image, plus one.
+static OWNERS: [u32; 70] = [
+    1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 3, 3, 4, 5, 5,
+    5, 5, 5, 5, 5, 6, 6, 6, 6, 6, 6, 6, 7, 7, 7, 7,
+    7, 7, 7, 7, 7, 7, 7, 7, 7, 8, 8, 8, 8, 8, 8, 8,
+    8, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8, 9, 9, 9, 9,
+    9, 9, 9, 9, 9, 0,
+];
+
+/// Loads the program in a fresh machine.
+pub fn new() -> Translated {
+    Translated::new(&IMAGE, &OWNERS, dispatch)
+}
+
+fn dispatch(rt: &mut Runtime) -> Option<Exit> {
+    Some(match rt.ip() {
+        0 if rt.is_clean(0) => block_0(rt),
+        2 if rt.is_clean(1) => block_2(rt),
+        11 if rt.is_clean(2) => block_11(rt),
+        13 if rt.is_clean(3) => block_13(rt),
+        14 if rt.is_clean(4) => block_14(rt),
+        21 if rt.is_clean(5) => block_21(rt),
+        28 if rt.is_clean(6) => block_28(rt),
+        41 if rt.is_clean(7) => block_41(rt),
+        60 if rt.is_clean(8) => block_60(rt),
+        _ => return None,
+    })
+}
+
+fn block_0(rt: &mut Runtime) -> Exit {
+    // 0: RBO #69
+    let rbo = rt.rbo().checked_add(69).ok_or(0)?;
+    rt.set_rbo(rbo);
+    rt.set_ip(2);
+    Ok(None)
+}
+
+fn block_2(rt: &mut Runtime) -> Exit {
+    // 2: IPT [rb+1]
+    if !rt.writable(rt.relative(1).ok_or(2)?) {
+        return Err(2);
+    }
+    let v = match rt.input() {
+        Some(v) => v,
+        None => {
+            rt.set_ip(2);
+            return Ok(Some(Stopped::NeedsInput));
+        }
+    };
+    if rt.store(rt.relative(1).ok_or(2)?, v).ok_or(2)? {
+        rt.set_ip(4);
+        return Ok(None);
+    }
+    // 4: ADD #11, #0, [rb+0]
+    let v = i64::checked_add(11, 0).ok_or(4)?;
+    if rt.store(rt.relative(0).ok_or(4)?, v).ok_or(4)? {
+        rt.set_ip(8);
+        return Ok(None);
+    }
+    // 8: JNZ #1, #14
+    rt.set_ip(14);
+    Ok(None)
+}
+
+fn block_11(rt: &mut Runtime) -> Exit {
+    // 11: OUT [rb+2]
+    let v = rt.load(rt.relative(2).ok_or(11)?).ok_or(11)?;
+    rt.set_ip(13);
+    Ok(Some(Stopped::Output(v)))
+}
+
+fn block_13(rt: &mut Runtime) -> Exit {
+    // 13: HLT
+    rt.halt();
+    Ok(Some(Stopped::Halted))
+}
+
+fn block_14(rt: &mut Runtime) -> Exit {
+    // 14: CLT [rb+1], #2, [rb+3]
+    let v = (rt.load(rt.relative(1).ok_or(14)?).ok_or(14)? < 2) as i64;
+    if rt.store(rt.relative(3).ok_or(14)?, v).ok_or(14)? {
+        rt.set_ip(18);
+        return Ok(None);
+    }
+    // 18: JPZ [rb+3], #28
+    if rt.load(rt.relative(3).ok_or(18)?).ok_or(18)? == 0 {
+        rt.set_ip(28);
+        return Ok(None);
+    }
+    rt.set_ip(21);
+    Ok(None)
+}
+
+fn block_21(rt: &mut Runtime) -> Exit {
+    // 21: ADD [rb+1], #0, [rb+2]
+    let v = i64::checked_add(rt.load(rt.relative(1).ok_or(21)?).ok_or(21)?, 0).ok_or(21)?;
+    if rt.store(rt.relative(2).ok_or(21)?, v).ok_or(21)? {
+        rt.set_ip(25);
+        return Ok(None);
+    }
+    // 25: JNZ #1, [rb+0]
+    rt.set_ip(rt.load(rt.relative(0).ok_or(25)?).ok_or(25)?);
+    Ok(None)
+}
+
+fn block_28(rt: &mut Runtime) -> Exit {
+    // 28: ADD [rb+1], #-1, [rb+5]
+    let v = i64::checked_add(rt.load(rt.relative(1).ok_or(28)?).ok_or(28)?, -1).ok_or(28)?;
+    if rt.store(rt.relative(5).ok_or(28)?, v).ok_or(28)? {
+        rt.set_ip(32);
+        return Ok(None);
+    }
+    // 32: ADD #41, #0, [rb+4]
+    let v = i64::checked_add(41, 0).ok_or(32)?;
+    if rt.store(rt.relative(4).ok_or(32)?, v).ok_or(32)? {
+        rt.set_ip(36);
+        return Ok(None);
+    }
+    // 36: RBO #4
+    let rbo = rt.rbo().checked_add(4).ok_or(36)?;
+    rt.set_rbo(rbo);
+    // 38: JNZ #1, #14
+    rt.set_ip(14);
+    Ok(None)
+}
+
+fn block_41(rt: &mut Runtime) -> Exit {
+    // 41: RBO #-4
+    let rbo = rt.rbo().checked_add(-4).ok_or(41)?;
+    rt.set_rbo(rbo);
+    // 43: ADD [rb+6], #0, [rb+3]
+    let v = i64::checked_add(rt.load(rt.relative(6).ok_or(43)?).ok_or(43)?, 0).ok_or(43)?;
+    if rt.store(rt.relative(3).ok_or(43)?, v).ok_or(43)? {
+        rt.set_ip(47);
+        return Ok(None);
+    }
+    // 47: ADD [rb+1], #-2, [rb+5]
+    let v = i64::checked_add(rt.load(rt.relative(1).ok_or(47)?).ok_or(47)?, -2).ok_or(47)?;
+    if rt.store(rt.relative(5).ok_or(47)?, v).ok_or(47)? {
+        rt.set_ip(51);
+        return Ok(None);
+    }
+    // 51: ADD #60, #0, [rb+4]
+    let v = i64::checked_add(60, 0).ok_or(51)?;
+    if rt.store(rt.relative(4).ok_or(51)?, v).ok_or(51)? {
+        rt.set_ip(55);
+        return Ok(None);
+    }
+    // 55: RBO #4
+    let rbo = rt.rbo().checked_add(4).ok_or(55)?;
+    rt.set_rbo(rbo);
+    // 57: JNZ #1, #14
+    rt.set_ip(14);
+    Ok(None)
+}
+
+fn block_60(rt: &mut Runtime) -> Exit {
+    // 60: RBO #-4
+    let rbo = rt.rbo().checked_add(-4).ok_or(60)?;
+    rt.set_rbo(rbo);
+    // 62: ADD [rb+3], [rb+6], [rb+2]
+    let v = i64::checked_add(rt.load(rt.relative(3).ok_or(62)?).ok_or(62)?, rt.load(rt.relative(6).ok_or(62)?).ok_or(62)?).ok_or(62)?;
+    if rt.store(rt.relative(2).ok_or(62)?, v).ok_or(62)? {
+        rt.set_ip(66);
+        return Ok(None);
+    }
+    // 66: JNZ #1, [rb+0]
+    rt.set_ip(rt.load(rt.relative(0).ok_or(66)?).ok_or(66)?);
+    Ok(None)
+}
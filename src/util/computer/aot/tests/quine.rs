@@ -0,0 +1,70 @@
+//! Intcode program of 16 words translated to Rust: 6 instructions in 3 blocks.
+//!
+//! Generated code, do not edit.
+
+use crate::util::computer::{
+    aot::{Exit, Runtime, Translated},
+    Stopped,
+};
+
+/// The program image.
+pub static IMAGE: [i64; 16] = [
+    109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+];
+
+/// Block owning each cell of the image, plus one.
+static OWNERS: [u32; 16] = [
+    1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 3,
+];
+
+/// Loads the program in a fresh machine.
+pub fn new() -> Translated {
+    Translated::new(&IMAGE, &OWNERS, dispatch)
+}
+
+fn dispatch(rt: &mut Runtime) -> Option<Exit> {
+    Some(match rt.ip() {
+        0 if rt.is_clean(0) => block_0(rt),
+        4 if rt.is_clean(1) => block_4(rt),
+        15 if rt.is_clean(2) => block_15(rt),
+        _ => return None,
+    })
+}
+
+fn block_0(rt: &mut Runtime) -> Exit {
+    // 0: RBO #1
+    let rbo = rt.rbo().checked_add(1).ok_or(0)?;
+    rt.set_rbo(rbo);
+    // 2: OUT [rb-1]
+    let v = rt.load(rt.relative(-1).ok_or(2)?).ok_or(2)?;
+    rt.set_ip(4);
+    Ok(Some(Stopped::Output(v)))
+}
+
+fn block_4(rt: &mut Runtime) -> Exit {
+    // 4: ADD [100], #1, [100]
+    let v = i64::checked_add(rt.load(100).ok_or(4)?, 1).ok_or(4)?;
+    if rt.store(100, v).ok_or(4)? {
+        rt.set_ip(8);
+        return Ok(None);
+    }
+    // 8: CEQ [100], #16, [101]
+    let v = (rt.load(100).ok_or(8)? == 16) as i64;
+    if rt.store(101, v).ok_or(8)? {
+        rt.set_ip(12);
+        return Ok(None);
+    }
+    // 12: JPZ [101], #0
+    if rt.load(101).ok_or(12)? == 0 {
+        rt.set_ip(0);
+        return Ok(None);
+    }
+    rt.set_ip(15);
+    Ok(None)
+}
+
+fn block_15(rt: &mut Runtime) -> Exit {
+    // 15: HLT
+    rt.halt();
+    Ok(Some(Stopped::Halted))
+}
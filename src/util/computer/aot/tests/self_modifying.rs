@@ -0,0 +1,82 @@
+//! Intcode program of 28 words translated to Rust: 7 instructions in 4 blocks.
+//!
+//! Generated code, do not edit.
+
+use crate::util::computer::{
+    aot::{Exit, Runtime, Translated},
+    Stopped,
+};
+
+/// The program image.
+pub static IMAGE: [i64; 28] = [
+    1, 24, 25, 26, 4, 26, 1006, 27, 23, 1101, 0, 2, 0, 1101, 0, 0,
+    27, 1105, 1, 0, 0, 0, 0, 99, 3, 4, 0, 1,
+];
+
+/// Block owning each cell of the image, plus one.
+static OWNERS: [u32; 28] = [
+    1, 1, 1, 1, 1, 1, 2, 2, 2, 3, 3, 3, 3, 3, 3, 3,
+    3, 3, 3, 3, 0, 0, 0, 4, 0, 0, 0, 0,
+];
+
+/// Loads the program in a fresh machine.
+pub fn new() -> Translated {
+    Translated::new(&IMAGE, &OWNERS, dispatch)
+}
+
+fn dispatch(rt: &mut Runtime) -> Option<Exit> {
+    Some(match rt.ip() {
+        0 if rt.is_clean(0) => block_0(rt),
+        6 if rt.is_clean(1) => block_6(rt),
+        9 if rt.is_clean(2) => block_9(rt),
+        23 if rt.is_clean(3) => block_23(rt),
+        _ => return None,
+    })
+}
+
+fn block_0(rt: &mut Runtime) -> Exit {
+    // 0: ADD [24], [25], [26]
+    let v = i64::checked_add(rt.load(24).ok_or(0)?, rt.load(25).ok_or(0)?).ok_or(0)?;
+    if rt.store(26, v).ok_or(0)? {
+        rt.set_ip(4);
+        return Ok(None);
+    }
+    // 4: OUT [26]
+    let v = rt.load(26).ok_or(4)?;
+    rt.set_ip(6);
+    Ok(Some(Stopped::Output(v)))
+}
+
+fn block_6(rt: &mut Runtime) -> Exit {
+    // 6: JPZ [27], #23
+    if rt.load(27).ok_or(6)? == 0 {
+        rt.set_ip(23);
+        return Ok(None);
+    }
+    rt.set_ip(9);
+    Ok(None)
+}
+
+fn block_9(rt: &mut Runtime) -> Exit {
+    // 9: ADD #0, #2, [0]
+    let v = i64::checked_add(0, 2).ok_or(9)?;
+    if rt.store(0, v).ok_or(9)? {
+        rt.set_ip(13);
+        return Ok(None);
+    }
+    // 13: ADD #0, #0, [27]
+    let v = i64::checked_add(0, 0).ok_or(13)?;
+    if rt.store(27, v).ok_or(13)? {
+        rt.set_ip(17);
+        return Ok(None);
+    }
+    // 17: JNZ #1, #0
+    rt.set_ip(0);
+    Ok(None)
+}
+
+fn block_23(rt: &mut Runtime) -> Exit {
+    // 23: HLT
+    rt.halt();
+    Ok(Some(Stopped::Halted))
+}
@@ -0,0 +1,148 @@
+use super::{Computer, ErrorKind, Result, Word};
+
+/// How additions and multiplications behave on overflow.
+///
+/// This covers the add and multiply instructions. Addresses and relative base
+/// adjustments are always checked, an overflow fails with [`ErrorKind::InvalidAddress`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Arithmetic {
+    /// Fail with [`ErrorKind::Overflow`]
+    Checked,
+    /// Wrap around, two's complement
+    Wrapping,
+    /// Clamp to the smallest or largest word
+    Saturating,
+}
+
+impl Default for Arithmetic {
+    #[inline]
+    fn default() -> Arithmetic {
+        Arithmetic::Checked
+    }
+}
+
+impl<W: Word> Computer<W> {
+    #[inline]
+    pub fn arithmetic(&self) -> Arithmetic {
+        self.arithmetic
+    }
+
+    /// Selects the overflow behavior, [`Arithmetic::Checked`] by default.
+    #[inline]
+    pub fn set_arithmetic(&mut self, arithmetic: Arithmetic) {
+        self.arithmetic = arithmetic;
+    }
+
+    #[inline]
+    pub(super) fn add(&self, lhs: &W, rhs: &W) -> Result<W, W> {
+        match self.arithmetic {
+            Arithmetic::Checked => lhs
+                .checked_add(rhs)
+                .ok_or_else(|| self.error(ErrorKind::Overflow(lhs.clone(), rhs.clone()))),
+            Arithmetic::Wrapping => Ok(lhs.wrapping_add(rhs)),
+            Arithmetic::Saturating => Ok(lhs.saturating_add(rhs)),
+        }
+    }
+
+    #[inline]
+    pub(super) fn mul(&self, lhs: &W, rhs: &W) -> Result<W, W> {
+        match self.arithmetic {
+            Arithmetic::Checked => lhs
+                .checked_mul(rhs)
+                .ok_or_else(|| self.error(ErrorKind::Overflow(lhs.clone(), rhs.clone()))),
+            Arithmetic::Wrapping => Ok(lhs.wrapping_mul(rhs)),
+            Arithmetic::Saturating => Ok(lhs.saturating_mul(rhs)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Engine;
+    use super::*;
+
+    /// Outputs `lhs + rhs` or `lhs * rhs`.
+    fn compute<W: Word>(opcode: i64, lhs: W, rhs: W, arithmetic: Arithmetic) -> Result<W, W> {
+        let code = [
+            W::from(1100 + opcode as i32),
+            lhs,
+            rhs,
+            W::from(7),
+            W::from(4),
+            W::from(7),
+            W::from(99),
+            W::default(),
+        ];
+        let mut results = [Engine::Interpreter, Engine::Cached].iter().map(|&engine| {
+            let mut computer = Computer::new(&code, None);
+            computer.set_arithmetic(arithmetic);
+            computer.set_engine(engine);
+            computer.resume(None).map(Option::unwrap)
+        });
+        let result = results.next().unwrap();
+        let cached = results.next().unwrap();
+        match (&result, &cached) {
+            (Ok(a), Ok(b)) => assert_eq!(a, b),
+            (Err(a), Err(b)) => assert_eq!(a.to_string(), b.to_string()),
+            _ => panic!("engines disagree: {:?} {:?}", result, cached),
+        }
+        result
+    }
+
+    #[test]
+    fn checked() {
+        assert_eq!(
+            Computer::new(&[99i64], None).arithmetic(),
+            Arithmetic::Checked
+        );
+        assert_eq!(
+            compute(1, i64::MAX - 1, 1, Arithmetic::Checked).unwrap(),
+            i64::MAX
+        );
+        assert_eq!(
+            compute(2, i64::MIN, 1, Arithmetic::Checked).unwrap(),
+            i64::MIN
+        );
+
+        let err = compute(1, i64::MAX, 1, Arithmetic::Checked).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Overflow(i64::MAX, 1)));
+        assert_eq!(err.location(), 0);
+        let err = compute(1, i64::MIN, -1, Arithmetic::Checked).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Overflow(i64::MIN, -1)));
+        let err = compute(2, i64::MIN, -1, Arithmetic::Checked).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Overflow(i64::MIN, -1)));
+        let err = compute(2, 1i32 << 16, 1 << 15, Arithmetic::Checked).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Overflow(65536, 32768)));
+    }
+
+    #[test]
+    fn wrapping() {
+        let wrapping = Arithmetic::Wrapping;
+        assert_eq!(compute(1, i64::MAX, 1, wrapping).unwrap(), i64::MIN);
+        assert_eq!(compute(1, i64::MIN, -1, wrapping).unwrap(), i64::MAX);
+        assert_eq!(compute(2, i64::MIN, -1, wrapping).unwrap(), i64::MIN);
+        assert_eq!(compute(2, i64::MAX, 2, wrapping).unwrap(), -2);
+        assert_eq!(compute(2, 1i32 << 16, 1 << 15, wrapping).unwrap(), i32::MIN);
+    }
+
+    #[test]
+    fn saturating() {
+        let saturating = Arithmetic::Saturating;
+        assert_eq!(compute(1, i64::MAX, 1, saturating).unwrap(), i64::MAX);
+        assert_eq!(compute(1, i64::MIN, -1, saturating).unwrap(), i64::MIN);
+        assert_eq!(compute(2, i64::MIN, -1, saturating).unwrap(), i64::MAX);
+        assert_eq!(compute(2, i64::MAX, -2, saturating).unwrap(), i64::MIN);
+        assert_eq!(compute(2, -3, 4, saturating).unwrap(), -12);
+    }
+
+    #[test]
+    fn addresses_are_checked() {
+        for &arithmetic in &[Arithmetic::Wrapping, Arithmetic::Saturating] {
+            let mut computer = Computer::new(&[109, i64::MAX, 109, 1, 99], None);
+            computer.set_arithmetic(arithmetic);
+            let err = computer.run(None).unwrap_err();
+            assert!(matches!(err.kind(), ErrorKind::InvalidAddress(_)));
+            assert_eq!(err.location(), 2);
+        }
+    }
+}
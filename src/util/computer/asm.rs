@@ -0,0 +1,615 @@
+//! Assembler for a small Intcode mnemonic language.
+//!
+//! ```text
+//! ; prints the numbers from 10 down to 1
+//!         ADD #10, #0, [counter]
+//! loop:   OUT [counter]
+//!         ADD [counter], #-1, [counter]
+//!         JNZ [counter], #loop
+//!         HLT
+//! counter: DB 0
+//! ```
+//!
+//! Operands are written `#expr` (immediate), `[expr]` (position) or `[rb+expr]`
+//! (relative), where an expression adds and subtracts numbers and label addresses.
+//! `DB` emits raw words. A numeric label such as `12:` asserts the address of the next
+//! statement, which lets the output of [`disasm`](super::disasm) be assembled back.
+
+use super::{Instruction, Mode, Opcode};
+
+use std::{collections::HashMap, convert::TryFrom, error, fmt};
+
+/// Assembles `source` into a program image that can be loaded by [`Computer::new`].
+///
+/// [`Computer::new`]: super::Computer::new
+pub fn assemble(source: &str) -> Result<Vec<i64>> {
+    let mut labels = HashMap::new();
+    let mut statements = Vec::new();
+    let mut addr = 0;
+
+    // first pass: parse statements and collect label addresses
+    for (line_no, line) in source.lines().enumerate() {
+        let mut parser = Parser::new(line_no + 1, line)?;
+        while let Some(label) = parser.label()? {
+            match label {
+                Label::Name(name, column) => {
+                    if labels.insert(name.clone(), addr).is_some() {
+                        return Err(parser.error_at(column, ErrorKind::DuplicateLabel(name)));
+                    }
+                }
+                Label::Address(expected, column) => {
+                    if expected != addr {
+                        return Err(parser.error_at(
+                            column,
+                            ErrorKind::AddressMismatch {
+                                expected,
+                                actual: addr,
+                            },
+                        ));
+                    }
+                }
+            }
+        }
+        if let Some(statement) = parser.statement()? {
+            addr += statement.size();
+            statements.push((line_no + 1, statement));
+        }
+    }
+
+    // second pass: emit words
+    let mut code = Vec::with_capacity(addr as usize);
+    for (line, statement) in statements {
+        let eval = |expr: &Expr| expr.eval(line, &labels);
+        match statement {
+            Statement::Instruction(opcode, operands) => {
+                let mut modes = [Mode::Position; 3];
+                for (mode, operand) in modes.iter_mut().zip(operands.iter()) {
+                    *mode = operand.mode;
+                }
+                code.push(Instruction { opcode, modes }.encode());
+                for operand in &operands {
+                    code.push(eval(&operand.expr)?);
+                }
+            }
+            Statement::Data(values) => {
+                for value in &values {
+                    code.push(eval(value)?);
+                }
+            }
+        }
+    }
+    Ok(code)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    line: usize,
+    column: usize,
+    kind: ErrorKind,
+}
+
+impl Error {
+    /// One-based line of the error.
+    #[inline]
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// One-based column of the error.
+    #[inline]
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    #[inline]
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    UnexpectedChar(char),
+    UnexpectedToken(String),
+    UnexpectedEnd,
+    UnknownMnemonic(String),
+    OperandCount { expected: usize, actual: usize },
+    UndefinedLabel(String),
+    DuplicateLabel(String),
+    AddressMismatch { expected: i64, actual: i64 },
+    Overflow,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: ", self.line, self.column)?;
+        match self.kind {
+            ErrorKind::UnexpectedChar(c) => write!(f, "unexpected character {:?}", c),
+            ErrorKind::UnexpectedToken(ref token) => write!(f, "unexpected `{}`", token),
+            ErrorKind::UnexpectedEnd => write!(f, "unexpected end of line"),
+            ErrorKind::UnknownMnemonic(ref name) => write!(f, "unknown mnemonic `{}`", name),
+            ErrorKind::OperandCount { expected, actual } => {
+                write!(f, "expected {} operands, found {}", expected, actual)
+            }
+            ErrorKind::UndefinedLabel(ref name) => write!(f, "undefined label `{}`", name),
+            ErrorKind::DuplicateLabel(ref name) => write!(f, "label `{}` already defined", name),
+            ErrorKind::AddressMismatch { expected, actual } => write!(
+                f,
+                "statement is at address {}, label expected {}",
+                actual, expected
+            ),
+            ErrorKind::Overflow => write!(f, "arithmetic overflow in expression"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Number(i128),
+    Colon,
+    Comma,
+    Hash,
+    LBracket,
+    RBracket,
+    Plus,
+    Minus,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Ident(name) => write!(f, "{}", name),
+            Token::Number(n) => write!(f, "{}", n),
+            Token::Colon => write!(f, ":"),
+            Token::Comma => write!(f, ","),
+            Token::Hash => write!(f, "#"),
+            Token::LBracket => write!(f, "["),
+            Token::RBracket => write!(f, "]"),
+            Token::Plus => write!(f, "+"),
+            Token::Minus => write!(f, "-"),
+        }
+    }
+}
+
+/// Splits a line into tokens paired with their one-based column.
+fn tokenize(line_no: usize, line: &str) -> Result<Vec<(Token, usize)>> {
+    let mut tokens = Vec::new();
+    let mut chars = line.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        let column = line[..i].chars().count() + 1;
+        let token = match c {
+            ';' => break,
+            c if c.is_whitespace() => continue,
+            ':' => Token::Colon,
+            ',' => Token::Comma,
+            '#' => Token::Hash,
+            '[' => Token::LBracket,
+            ']' => Token::RBracket,
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            c if c.is_ascii_digit() => {
+                let mut end = i + 1;
+                while let Some(&(j, d)) = chars.peek() {
+                    if !d.is_ascii_digit() {
+                        break;
+                    }
+                    end = j + 1;
+                    chars.next();
+                }
+                Token::Number(line[i..end].parse().map_err(|_| Error {
+                    line: line_no,
+                    column,
+                    kind: ErrorKind::Overflow,
+                })?)
+            }
+            c if c.is_ascii_alphabetic() || c == '_' || c == '.' => {
+                let mut end = i + 1;
+                while let Some(&(j, d)) = chars.peek() {
+                    if !(d.is_ascii_alphanumeric() || d == '_' || d == '.') {
+                        break;
+                    }
+                    end = j + 1;
+                    chars.next();
+                }
+                Token::Ident(line[i..end].to_owned())
+            }
+            c => {
+                return Err(Error {
+                    line: line_no,
+                    column,
+                    kind: ErrorKind::UnexpectedChar(c),
+                })
+            }
+        };
+        tokens.push((token, column));
+    }
+    Ok(tokens)
+}
+
+enum Label {
+    Name(String, usize),
+    Address(i64, usize),
+}
+
+#[derive(Debug)]
+enum Statement {
+    Instruction(Opcode, Vec<Operand>),
+    Data(Vec<Expr>),
+}
+
+impl Statement {
+    fn size(&self) -> i64 {
+        match self {
+            Statement::Instruction(_, operands) => operands.len() as i64 + 1,
+            Statement::Data(values) => values.len() as i64,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Operand {
+    mode: Mode,
+    expr: Expr,
+}
+
+#[derive(Debug)]
+enum Term {
+    Number(i128),
+    Label(String),
+}
+
+/// Sum of terms, each with its sign and column.
+#[derive(Debug)]
+struct Expr(Vec<(bool, Term, usize)>);
+
+impl Expr {
+    fn eval(&self, line: usize, labels: &HashMap<String, i64>) -> Result<i64> {
+        let overflow = |column| Error {
+            line,
+            column,
+            kind: ErrorKind::Overflow,
+        };
+        // evaluate on a wider type so that `-9223372036854775808` is accepted
+        let value = self
+            .0
+            .iter()
+            .try_fold(0i128, |acc, (negative, term, column)| {
+                let value = match term {
+                    Term::Number(n) => *n,
+                    Term::Label(name) => i128::from(*labels.get(name).ok_or_else(|| Error {
+                        line,
+                        column: *column,
+                        kind: ErrorKind::UndefinedLabel(name.clone()),
+                    })?),
+                };
+                if *negative {
+                    acc.checked_sub(value)
+                } else {
+                    acc.checked_add(value)
+                }
+                .ok_or_else(|| overflow(*column))
+            })?;
+        i64::try_from(value).map_err(|_| overflow(self.0[0].2))
+    }
+}
+
+struct Parser {
+    line: usize,
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    /// Column just past the end of the line, for errors at the end of input
+    end: usize,
+}
+
+impl Parser {
+    fn new(line_no: usize, line: &str) -> Result<Parser> {
+        Ok(Parser {
+            line: line_no,
+            tokens: tokenize(line_no, line)?,
+            pos: 0,
+            end: line.chars().count() + 1,
+        })
+    }
+
+    #[inline]
+    fn error_at(&self, column: usize, kind: ErrorKind) -> Error {
+        Error {
+            line: self.line,
+            column,
+            kind,
+        }
+    }
+
+    #[inline]
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    #[inline]
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + offset).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Result<(Token, usize)> {
+        match self.tokens.get(self.pos) {
+            Some(token) => {
+                self.pos += 1;
+                Ok(token.clone())
+            }
+            None => Err(self.error_at(self.end, ErrorKind::UnexpectedEnd)),
+        }
+    }
+
+    fn unexpected(&self) -> Error {
+        match self.tokens.get(self.pos) {
+            Some((token, column)) => {
+                self.error_at(*column, ErrorKind::UnexpectedToken(token.to_string()))
+            }
+            None => self.error_at(self.end, ErrorKind::UnexpectedEnd),
+        }
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<()> {
+        if self.peek() == Some(&expected) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    /// Parses `name:` or `addr:` at the start of the line.
+    fn label(&mut self) -> Result<Option<Label>> {
+        if self.peek_at(1) != Some(&Token::Colon) {
+            return Ok(None);
+        }
+        let label = match self.next()? {
+            (Token::Ident(name), column) => Label::Name(name, column),
+            (Token::Number(addr), column) => Label::Address(
+                i64::try_from(addr).map_err(|_| self.error_at(column, ErrorKind::Overflow))?,
+                column,
+            ),
+            _ => {
+                self.pos -= 1;
+                return Err(self.unexpected());
+            }
+        };
+        self.pos += 1;
+        Ok(Some(label))
+    }
+
+    fn statement(&mut self) -> Result<Option<Statement>> {
+        let (name, column) = match self.peek() {
+            None => return Ok(None),
+            Some(Token::Ident(_)) => match self.next()? {
+                (Token::Ident(name), column) => (name, column),
+                _ => unreachable!(),
+            },
+            Some(_) => return Err(self.unexpected()),
+        };
+
+        let statement = if name.eq_ignore_ascii_case("db") {
+            let mut values = vec![self.expr()?];
+            while self.peek() == Some(&Token::Comma) {
+                self.pos += 1;
+                values.push(self.expr()?);
+            }
+            Statement::Data(values)
+        } else {
+            let opcode = Opcode::from_mnemonic(&name)
+                .ok_or_else(|| self.error_at(column, ErrorKind::UnknownMnemonic(name)))?;
+            let mut operands = Vec::with_capacity(opcode.arity());
+            if self.peek().is_some() {
+                operands.push(self.operand()?);
+                while self.peek() == Some(&Token::Comma) {
+                    self.pos += 1;
+                    operands.push(self.operand()?);
+                }
+            }
+            if operands.len() != opcode.arity() {
+                return Err(self.error_at(
+                    column,
+                    ErrorKind::OperandCount {
+                        expected: opcode.arity(),
+                        actual: operands.len(),
+                    },
+                ));
+            }
+            Statement::Instruction(opcode, operands)
+        };
+
+        match self.peek() {
+            None => Ok(Some(statement)),
+            Some(_) => Err(self.unexpected()),
+        }
+    }
+
+    fn operand(&mut self) -> Result<Operand> {
+        match self.peek() {
+            Some(Token::Hash) => {
+                self.pos += 1;
+                Ok(Operand {
+                    mode: Mode::Immediate,
+                    expr: self.expr()?,
+                })
+            }
+            Some(Token::LBracket) => {
+                self.pos += 1;
+                let operand = match self.peek() {
+                    Some(Token::Ident(name)) if name.eq_ignore_ascii_case("rb") => {
+                        self.pos += 1;
+                        let expr = match self.peek() {
+                            Some(Token::RBracket) => Expr(vec![(false, Term::Number(0), 0)]),
+                            Some(Token::Plus) | Some(Token::Minus) => self.expr()?,
+                            _ => return Err(self.unexpected()),
+                        };
+                        Operand {
+                            mode: Mode::Relative,
+                            expr,
+                        }
+                    }
+                    _ => Operand {
+                        mode: Mode::Position,
+                        expr: self.expr()?,
+                    },
+                };
+                self.expect(Token::RBracket)?;
+                Ok(operand)
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr> {
+        let mut terms = vec![self.signed_term()?];
+        while let Some(Token::Plus) | Some(Token::Minus) = self.peek() {
+            terms.push(self.signed_term()?);
+        }
+        Ok(Expr(terms))
+    }
+
+    /// Parses a term preceded by any number of `+` and `-` signs.
+    fn signed_term(&mut self) -> Result<(bool, Term, usize)> {
+        let mut negative = false;
+        loop {
+            match self.next()? {
+                (Token::Plus, _) => (),
+                (Token::Minus, _) => negative = !negative,
+                (Token::Number(n), column) => return Ok((negative, Term::Number(n), column)),
+                (Token::Ident(name), column) => return Ok((negative, Term::Label(name), column)),
+                _ => {
+                    self.pos -= 1;
+                    return Err(self.unexpected());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::computer::{examples, Computer};
+
+    fn error(source: &str) -> (usize, usize, ErrorKind) {
+        let err = assemble(source).unwrap_err();
+        (err.line(), err.column(), err.kind().clone())
+    }
+
+    #[test]
+    fn statements() {
+        let source = "
+            ; comment
+            start:  ADD #10, #-2, [rb+3]   ; trailing comment
+                    mul [rb-1], [end], [rb]
+                    jnz #1, #start + 1
+            end:    DB 1, -2, end - start, --3
+        ";
+        assert_eq!(
+            assemble(source).unwrap(),
+            vec![21101, 10, -2, 3, 20202, -1, 11, 0, 1105, 1, 1, 1, -2, 11, 3]
+        );
+    }
+
+    #[test]
+    fn programs() {
+        let code = assemble(examples::FIB).unwrap();
+        assert_eq!(Computer::new(&code, None).resume_get(Some(10)).unwrap(), 55);
+
+        let source = "
+                    ADD #10, #0, [counter]
+            loop:   OUT [counter]
+                    ADD [counter], #-1, [counter]
+                    JNZ [counter], #loop
+                    HLT
+            counter: DB 0
+        ";
+        let outputs: Vec<i64> = Computer::new(&assemble(source).unwrap(), None)
+            .resume_iter(None)
+            .map(|out| out.unwrap())
+            .collect();
+        assert_eq!(outputs, (1..=10).rev().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn address_labels() {
+        assert_eq!(assemble("0: IPT [3]\n2: HLT").unwrap(), vec![3, 3, 99]);
+        assert_eq!(
+            error("0: IPT [3]\n  3: HLT"),
+            (
+                2,
+                3,
+                ErrorKind::AddressMismatch {
+                    expected: 3,
+                    actual: 2
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            error("HLT\n  OUT [1] $"),
+            (2, 11, ErrorKind::UnexpectedChar('$'))
+        );
+        assert_eq!(
+            error("  FOO #1"),
+            (1, 3, ErrorKind::UnknownMnemonic("FOO".to_owned()))
+        );
+        assert_eq!(
+            error("\n\n ADD #1, #2"),
+            (
+                3,
+                2,
+                ErrorKind::OperandCount {
+                    expected: 3,
+                    actual: 2
+                }
+            )
+        );
+        assert_eq!(error("OUT [1"), (1, 7, ErrorKind::UnexpectedEnd));
+        assert_eq!(
+            error("OUT #1 #2"),
+            (1, 8, ErrorKind::UnexpectedToken("#".to_owned()))
+        );
+        assert_eq!(
+            error("OUT [rb 1]"),
+            (1, 9, ErrorKind::UnexpectedToken("1".to_owned()))
+        );
+        assert_eq!(
+            error("a: HLT\na: HLT"),
+            (2, 1, ErrorKind::DuplicateLabel("a".to_owned()))
+        );
+        assert_eq!(
+            error("HLT\nJNZ #1, #there + 1"),
+            (2, 10, ErrorKind::UndefinedLabel("there".to_owned()))
+        );
+    }
+
+    #[test]
+    fn overflow() {
+        assert_eq!(assemble("DB -9223372036854775808").unwrap(), vec![i64::MIN]);
+        assert_eq!(error("DB 9223372036854775808"), (1, 4, ErrorKind::Overflow));
+        assert_eq!(
+            error("DB 9223372036854775807 + 1"),
+            (1, 4, ErrorKind::Overflow)
+        );
+        assert_eq!(
+            error("DB 99999999999999999999999999999999999999999"),
+            (1, 4, ErrorKind::Overflow)
+        );
+    }
+
+    #[test]
+    fn display() {
+        let err = assemble("HLT\n  FOO").unwrap_err();
+        assert_eq!(err.to_string(), "2:3: unknown mnemonic `FOO`");
+    }
+}
@@ -0,0 +1,497 @@
+//! Arbitrary precision integers, for programs whose values outgrow 64 bits.
+
+use std::{
+    cmp::Ordering,
+    fmt,
+    ops::{Add, Mul, Neg, Sub},
+    str::FromStr,
+};
+
+/// A signed integer of unbounded size.
+///
+/// Only the operations needed by the Intcode machine are provided: addition, subtraction,
+/// multiplication, comparison and decimal conversions.
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    /// Magnitude, little-endian base 2^32 limbs without trailing zeros
+    limbs: Vec<u32>,
+}
+
+/// Largest power of ten that fits in a limb, used for decimal conversions.
+const DECIMAL_BASE: u32 = 1_000_000_000;
+const DECIMAL_DIGITS: usize = 9;
+
+impl BigInt {
+    #[inline]
+    pub fn zero() -> BigInt {
+        BigInt::default()
+    }
+
+    #[inline]
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    #[inline]
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    /// Returns the value as an `i64`, `None` if it does not fit.
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.limbs.len() > 2 {
+            return None;
+        }
+        let magnitude = self
+            .limbs
+            .iter()
+            .rev()
+            .fold(0u64, |acc, &limb| acc << 32 | u64::from(limb));
+        if self.negative {
+            if magnitude <= 1 << 63 {
+                Some((magnitude as i64).wrapping_neg())
+            } else {
+                None
+            }
+        } else if magnitude < 1 << 63 {
+            Some(magnitude as i64)
+        } else {
+            None
+        }
+    }
+
+    /// Limbs of the magnitude, least significant first.
+    #[inline]
+    pub fn limbs(&self) -> &[u32] {
+        &self.limbs
+    }
+
+    fn from_parts(negative: bool, mut limbs: Vec<u32>) -> BigInt {
+        while limbs.last() == Some(&0) {
+            limbs.pop();
+        }
+        BigInt {
+            negative: negative && !limbs.is_empty(),
+            limbs,
+        }
+    }
+
+    fn from_magnitude(negative: bool, mut magnitude: u128) -> BigInt {
+        let mut limbs = Vec::with_capacity(4);
+        while magnitude != 0 {
+            limbs.push(magnitude as u32);
+            magnitude >>= 32;
+        }
+        BigInt::from_parts(negative, limbs)
+    }
+
+    /// Multiplies the magnitude by `factor` and adds `term`.
+    fn mul_add_small(&mut self, factor: u32, term: u32) {
+        let mut carry = u64::from(term);
+        for limb in &mut self.limbs {
+            let value = u64::from(*limb) * u64::from(factor) + carry;
+            *limb = value as u32;
+            carry = value >> 32;
+        }
+        if carry != 0 {
+            self.limbs.push(carry as u32);
+        }
+    }
+
+    /// Divides the magnitude by `divisor` in place, returns the remainder.
+    fn div_rem_small(&mut self, divisor: u32) -> u32 {
+        let mut rem = 0u64;
+        for limb in self.limbs.iter_mut().rev() {
+            let value = rem << 32 | u64::from(*limb);
+            *limb = (value / u64::from(divisor)) as u32;
+            rem = value % u64::from(divisor);
+        }
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+        rem as u32
+    }
+}
+
+fn cmp_magnitude(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut sum = Vec::with_capacity(long.len() + 1);
+    let mut carry = 0u64;
+    for (i, &limb) in long.iter().enumerate() {
+        let value = u64::from(limb) + u64::from(short.get(i).copied().unwrap_or(0)) + carry;
+        sum.push(value as u32);
+        carry = value >> 32;
+    }
+    if carry != 0 {
+        sum.push(carry as u32);
+    }
+    sum
+}
+
+/// `a - b`, with `|a| >= |b|`.
+fn sub_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut diff = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+    for (i, &limb) in a.iter().enumerate() {
+        let mut value = i64::from(limb) - i64::from(b.get(i).copied().unwrap_or(0)) - borrow;
+        borrow = 0;
+        if value < 0 {
+            value += 1 << 32;
+            borrow = 1;
+        }
+        diff.push(value as u32);
+    }
+    diff
+}
+
+fn mul_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    if a.is_empty() || b.is_empty() {
+        return Vec::new();
+    }
+    let mut product = vec![0u32; a.len() + b.len()];
+    for (i, &x) in a.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, &y) in b.iter().enumerate() {
+            let value = u64::from(x) * u64::from(y) + u64::from(product[i + j]) + carry;
+            product[i + j] = value as u32;
+            carry = value >> 32;
+        }
+        product[i + b.len()] = carry as u32;
+    }
+    product
+}
+
+impl From<i32> for BigInt {
+    #[inline]
+    fn from(value: i32) -> BigInt {
+        BigInt::from(i128::from(value))
+    }
+}
+
+impl From<i64> for BigInt {
+    #[inline]
+    fn from(value: i64) -> BigInt {
+        BigInt::from(i128::from(value))
+    }
+}
+
+impl From<i128> for BigInt {
+    #[inline]
+    fn from(value: i128) -> BigInt {
+        BigInt::from_magnitude(value < 0, value.unsigned_abs())
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &BigInt) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_magnitude(&self.limbs, &other.limbs),
+            (true, true) => cmp_magnitude(&other.limbs, &self.limbs),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    #[inline]
+    fn partial_cmp(&self, other: &BigInt) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Neg for BigInt {
+    type Output = BigInt;
+
+    #[inline]
+    fn neg(self) -> BigInt {
+        let negative = !self.negative;
+        BigInt::from_parts(negative, self.limbs)
+    }
+}
+
+impl<'a> Add<&'a BigInt> for &'a BigInt {
+    type Output = BigInt;
+
+    fn add(self, rhs: &BigInt) -> BigInt {
+        if self.negative == rhs.negative {
+            return BigInt::from_parts(self.negative, add_magnitude(&self.limbs, &rhs.limbs));
+        }
+        match cmp_magnitude(&self.limbs, &rhs.limbs) {
+            Ordering::Equal => BigInt::zero(),
+            Ordering::Greater => {
+                BigInt::from_parts(self.negative, sub_magnitude(&self.limbs, &rhs.limbs))
+            }
+            Ordering::Less => {
+                BigInt::from_parts(rhs.negative, sub_magnitude(&rhs.limbs, &self.limbs))
+            }
+        }
+    }
+}
+
+impl<'a> Sub<&'a BigInt> for &'a BigInt {
+    type Output = BigInt;
+
+    #[inline]
+    fn sub(self, rhs: &BigInt) -> BigInt {
+        self + &-rhs.clone()
+    }
+}
+
+impl<'a> Mul<&'a BigInt> for &'a BigInt {
+    type Output = BigInt;
+
+    #[inline]
+    fn mul(self, rhs: &BigInt) -> BigInt {
+        BigInt::from_parts(
+            self.negative != rhs.negative,
+            mul_magnitude(&self.limbs, &rhs.limbs),
+        )
+    }
+}
+
+impl Add for BigInt {
+    type Output = BigInt;
+
+    #[inline]
+    fn add(self, rhs: BigInt) -> BigInt {
+        &self + &rhs
+    }
+}
+
+impl Sub for BigInt {
+    type Output = BigInt;
+
+    #[inline]
+    fn sub(self, rhs: BigInt) -> BigInt {
+        &self - &rhs
+    }
+}
+
+impl Mul for BigInt {
+    type Output = BigInt;
+
+    #[inline]
+    fn mul(self, rhs: BigInt) -> BigInt {
+        &self * &rhs
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut magnitude = BigInt::from_parts(false, self.limbs.clone());
+        let mut chunks = Vec::new();
+        loop {
+            chunks.push(magnitude.div_rem_small(DECIMAL_BASE));
+            if magnitude.is_zero() {
+                break;
+            }
+        }
+        let mut digits = String::with_capacity(chunks.len() * DECIMAL_DIGITS);
+        let mut chunks = chunks.iter().rev();
+        if let Some(first) = chunks.next() {
+            digits.push_str(&first.to_string());
+        }
+        for chunk in chunks {
+            digits.push_str(&format!("{:09}", chunk));
+        }
+        f.pad_integral(!self.negative, "", &digits)
+    }
+}
+
+impl fmt::Debug for BigInt {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseBigIntError;
+
+impl fmt::Display for ParseBigIntError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("invalid digit found in string")
+    }
+}
+
+impl std::error::Error for ParseBigIntError {}
+
+impl FromStr for BigInt {
+    type Err = ParseBigIntError;
+
+    fn from_str(s: &str) -> Result<BigInt, ParseBigIntError> {
+        let (negative, digits) = match s.as_bytes().first() {
+            Some(b'-') => (true, &s[1..]),
+            Some(b'+') => (false, &s[1..]),
+            _ => (false, s),
+        };
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(ParseBigIntError);
+        }
+        let mut value = BigInt::zero();
+        let head = digits.len() % DECIMAL_DIGITS;
+        let chunks = std::iter::once(&digits[..head]).chain(
+            digits.as_bytes()[head..]
+                .chunks(DECIMAL_DIGITS)
+                .map(|chunk| std::str::from_utf8(chunk).unwrap()),
+        );
+        for chunk in chunks.filter(|chunk| !chunk.is_empty()) {
+            let factor = 10u32.pow(chunk.len() as u32);
+            value.mul_add_small(factor, chunk.parse().map_err(|_| ParseBigIntError)?);
+        }
+        Ok(BigInt::from_parts(negative, value.limbs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Computer;
+    use super::*;
+
+    fn big(s: &str) -> BigInt {
+        s.parse().unwrap()
+    }
+
+    const VALUES: &[i64] = &[
+        0,
+        1,
+        -1,
+        2,
+        1 << 31,
+        (1 << 32) - 1,
+        1 << 32,
+        -(1 << 32),
+        (1 << 62) + 12345,
+        i64::MAX,
+        i64::MIN + 1,
+        i64::MIN,
+    ];
+
+    #[test]
+    fn matches_i128() {
+        for &a in VALUES {
+            for &b in VALUES {
+                let (x, y) = (BigInt::from(a), BigInt::from(b));
+                let (a, b) = (i128::from(a), i128::from(b));
+                assert_eq!(&x + &y, BigInt::from(a + b), "{} + {}", a, b);
+                assert_eq!(&x - &y, BigInt::from(a - b), "{} - {}", a, b);
+                assert_eq!(&x * &y, BigInt::from(a * b), "{} * {}", a, b);
+                assert_eq!(x.cmp(&y), a.cmp(&b), "{} <=> {}", a, b);
+                assert_eq!((&x * &y).to_string(), (a * b).to_string());
+            }
+        }
+    }
+
+    #[test]
+    fn carries() {
+        let max = BigInt::from(u64::MAX as i128);
+        assert_eq!(max.limbs(), [u32::MAX, u32::MAX]);
+        assert_eq!((&max + &BigInt::from(1)).limbs(), [0, 0, 1]);
+        assert_eq!((&BigInt::from(1i128 << 64) - &BigInt::from(1)), max);
+        assert_eq!(
+            (big("79228162514264337593543950335") + BigInt::from(1)).to_string(),
+            "79228162514264337593543950336"
+        );
+
+        let max = big("340282366920938463463374607431768211455");
+        assert_eq!(max.limbs(), [u32::MAX; 4]);
+        assert_eq!(
+            (&max * &max).to_string(),
+            "115792089237316195423570985008687907852589419931798687112530834793049593217025"
+        );
+        assert_eq!(
+            (big("-1000000000000000000000000000000") * big("1000000000000000000000000000000")
+                + BigInt::from(1))
+            .to_string(),
+            "-999999999999999999999999999999999999999999999999999999999999"
+        );
+    }
+
+    #[test]
+    fn zero_has_no_sign() {
+        let zero = BigInt::zero();
+        assert_eq!(-zero.clone(), zero);
+        assert!(!(-BigInt::zero()).is_negative());
+        assert_eq!(big("-0"), zero);
+        assert_eq!(big("-000").to_string(), "0");
+        assert_eq!(&BigInt::from(5) - &BigInt::from(5), zero);
+        assert_eq!(&BigInt::from(-5) + &BigInt::from(5), zero);
+        assert!(!(&BigInt::from(-5) * &zero).is_negative());
+        assert!(!(&zero * &BigInt::from(-5)).is_negative());
+        assert!(zero.limbs().is_empty());
+    }
+
+    #[test]
+    fn mul_signs() {
+        for &(a, b) in &[(3, 4), (-3, 4), (3, -4), (-3, -4)] {
+            let product = BigInt::from(a) * BigInt::from(b);
+            assert_eq!(product.is_negative(), (a < 0) != (b < 0));
+            assert_eq!(product.to_i64(), Some(a * b));
+        }
+        let product = big("-18446744073709551616") * big("18446744073709551616");
+        assert!(product.is_negative());
+        assert_eq!(
+            product.to_string(),
+            "-340282366920938463463374607431768211456"
+        );
+        assert!(!(&product * &product).is_negative());
+    }
+
+    #[test]
+    fn to_i64() {
+        for &value in VALUES {
+            assert_eq!(BigInt::from(value).to_i64(), Some(value));
+        }
+        let min = BigInt::from(i64::MIN);
+        assert_eq!(min.limbs(), [0, 1 << 31]);
+        assert_eq!((&min - &BigInt::from(1)).to_i64(), None);
+        assert_eq!((BigInt::from(i64::MAX) + BigInt::from(1)).to_i64(), None);
+        assert_eq!(BigInt::from(u64::MAX as i128).to_i64(), None);
+        assert_eq!(BigInt::from(1i128 << 64).to_i64(), None);
+        assert_eq!(BigInt::from(-(1i128 << 64)).to_i64(), None);
+    }
+
+    #[test]
+    fn machine() {
+        // raises its input to the fourth power
+        let code: Vec<BigInt> = [3, 13, 2, 13, 13, 13, 2, 13, 13, 13, 4, 13, 99]
+            .iter()
+            .map(|&n| BigInt::from(n))
+            .collect();
+        let mut computer = Computer::new(&code, None);
+        let power = computer.resume(Some(BigInt::from(i64::MIN))).unwrap();
+        assert_eq!(
+            power.unwrap().to_string(),
+            "7237005577332262213973186563042994240829374041602535252466099000494570602496"
+        );
+    }
+
+    #[test]
+    fn parse() {
+        for s in &[
+            "0",
+            "7",
+            "-7",
+            "999999999",
+            "1000000000",
+            "-123456789012345678901234567890",
+        ] {
+            assert_eq!(big(s).to_string(), *s);
+        }
+        assert_eq!(big("+42"), BigInt::from(42));
+        assert_eq!(big("0001000000000"), BigInt::from(1_000_000_000));
+        assert_eq!(big(&i64::MIN.to_string()), BigInt::from(i64::MIN));
+        assert_eq!(format!("{:>5}", BigInt::from(-12)), "  -12");
+        for s in &["", "-", "+", "1a", "--1", " 1", "1.0"] {
+            assert_eq!(s.parse::<BigInt>(), Err(ParseBigIntError), "{:?}", s);
+        }
+    }
+}
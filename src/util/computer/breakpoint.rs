@@ -0,0 +1,311 @@
+//! Breakpoints and memory watchpoints, reported by [`Computer::run`] as
+//! [`Stopped::Breakpoint`] and [`Stopped::Watch`].
+
+use super::{Computer, Instruction, Opcode, Stopped, Word};
+
+use std::collections::HashSet;
+
+/// Pauses execution before an instruction runs.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Breakpoint {
+    /// Instruction at this address
+    Address(i64),
+    /// Any instruction with this opcode
+    Opcode(Opcode),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Access {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl Access {
+    #[inline]
+    fn covers(self, access: Access) -> bool {
+        self == Access::ReadWrite || self == access
+    }
+}
+
+/// Pauses execution after an instruction accessed a memory cell.
+///
+/// Only the data accesses made by instructions are watched, fetching the instruction
+/// words themselves and calls to [`Computer::read_raw`]/[`Computer::write_raw`] are not.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Watchpoint<W = i64> {
+    pub addr: i64,
+    pub access: Access,
+    /// Only trigger when this value is read or written
+    pub value: Option<W>,
+}
+
+impl<W> Watchpoint<W> {
+    #[inline]
+    pub fn new(addr: i64, access: Access) -> Watchpoint<W> {
+        Watchpoint {
+            addr,
+            access,
+            value: None,
+        }
+    }
+
+    /// Restricts the watchpoint to accesses of `value`.
+    #[inline]
+    pub fn with_value(self, value: W) -> Watchpoint<W> {
+        Watchpoint {
+            value: Some(value),
+            ..self
+        }
+    }
+}
+
+/// A triggered watchpoint.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct WatchHit<W = i64> {
+    pub watchpoint: Watchpoint<W>,
+    /// Address of the instruction that made the access
+    pub ip: i64,
+    /// Either `Access::Read` or `Access::Write`
+    pub access: Access,
+    pub value: W,
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct Probes<W> {
+    breakpoints: HashSet<Breakpoint>,
+    watchpoints: Vec<Watchpoint<W>>,
+    hit: Option<WatchHit<W>>,
+    /// Set after stopping on a breakpoint, so that the next run steps over it
+    resuming: bool,
+}
+
+impl<W> Default for Probes<W> {
+    fn default() -> Probes<W> {
+        Probes {
+            breakpoints: HashSet::new(),
+            watchpoints: Vec::new(),
+            hit: None,
+            resuming: false,
+        }
+    }
+}
+
+impl<W: Word> Computer<W> {
+    /// Returns `false` if the breakpoint was already set.
+    #[inline]
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> bool {
+        self.probes.breakpoints.insert(breakpoint)
+    }
+
+    /// Returns `false` if the breakpoint was not set.
+    #[inline]
+    pub fn remove_breakpoint(&mut self, breakpoint: Breakpoint) -> bool {
+        self.probes.breakpoints.remove(&breakpoint)
+    }
+
+    #[inline]
+    pub fn breakpoints(&self) -> impl Iterator<Item = &Breakpoint> + '_ {
+        self.probes.breakpoints.iter()
+    }
+
+    #[inline]
+    pub fn clear_breakpoints(&mut self) {
+        self.probes.breakpoints.clear();
+    }
+
+    #[inline]
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint<W>) {
+        self.probes.watchpoints.push(watchpoint);
+    }
+
+    /// Returns `false` if the watchpoint was not set.
+    pub fn remove_watchpoint(&mut self, watchpoint: Watchpoint<W>) -> bool {
+        let count = self.probes.watchpoints.len();
+        self.probes.watchpoints.retain(|w| *w != watchpoint);
+        self.probes.watchpoints.len() != count
+    }
+
+    #[inline]
+    pub fn watchpoints(&self) -> &[Watchpoint<W>] {
+        &self.probes.watchpoints
+    }
+
+    #[inline]
+    pub fn clear_watchpoints(&mut self) {
+        self.probes.watchpoints.clear();
+    }
+
+    /// Records a data access made by the current instruction.
+    #[inline]
+    pub(super) fn watch(&mut self, access: Access, addr: i64, value: &W) {
+        if self.probes.watchpoints.is_empty() || self.probes.hit.is_some() {
+            return;
+        }
+        let ip = self.ip;
+        self.probes.hit = self
+            .probes
+            .watchpoints
+            .iter()
+            .find(|w| {
+                w.addr == addr && w.access.covers(access) && w.value.iter().all(|v| v == value)
+            })
+            .map(|watchpoint| WatchHit {
+                watchpoint: watchpoint.clone(),
+                ip,
+                access,
+                value: value.clone(),
+            });
+    }
+
+    /// Whether no breakpoint, watchpoint or pending hit can pause the machine.
+    #[inline]
+    pub(super) fn probes_idle(&self) -> bool {
+        self.probes.breakpoints.is_empty()
+            && self.probes.watchpoints.is_empty()
+            && self.probes.hit.is_none()
+    }
+
+    /// Returns the reason to pause before running the instruction at `ip`, if any.
+    pub(super) fn probe(&mut self) -> Option<Stopped<W>> {
+        if let Some(hit) = self.probes.hit.take() {
+            return Some(Stopped::Watch(hit));
+        }
+        if self.probes.breakpoints.is_empty() || self.probes.resuming {
+            return None;
+        }
+        let by_address = Breakpoint::Address(self.ip);
+        let breakpoint = if self.probes.breakpoints.contains(&by_address) {
+            by_address
+        } else {
+            let word = self.read_raw(self.ip).ok()?.to_i64()?;
+            let by_opcode = Breakpoint::Opcode(Instruction::decode(word).ok()?.opcode);
+            if !self.probes.breakpoints.contains(&by_opcode) {
+                return None;
+            }
+            by_opcode
+        };
+        self.probes.resuming = true;
+        Some(Stopped::Breakpoint(breakpoint))
+    }
+
+    /// Called when the machine state was rewound, drops the pending watchpoint hit and
+    /// steps over a breakpoint at the new `ip`.
+    #[inline]
+    pub(super) fn probe_reset(&mut self) {
+        self.probes.hit = None;
+        self.probes.resuming = true;
+    }
+
+    /// Called once an instruction has run to completion.
+    #[inline]
+    pub(super) fn probe_done(&mut self) {
+        self.probes.resuming = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::computer::asm::assemble;
+
+    /// Counts down from 3 at address 10, outputting each value.
+    const COUNTDOWN: &str = "
+                OUT [n]
+                ADD [n], #-1, [n]
+                JNZ [n], #0
+                HLT
+        n:      DB 3
+    ";
+
+    fn countdown() -> Computer {
+        Computer::new(&assemble(COUNTDOWN).unwrap(), None)
+    }
+
+    #[test]
+    fn address_breakpoints() {
+        let mut computer = countdown();
+        assert!(computer.add_breakpoint(Breakpoint::Address(2)));
+        assert!(!computer.add_breakpoint(Breakpoint::Address(2)));
+        assert_eq!(computer.run(None).unwrap(), Stopped::Output(3));
+        let stop = Stopped::Breakpoint(Breakpoint::Address(2));
+        assert_eq!(computer.run(None).unwrap(), stop);
+        assert_eq!(computer.ip(), 2);
+        // resuming steps over the breakpoint
+        assert_eq!(computer.run(None).unwrap(), Stopped::Output(2));
+        assert_eq!(computer.run(None).unwrap(), stop);
+
+        assert!(computer.remove_breakpoint(Breakpoint::Address(2)));
+        assert!(!computer.remove_breakpoint(Breakpoint::Address(2)));
+        assert_eq!(computer.run(None).unwrap(), Stopped::Output(1));
+        assert_eq!(computer.run(None).unwrap(), Stopped::Halted);
+    }
+
+    #[test]
+    fn opcode_breakpoints() {
+        let mut computer = countdown();
+        computer.add_breakpoint(Breakpoint::Opcode(Opcode::Halt));
+        let outputs: Vec<_> = std::iter::from_fn(|| match computer.run(None).unwrap() {
+            Stopped::Output(out) => Some(out),
+            _ => None,
+        })
+        .collect();
+        assert_eq!(outputs, vec![3, 2, 1]);
+        assert_eq!(computer.ip(), 9);
+        assert!(!computer.is_halted());
+        assert_eq!(computer.run(None).unwrap(), Stopped::Halted);
+    }
+
+    #[test]
+    fn breakpoints_make_resume_fail() {
+        let mut computer = countdown();
+        computer.add_breakpoint(Breakpoint::Opcode(Opcode::Add));
+        assert_eq!(computer.resume(None).unwrap(), Some(3));
+        assert!(computer.resume(None).is_err());
+        assert_eq!(computer.resume(None).unwrap(), Some(2));
+    }
+
+    #[test]
+    fn watchpoints() {
+        let mut computer = countdown();
+        computer.add_watchpoint(Watchpoint::new(10, Access::Write).with_value(1));
+        assert_eq!(computer.run(None).unwrap(), Stopped::Output(3));
+        assert_eq!(computer.run(None).unwrap(), Stopped::Output(2));
+        let hit = WatchHit {
+            watchpoint: Watchpoint::new(10, Access::Write).with_value(1),
+            ip: 2,
+            access: Access::Write,
+            value: 1,
+        };
+        assert_eq!(computer.run(None).unwrap(), Stopped::Watch(hit));
+        // reported after the write completed
+        assert_eq!(computer.ip(), 6);
+        assert_eq!(computer.read_raw(10).unwrap(), 1);
+        assert_eq!(computer.run(None).unwrap(), Stopped::Output(1));
+    }
+
+    #[test]
+    fn read_watchpoints() {
+        let mut computer = countdown();
+        let watchpoint = Watchpoint::new(10, Access::ReadWrite);
+        computer.add_watchpoint(watchpoint);
+        assert_eq!(computer.watchpoints(), &[watchpoint]);
+        // the output is reported first, the watchpoint on the next run
+        assert_eq!(computer.run(None).unwrap(), Stopped::Output(3));
+        match computer.run(None).unwrap() {
+            Stopped::Watch(hit) => {
+                assert_eq!((hit.ip, hit.access, hit.value), (0, Access::Read, 3))
+            }
+            stop => panic!("unexpected {:?}", stop),
+        }
+        match computer.run(None).unwrap() {
+            Stopped::Watch(hit) => {
+                assert_eq!((hit.ip, hit.access, hit.value), (2, Access::Read, 3))
+            }
+            stop => panic!("unexpected {:?}", stop),
+        }
+        assert!(computer.remove_watchpoint(watchpoint));
+        computer.write_raw(10, 1).unwrap();
+        assert_eq!(computer.run(None).unwrap(), Stopped::Output(1));
+    }
+}
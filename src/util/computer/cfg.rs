@@ -0,0 +1,582 @@
+//! Control-flow graphs of Intcode programs.
+//!
+//! Basic blocks are discovered by following the control flow from address 0: fall-throughs
+//! and conditional jumps (`JNZ`, `JPZ`) with an immediate target. The targets of jumps
+//! reading their target from memory are unknown, such indirect jumps are flagged instead.
+//! They usually return from a function, whose caller saved the address following its own
+//! jump with an `ADD` or `MUL` of immediate operands: an address following an
+//! unconditional jump is also an entry point if such an instruction computes it.
+//!
+//! [`Cfg::to_dot`] exports the graph to the Graphviz DOT language.
+
+use super::{disasm::Line, Instruction, Memory, Mode, Opcode};
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    convert::TryFrom,
+    fmt::Write,
+};
+
+/// How control leaves a basic block.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Terminator {
+    /// Falls through to the block starting right after it
+    Fallthrough,
+    /// Jumps unconditionally to an immediate target
+    Jump,
+    /// Conditional jump to an immediate target
+    Branch,
+    /// Jumps to a target read from memory, falls through otherwise if `conditional`
+    Indirect {
+        conditional: bool,
+    },
+    Halt,
+    /// Runs into a word that does not decode to an instruction, or past the image
+    Invalid,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum EdgeKind {
+    Fallthrough,
+    Jump,
+    /// Conditional jump taken
+    Taken,
+    /// Conditional jump not taken
+    NotTaken,
+}
+
+/// An edge between the blocks starting at `from` and `to`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub kind: EdgeKind,
+}
+
+/// A run of instructions entered at its first one and left after its last one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub lines: Vec<Line>,
+    pub terminator: Terminator,
+}
+
+impl Block {
+    /// Address of the first instruction.
+    #[inline]
+    pub fn start(&self) -> usize {
+        self.lines[0].addr()
+    }
+
+    /// Address following the last instruction.
+    #[inline]
+    pub fn end(&self) -> usize {
+        let last = &self.lines[self.lines.len() - 1];
+        last.addr() + last.size()
+    }
+
+    /// The jump ending the block, if any.
+    #[inline]
+    pub fn jump(&self) -> Option<&Line> {
+        self.lines.last().filter(|line| line.is_branch())
+    }
+}
+
+/// Control-flow graph of a program, see the [module documentation](self).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cfg {
+    blocks: BTreeMap<usize, Block>,
+    edges: Vec<Edge>,
+    entries: BTreeSet<usize>,
+}
+
+/// Decodes the instruction at `addr`, if it lies within `code`.
+fn decode(code: &[i64], addr: usize) -> Option<Line> {
+    let insn = Instruction::decode(*code.get(addr)?).ok()?;
+    let operands = code.get(addr + 1..addr + insn.size())?;
+    let mut params = [0; 3];
+    params[..operands.len()].copy_from_slice(operands);
+    Some(Line::Instruction { addr, insn, params })
+}
+
+/// Whether the jump in `line` is always taken (`Some(true)`), never taken
+/// (`Some(false)`), or depends on memory (`None`).
+fn condition(line: &Line) -> Option<bool> {
+    match *line {
+        Line::Instruction { insn, params, .. } if insn.modes[0] == Mode::Immediate => {
+            Some((params[0] != 0) == (insn.opcode == Opcode::JumpIfTrue))
+        }
+        _ => None,
+    }
+}
+
+/// Immediate target of the jump in `line`.
+pub(super) fn target(line: &Line) -> Option<i64> {
+    match *line {
+        Line::Instruction { insn, params, .. } if insn.modes[1] == Mode::Immediate => {
+            Some(params[1])
+        }
+        _ => None,
+    }
+}
+
+/// Value stored by `line` if its operands are immediate.
+pub(super) fn constant(line: &Line) -> Option<i64> {
+    match *line {
+        Line::Instruction { insn, params, .. }
+            if insn.modes[0] == Mode::Immediate && insn.modes[1] == Mode::Immediate =>
+        {
+            match insn.opcode {
+                Opcode::Add => params[0].checked_add(params[1]),
+                Opcode::Mul => params[0].checked_mul(params[1]),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Instructions found by following the control flow, see the [module
+/// documentation](self).
+pub(super) struct Discovery {
+    pub lines: BTreeMap<usize, Line>,
+    /// Addresses starting a basic block
+    pub leaders: BTreeSet<usize>,
+    /// Address 0 and the return addresses
+    pub entries: BTreeSet<usize>,
+}
+
+/// Decodes the instructions reachable from address 0 and finds the start of the basic
+/// blocks.
+pub(super) fn discover(code: &[i64]) -> Discovery {
+    let mut lines = BTreeMap::new();
+    let mut leaders = BTreeSet::new();
+    let mut entries = BTreeSet::new();
+    let mut constants = BTreeSet::new();
+    // addresses following unconditional jumps, not reached by the control flow
+    let mut after_jumps = BTreeSet::new();
+    let mut owned = vec![false; code.len()];
+    let mut pending = vec![0];
+    leaders.insert(0);
+    entries.insert(0);
+
+    while !pending.is_empty() {
+        // discover the instructions reachable from the entry points
+        while let Some(addr) = pending.pop() {
+            if lines.contains_key(&addr) {
+                continue;
+            }
+            let line = match decode(code, addr) {
+                // an instruction overlapping a known one is not decoded twice
+                Some(line) if !owned[addr..addr + line.size()].contains(&true) => line,
+                _ => continue,
+            };
+            owned[addr..addr + line.size()]
+                .iter_mut()
+                .for_each(|cell| *cell = true);
+            let next = addr + line.size();
+            constants.extend(constant(&line));
+            if line.is_branch() {
+                let taken = target(&line).filter(|_| condition(&line) != Some(false));
+                if let Some(target) = taken.and_then(|t| usize::try_from(t).ok()) {
+                    leaders.insert(target);
+                    pending.push(target);
+                }
+                if condition(&line) != Some(true) {
+                    leaders.insert(next);
+                    pending.push(next);
+                } else {
+                    after_jumps.insert(next);
+                }
+            } else if !matches!(line, Line::Instruction { insn, .. } if insn.opcode == Opcode::Halt)
+            {
+                pending.push(next);
+            }
+            lines.insert(addr, line);
+        }
+
+        // return addresses: the call saved the address following its jump
+        for &addr in &after_jumps {
+            if constants.contains(&(addr as i64)) && entries.insert(addr) {
+                leaders.insert(addr);
+                pending.push(addr);
+            }
+        }
+    }
+    leaders.retain(|addr| lines.contains_key(addr));
+    entries.retain(|addr| lines.contains_key(addr));
+    Discovery {
+        lines,
+        leaders,
+        entries,
+    }
+}
+
+impl Cfg {
+    /// Builds the graph of a program image, such as the one given to
+    /// [`Computer::new`](super::Computer::new).
+    pub fn code(code: &[i64]) -> Cfg {
+        let Discovery {
+            lines,
+            leaders,
+            entries,
+        } = discover(code);
+
+        // split the instructions into blocks
+        let mut blocks = BTreeMap::new();
+        let mut edges = Vec::new();
+        for &leader in &leaders {
+            let mut block = Vec::new();
+            let mut addr = leader;
+            let terminator = loop {
+                let line = match lines.get(&addr) {
+                    Some(line) => line.clone(),
+                    None => break Terminator::Invalid,
+                };
+                if addr != leader && leaders.contains(&addr) {
+                    break Terminator::Fallthrough;
+                }
+                addr += line.size();
+                let opcode = match line {
+                    Line::Instruction { insn, .. } => insn.opcode,
+                    Line::Data { .. } => unreachable!(),
+                };
+                let jump = (line.is_branch(), condition(&line), target(&line));
+                block.push(line);
+                match jump {
+                    (true, Some(false), _) => break Terminator::Fallthrough,
+                    (true, Some(true), Some(_)) => break Terminator::Jump,
+                    (true, None, Some(_)) => break Terminator::Branch,
+                    (true, Some(true), None) => break Terminator::Indirect { conditional: false },
+                    (true, None, None) => break Terminator::Indirect { conditional: true },
+                    _ if opcode == Opcode::Halt => break Terminator::Halt,
+                    _ => (),
+                }
+            };
+
+            // jumps to a negative address have no target block
+            let mut edge = |to: Option<usize>, kind| {
+                if let Some(to) = to {
+                    edges.push(Edge {
+                        from: leader,
+                        to,
+                        kind,
+                    });
+                }
+            };
+            let jump_target = block
+                .last()
+                .and_then(target)
+                .and_then(|t| usize::try_from(t).ok());
+            match terminator {
+                Terminator::Fallthrough => edge(Some(addr), EdgeKind::Fallthrough),
+                Terminator::Jump => edge(jump_target, EdgeKind::Jump),
+                Terminator::Branch => {
+                    edge(jump_target, EdgeKind::Taken);
+                    edge(Some(addr), EdgeKind::NotTaken);
+                }
+                Terminator::Indirect { conditional: true } => edge(Some(addr), EdgeKind::NotTaken),
+                _ => (),
+            }
+            blocks.insert(
+                leader,
+                Block {
+                    lines: block,
+                    terminator,
+                },
+            );
+        }
+        Cfg {
+            blocks,
+            edges,
+            entries,
+        }
+    }
+
+    /// Builds the graph of the program in the memory of a machine.
+    pub fn memory(mem: &Memory) -> Cfg {
+        let code: Vec<_> = (0..mem.len())
+            .map(|addr| mem.get(addr).unwrap_or(0))
+            .collect();
+        Cfg::code(&code)
+    }
+
+    /// Blocks by increasing address.
+    pub fn blocks(&self) -> impl Iterator<Item = &Block> + '_ {
+        self.blocks.values()
+    }
+
+    /// The block starting at `addr`.
+    #[inline]
+    pub fn block(&self, addr: usize) -> Option<&Block> {
+        self.blocks.get(&addr)
+    }
+
+    /// The block holding the instruction at `addr`.
+    pub fn block_containing(&self, addr: usize) -> Option<&Block> {
+        let (_, block) = self.blocks.range(..=addr).next_back()?;
+        block
+            .lines
+            .iter()
+            .any(|line| line.addr() == addr)
+            .then_some(block)
+    }
+
+    #[inline]
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+
+    pub fn successors(&self, addr: usize) -> impl Iterator<Item = &Edge> + '_ {
+        self.edges.iter().filter(move |edge| edge.from == addr)
+    }
+
+    pub fn predecessors(&self, addr: usize) -> impl Iterator<Item = &Edge> + '_ {
+        self.edges.iter().filter(move |edge| edge.to == addr)
+    }
+
+    /// Entry points: address 0 and the return addresses computed by immediate operands.
+    pub fn entries(&self) -> impl Iterator<Item = usize> + '_ {
+        self.entries.iter().copied()
+    }
+
+    /// Jumps whose target is read from memory.
+    pub fn indirect_jumps(&self) -> impl Iterator<Item = &Line> + '_ {
+        self.blocks
+            .values()
+            .filter_map(|block| match block.terminator {
+                Terminator::Indirect { .. } => block.jump(),
+                _ => None,
+            })
+    }
+
+    /// Renders the graph in the Graphviz DOT language.
+    ///
+    /// Blocks are boxes listing their instructions, entry points have a bold border and
+    /// halting blocks a double one. Taken branches are green, branches not taken red,
+    /// and indirect jumps lead to a `?` node through a dashed edge.
+    pub fn to_dot(&self) -> String {
+        let mut res = String::new();
+        writeln!(res, "digraph cfg {{").unwrap();
+        writeln!(res, "    node [shape=box, fontname=\"monospace\"];").unwrap();
+        for (&addr, block) in &self.blocks {
+            let mut label = String::new();
+            for line in &block.lines {
+                write!(label, "{}\\l", line.to_string().trim_start()).unwrap();
+            }
+            if block.terminator == Terminator::Invalid {
+                label.push_str("(invalid)\\l");
+            }
+            let mut attrs = format!("label=\"{}\"", label);
+            if self.entries.contains(&addr) {
+                attrs.push_str(", penwidth=2");
+            }
+            if block.terminator == Terminator::Halt {
+                attrs.push_str(", peripheries=2");
+            }
+            writeln!(res, "    b{} [{}];", addr, attrs).unwrap();
+        }
+
+        let mut missing = BTreeSet::new();
+        for edge in &self.edges {
+            let attrs = match edge.kind {
+                EdgeKind::Fallthrough | EdgeKind::Jump => "",
+                EdgeKind::Taken => " [color=green]",
+                EdgeKind::NotTaken => " [color=red]",
+            };
+            writeln!(res, "    b{} -> b{}{};", edge.from, edge.to, attrs).unwrap();
+            if !self.blocks.contains_key(&edge.to) {
+                missing.insert(edge.to);
+            }
+        }
+        for addr in missing {
+            writeln!(
+                res,
+                "    b{} [label=\"{}: (invalid)\", shape=plaintext];",
+                addr, addr
+            )
+            .unwrap();
+        }
+
+        let mut indirect = self.indirect_jumps().peekable();
+        if indirect.peek().is_some() {
+            writeln!(res, "    indirect [label=\"?\", shape=diamond];").unwrap();
+        }
+        for line in indirect {
+            let block = self.block_containing(line.addr()).unwrap();
+            writeln!(res, "    b{} -> indirect [style=dashed];", block.start()).unwrap();
+        }
+        writeln!(res, "}}").unwrap();
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        asm::assemble,
+        examples::{DAY05, FIB},
+    };
+    use super::*;
+
+    fn edge(from: usize, to: usize, kind: EdgeKind) -> Edge {
+        Edge { from, to, kind }
+    }
+
+    fn terminators(cfg: &Cfg) -> Vec<(usize, Terminator)> {
+        cfg.blocks()
+            .map(|block| (block.start(), block.terminator))
+            .collect()
+    }
+
+    #[test]
+    fn edges() {
+        let cfg = Cfg::code(DAY05);
+        assert_eq!(
+            terminators(&cfg),
+            [
+                (0, Terminator::Branch),
+                (9, Terminator::Branch),
+                (16, Terminator::Jump),
+                (22, Terminator::Jump),
+                (31, Terminator::Jump),
+                (36, Terminator::Jump),
+                (46, Terminator::Halt),
+            ]
+        );
+        assert_eq!(
+            cfg.edges(),
+            [
+                edge(0, 22, EdgeKind::Taken),
+                edge(0, 9, EdgeKind::NotTaken),
+                edge(9, 31, EdgeKind::Taken),
+                edge(9, 16, EdgeKind::NotTaken),
+                edge(16, 36, EdgeKind::Jump),
+                edge(22, 46, EdgeKind::Jump),
+                edge(31, 46, EdgeKind::Jump),
+                edge(36, 46, EdgeKind::Jump),
+            ]
+        );
+        assert_eq!(cfg.predecessors(46).count(), 3);
+        assert_eq!(cfg.successors(0).count(), 2);
+        assert_eq!(cfg.block(22).unwrap().end(), 31);
+        assert_eq!(cfg.block_containing(26).unwrap().start(), 22);
+        // a parameter, or a cell not reached by the control flow
+        assert!(cfg.block_containing(27).is_none());
+        assert!(cfg.block_containing(19).is_none());
+
+        // `JNZ #0` never jumps, `JPZ #0` always does
+        let cfg = Cfg::code(&[1105, 0, 6, 1106, 0, 7, 99, 99]);
+        assert_eq!(
+            terminators(&cfg),
+            [
+                (0, Terminator::Fallthrough),
+                (3, Terminator::Jump),
+                (7, Terminator::Halt)
+            ]
+        );
+        assert_eq!(
+            cfg.edges(),
+            [
+                edge(0, 3, EdgeKind::Fallthrough),
+                edge(3, 7, EdgeKind::Jump)
+            ]
+        );
+    }
+
+    #[test]
+    fn calls() {
+        let cfg = Cfg::code(&assemble(FIB).unwrap());
+        assert_eq!(cfg.entries().collect::<Vec<_>>(), [0, 11, 41, 60]);
+        assert_eq!(
+            terminators(&cfg),
+            [
+                (0, Terminator::Jump),
+                (11, Terminator::Halt),
+                (14, Terminator::Branch),
+                (21, Terminator::Indirect { conditional: false }),
+                (28, Terminator::Jump),
+                (41, Terminator::Jump),
+                (60, Terminator::Indirect { conditional: false }),
+            ]
+        );
+        let indirect: Vec<_> = cfg.indirect_jumps().map(Line::addr).collect();
+        assert_eq!(indirect, [25, 66]);
+        // returns have no edges
+        assert_eq!(cfg.predecessors(11).count(), 0);
+        assert_eq!(cfg.predecessors(14).count(), 3);
+    }
+
+    #[test]
+    fn invalid_targets() {
+        // past the image
+        let cfg = Cfg::code(&[1105, 1, 7, 99]);
+        assert_eq!(terminators(&cfg), [(0, Terminator::Jump)]);
+        assert_eq!(cfg.edges(), [edge(0, 7, EdgeKind::Jump)]);
+        // the fall-through is past the image
+        let cfg = Cfg::code(&[1005, 10, 0]);
+        assert_eq!(terminators(&cfg), [(0, Terminator::Branch)]);
+        assert_eq!(
+            cfg.edges(),
+            [edge(0, 0, EdgeKind::Taken), edge(0, 3, EdgeKind::NotTaken)]
+        );
+        // a data word
+        let cfg = Cfg::code(&[1105, 1, 3, 42]);
+        assert_eq!(cfg.edges(), [edge(0, 3, EdgeKind::Jump)]);
+        assert!(cfg.block(3).is_none());
+        // negative addresses have no block
+        let cfg = Cfg::code(&[1106, 0, -1]);
+        assert_eq!(terminators(&cfg), [(0, Terminator::Jump)]);
+        assert!(cfg.edges().is_empty());
+        // runs into a data word
+        let cfg = Cfg::code(&[1, 0, 0, 0, 42]);
+        assert_eq!(terminators(&cfg), [(0, Terminator::Invalid)]);
+    }
+
+    #[test]
+    fn overlapping() {
+        // the jump lands on the parameter of the addition
+        let cfg = Cfg::code(&[1005, 9, 4, 1101, 99, 0, 0, 99, 0, 0]);
+        assert_eq!(
+            terminators(&cfg),
+            [(0, Terminator::Branch), (3, Terminator::Halt)]
+        );
+        assert_eq!(
+            cfg.edges(),
+            [edge(0, 4, EdgeKind::Taken), edge(0, 3, EdgeKind::NotTaken)]
+        );
+        assert!(cfg.block(4).is_none());
+    }
+
+    #[test]
+    fn dot() {
+        let cfg = Cfg::code(&[3, 12, 1005, 12, 8, 104, 0, 99, 105, 1, 11, 0, 0]);
+        assert_eq!(
+            cfg.to_dot(),
+            "digraph cfg {
+    node [shape=box, fontname=\"monospace\"];
+    b0 [label=\"0: IPT [12]\\l2: JNZ [12], #8\\l\", penwidth=2];
+    b5 [label=\"5: OUT #0\\l7: HLT\\l\", peripheries=2];
+    b8 [label=\"8: JNZ #1, [11]\\l\"];
+    b0 -> b8 [color=green];
+    b0 -> b5 [color=red];
+    indirect [label=\"?\", shape=diamond];
+    b8 -> indirect [style=dashed];
+}
+"
+        );
+        assert_eq!(
+            Cfg::code(&[1005, 10, 0]).to_dot(),
+            "digraph cfg {
+    node [shape=box, fontname=\"monospace\"];
+    b0 [label=\"0: JNZ [10], #0\\l\", penwidth=2];
+    b0 -> b0 [color=green];
+    b0 -> b3 [color=red];
+    b3 [label=\"3: (invalid)\", shape=plaintext];
+}
+"
+        );
+        assert!(Cfg::code(&[1, 0, 0, 0, 42])
+            .to_dot()
+            .contains("b0 [label=\"0: ADD [0], [0], [0]\\l(invalid)\\l\", penwidth=2];"));
+    }
+}
@@ -0,0 +1,282 @@
+//! Code coverage: executed instructions and conditional jump edges.
+
+use super::{disasm::Line, Computer, Word};
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::{self, Write},
+    sync::{Arc, Mutex},
+};
+
+/// Outcomes seen for a conditional jump.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Edges {
+    pub taken: bool,
+    pub not_taken: bool,
+}
+
+/// Addresses and branch edges executed by one or more runs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Coverage {
+    addrs: BTreeSet<i64>,
+    branches: BTreeMap<i64, Edges>,
+}
+
+/// Covered fraction of a program, see [`Coverage::summary`].
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Summary {
+    pub instructions: usize,
+    pub covered_instructions: usize,
+    /// Two per conditional jump
+    pub edges: usize,
+    pub covered_edges: usize,
+}
+
+#[inline]
+fn percent(covered: usize, total: usize) -> f64 {
+    if total == 0 {
+        100.0
+    } else {
+        covered as f64 * 100.0 / total as f64
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "instructions: {}/{} ({:.1}%), branch edges: {}/{} ({:.1}%)",
+            self.covered_instructions,
+            self.instructions,
+            percent(self.covered_instructions, self.instructions),
+            self.covered_edges,
+            self.edges,
+            percent(self.covered_edges, self.edges)
+        )
+    }
+}
+
+impl Coverage {
+    #[inline]
+    pub fn is_covered(&self, addr: i64) -> bool {
+        self.addrs.contains(&addr)
+    }
+
+    /// Executed instruction addresses, in increasing order.
+    pub fn addresses(&self) -> impl Iterator<Item = i64> + '_ {
+        self.addrs.iter().copied()
+    }
+
+    /// Outcomes seen for the conditional jump at `addr`.
+    #[inline]
+    pub fn edges(&self, addr: i64) -> Edges {
+        self.branches.get(&addr).copied().unwrap_or_default()
+    }
+
+    /// Adds the coverage of another run.
+    pub fn merge(&mut self, other: &Coverage) {
+        self.addrs.extend(&other.addrs);
+        for (&addr, edges) in &other.branches {
+            let entry = self.branches.entry(addr).or_default();
+            entry.taken |= edges.taken;
+            entry.not_taken |= edges.not_taken;
+        }
+    }
+
+    /// Covered fraction of the instructions in `lines`, a disassembly of the program.
+    pub fn summary<I>(&self, lines: I) -> Summary
+    where
+        I: IntoIterator<Item = Line>,
+    {
+        let mut summary = Summary::default();
+        for line in lines {
+            if let Line::Data { .. } = line {
+                continue;
+            }
+            let addr = line.addr() as i64;
+            summary.instructions += 1;
+            summary.covered_instructions += self.is_covered(addr) as usize;
+            if line.is_branch() {
+                let edges = self.edges(addr);
+                summary.edges += 2;
+                summary.covered_edges += edges.taken as usize + edges.not_taken as usize;
+            }
+        }
+        summary
+    }
+
+    /// Renders `lines` with coverage marks, followed by the summary.
+    ///
+    /// Executed instructions are marked with `*`. Conditional jumps show `T` if they
+    /// jumped and `N` if they fell through, `-` for an outcome never seen.
+    pub fn annotate<I>(&self, lines: I) -> String
+    where
+        I: IntoIterator<Item = Line>,
+    {
+        let lines: Vec<_> = lines.into_iter().collect();
+        let mut res = String::new();
+        for line in &lines {
+            let addr = line.addr() as i64;
+            let executed = match line {
+                Line::Instruction { .. } if self.is_covered(addr) => '*',
+                _ => ' ',
+            };
+            let edges = if line.is_branch() {
+                let edges = self.edges(addr);
+                format!(
+                    "{}{}",
+                    if edges.taken { 'T' } else { '-' },
+                    if edges.not_taken { 'N' } else { '-' }
+                )
+            } else {
+                "  ".to_owned()
+            };
+            writeln!(res, "{} {} {}", executed, edges, line).unwrap();
+        }
+        writeln!(res, "{}", self.summary(lines)).unwrap();
+        res
+    }
+
+    fn record(&mut self, ip: i64, branch: Option<bool>) {
+        self.addrs.insert(ip);
+        if let Some(taken) = branch {
+            let edges = self.branches.entry(ip).or_default();
+            if taken {
+                edges.taken = true;
+            } else {
+                edges.not_taken = true;
+            }
+        }
+    }
+}
+
+impl<W: Word> Computer<W> {
+    /// Starts recording the executed instructions and branch edges, discarding the
+    /// coverage recorded so far.
+    ///
+    /// The record is shared with the clones of the machine made afterwards, their runs
+    /// are merged into it as they go.
+    pub fn start_coverage(&mut self) {
+        self.coverage = Some(Arc::new(Mutex::new(Coverage::default())));
+    }
+
+    /// Stops recording, returns the coverage.
+    pub fn stop_coverage(&mut self) -> Option<Coverage> {
+        let coverage = self.coverage.take()?;
+        let coverage = coverage.lock().unwrap().clone();
+        Some(coverage)
+    }
+
+    /// Coverage recorded so far.
+    pub fn coverage(&self) -> Option<Coverage> {
+        self.coverage.as_ref().map(|c| c.lock().unwrap().clone())
+    }
+
+    /// Marks the completed instruction at `ip` as covered, and for a conditional jump
+    /// the edge it followed: `Some(true)` to the target, `Some(false)` to the next
+    /// instruction.
+    #[inline]
+    pub(super) fn coverage_end(&mut self, ip: i64, branch: Option<bool>) {
+        if let Some(coverage) = &self.coverage {
+            coverage.lock().unwrap().record(ip, branch);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{disasm::Disasm, examples::DAY05, Memory, Stopped};
+    use super::*;
+
+    /// Outputs 0 then 1, skipping the 0 if its input is non-zero. Ends with two data words.
+    const CHOICE: &[i64] = &[3, 11, 1005, 11, 7, 104, 0, 104, 1, 99, 42, 0];
+
+    fn cover(code: &[i64], input: i64) -> Coverage {
+        let mut computer = Computer::new(code, None);
+        computer.start_coverage();
+        computer.push_input(input);
+        computer.run_buffered().unwrap();
+        computer.stop_coverage().unwrap()
+    }
+
+    fn lines(code: &[i64]) -> Vec<Line> {
+        Disasm::memory(&Memory::new(code, None)).collect()
+    }
+
+    #[test]
+    fn annotate() {
+        let coverage = cover(CHOICE, 5);
+        assert_eq!(coverage.addresses().collect::<Vec<_>>(), [0, 2, 7, 9]);
+        // data words are neither marked nor counted
+        assert_eq!(
+            coverage.annotate(lines(CHOICE)),
+            "\
+*        0: IPT [11]
+* T-     2: JNZ [11], #7
+         5: OUT #0
+*        7: OUT #1
+*        9: HLT
+        10: DB 42
+        11: DB 0
+instructions: 4/5 (80.0%), branch edges: 1/2 (50.0%)
+"
+        );
+    }
+
+    #[test]
+    fn merge() {
+        let (jumped, fell_through) = (cover(CHOICE, 1), cover(CHOICE, 0));
+        let mut both = jumped.clone();
+        both.merge(&fell_through);
+        assert_eq!(
+            both.edges(2),
+            Edges {
+                taken: true,
+                not_taken: true
+            }
+        );
+        // the runs only differ by the first output
+        assert_eq!(both.addresses().count(), 5);
+        let mut other = fell_through.clone();
+        other.merge(&jumped);
+        assert_eq!(other, both);
+        other.merge(&jumped);
+        assert_eq!(other, both);
+
+        // both edges of the two tests, only the taken edge of the four unconditional jumps
+        let mut coverage = Coverage::default();
+        for input in 7..=9 {
+            coverage.merge(&cover(DAY05, input));
+            let summary = coverage.summary(lines(DAY05));
+            assert_eq!(summary.edges, 2 * 6);
+            assert_eq!(summary.covered_edges == 2 * 2 + 4, input == 9);
+        }
+        let summary = coverage.summary(lines(DAY05));
+        assert_eq!(summary.covered_instructions, summary.instructions);
+        assert_eq!(
+            coverage.summary(Vec::new()).to_string(),
+            "instructions: 0/0 (100.0%), branch edges: 0/0 (100.0%)"
+        );
+    }
+
+    #[test]
+    fn recording() {
+        let mut computer = Computer::new(CHOICE, None);
+        assert!(computer.coverage().is_none());
+        computer.start_coverage();
+        // a read waiting for input is not covered yet
+        assert_eq!(computer.run(None).unwrap(), Stopped::NeedsInput);
+        assert_eq!(computer.coverage().unwrap(), Coverage::default());
+        let mut clone = computer.clone();
+        assert_eq!(computer.run(Some(0)).unwrap(), Stopped::Output(0));
+        let coverage = computer.coverage().unwrap();
+        assert!(!coverage.edges(2).taken && coverage.edges(2).not_taken);
+        assert!(!coverage.is_covered(7));
+        // the clone follows the other edge, into the same record
+        assert_eq!(clone.run(Some(1)).unwrap(), Stopped::Output(1));
+        let coverage = computer.coverage().unwrap();
+        assert!(coverage.edges(2).taken && coverage.is_covered(7));
+        assert_eq!(computer.stop_coverage(), clone.coverage());
+        assert_eq!(computer.stop_coverage(), None);
+    }
+}
@@ -0,0 +1,435 @@
+//! Crash dumps: the state of a machine at the time of an error, saved for post-mortem
+//! inspection.
+//!
+//! # File format
+//!
+//! A crash dump is a text file starting with the line `# intcode crash dump v1`. The
+//! [error report](super::Error::report) follows as comment lines starting with `#`, then
+//! `key: value` lines:
+//!
+//! ```text
+//! error: IntCode error at 12: illegal opcode 42
+//! ip: 12
+//! rbo: 0
+//! halted: false
+//! limit: none
+//! length: 22
+//! inputs: 1 2
+//! outputs:
+//! recent: 0 4 8 11
+//! memory:
+//! 0: 1001 20 1 20 1007 20 3 21 1005 21 0 9 42 0 0 0
+//! 16: 0 0 0 0 3 0 0 0 0 0 0 0 0 0 0 0
+//! ```
+//!
+//! `recent` lists the addresses of the last executed instructions, oldest first. Memory
+//! lines hold the address of their first cell and 16 cells, lines of zeros are left out.
+
+use super::{disasm::Disasm, trace::invalid_data, Computer, Error, Memory, Word};
+
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, prelude::*, BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+const HEADER: &str = "# intcode crash dump v1";
+/// Cells per memory line.
+const LINE_CELLS: usize = 16;
+
+/// A crash dump loaded from a file.
+#[derive(Debug, Clone)]
+pub struct CrashDump<W = i64> {
+    error: String,
+    ip: i64,
+    rbo: i64,
+    halted: bool,
+    recent: Vec<i64>,
+    inputs: VecDeque<W>,
+    outputs: VecDeque<W>,
+    mem: Memory<W>,
+}
+
+impl<W: Word> CrashDump<W> {
+    /// Message of the error that caused the dump.
+    #[inline]
+    pub fn error(&self) -> &str {
+        &self.error
+    }
+
+    #[inline]
+    pub fn ip(&self) -> i64 {
+        self.ip
+    }
+
+    #[inline]
+    pub fn rbo(&self) -> i64 {
+        self.rbo
+    }
+
+    #[inline]
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Addresses of the last instructions executed before the error, oldest first.
+    #[inline]
+    pub fn recent(&self) -> &[i64] {
+        &self.recent
+    }
+
+    /// Inputs queued but not read yet.
+    #[inline]
+    pub fn pending_inputs(&self) -> &VecDeque<W> {
+        &self.inputs
+    }
+
+    /// Outputs buffered but not taken yet.
+    #[inline]
+    pub fn outputs(&self) -> &VecDeque<W> {
+        &self.outputs
+    }
+
+    #[inline]
+    pub fn memory(&self) -> &Memory<W> {
+        &self.mem
+    }
+
+    pub fn read_from<R: BufRead>(input: R) -> io::Result<CrashDump<W>> {
+        let mut lines = input.lines();
+        if lines.next().transpose()?.as_deref() != Some(HEADER) {
+            return Err(invalid_data("not an Intcode crash dump"));
+        }
+
+        let mut error = None;
+        let mut ip = None;
+        let mut rbo = None;
+        let mut halted = None;
+        let mut limit = None;
+        let mut len = None;
+        let mut inputs = None;
+        let mut outputs = None;
+        let mut recent = None;
+        let mut cells = Vec::new();
+        let mut in_memory = false;
+        for line in lines {
+            let line = line?;
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = split_line(&line)?;
+            if in_memory {
+                let addr: usize = number(key)?;
+                for (i, word) in value.split_whitespace().enumerate() {
+                    let addr = addr.checked_add(i).ok_or_else(|| invalid_word(key))?;
+                    cells.push((addr, number::<W>(word)?));
+                }
+                continue;
+            }
+            match key {
+                "error" => error = Some(value.to_owned()),
+                "ip" => ip = Some(number(value)?),
+                "rbo" => rbo = Some(number(value)?),
+                "halted" => halted = Some(number(value)?),
+                "limit" if value == "none" => limit = Some(None),
+                "limit" => limit = Some(Some(number(value)?)),
+                "length" => len = Some(number(value)?),
+                "inputs" => inputs = Some(words(value)?),
+                "outputs" => outputs = Some(words(value)?),
+                "recent" => recent = Some(words(value)?),
+                "memory" => in_memory = true,
+                _ => return Err(invalid_data(&format!("unknown crash dump key `{}`", key))),
+            }
+        }
+        if !in_memory {
+            return Err(missing("memory"));
+        }
+
+        let mut mem = Memory::new(&[], limit.ok_or_else(|| missing("limit"))?);
+        for (addr, value) in cells {
+            // zeros past the memory limit pad the last line
+            if !value.is_zero() && !mem.set(addr, value) {
+                return Err(invalid_data("memory cell beyond the memory limit"));
+            }
+        }
+        mem.set_len(len.ok_or_else(|| missing("length"))?);
+        Ok(CrashDump {
+            error: error.ok_or_else(|| missing("error"))?,
+            ip: ip.ok_or_else(|| missing("ip"))?,
+            rbo: rbo.ok_or_else(|| missing("rbo"))?,
+            halted: halted.ok_or_else(|| missing("halted"))?,
+            recent: recent.ok_or_else(|| missing("recent"))?.into(),
+            inputs: inputs.ok_or_else(|| missing("inputs"))?,
+            outputs: outputs.ok_or_else(|| missing("outputs"))?,
+            mem,
+        })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<CrashDump<W>> {
+        CrashDump::read_from(BufReader::new(File::open(path)?))
+    }
+}
+
+impl CrashDump {
+    /// Disassembles the memory of the crashed machine.
+    #[inline]
+    pub fn disasm(&self) -> Disasm<'_> {
+        Disasm::memory(&self.mem)
+    }
+}
+
+fn split_line(line: &str) -> io::Result<(&str, &str)> {
+    let colon = line
+        .find(':')
+        .ok_or_else(|| invalid_data(&format!("invalid crash dump line `{}`", line)))?;
+    Ok((line[..colon].trim(), line[colon + 1..].trim()))
+}
+
+fn number<T: std::str::FromStr>(s: &str) -> io::Result<T> {
+    s.parse().map_err(|_| invalid_word(s))
+}
+
+fn words<T: std::str::FromStr>(s: &str) -> io::Result<VecDeque<T>> {
+    s.split_whitespace().map(number).collect()
+}
+
+fn invalid_word(word: &str) -> io::Error {
+    invalid_data(&format!("invalid value `{}`", word))
+}
+
+fn missing(key: &str) -> io::Error {
+    invalid_data(&format!("missing crash dump key `{}`", key))
+}
+
+/// Space-separated `values`.
+fn join<'a, W: Word>(values: impl IntoIterator<Item = &'a W>) -> String {
+    values
+        .into_iter()
+        .map(W::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Writes a `key: value` line, without trailing space if `value` is empty.
+fn field<O: Write>(out: &mut O, key: &str, value: &str) -> io::Result<()> {
+    if value.is_empty() {
+        writeln!(out, "{}:", key)
+    } else {
+        writeln!(out, "{}: {}", key, value)
+    }
+}
+
+impl<W: Word> Computer<W> {
+    /// Writes a crash dump to `path` whenever running the machine fails.
+    ///
+    /// Only errors of [`run`](Computer::run) and [`step`](Computer::step) (and the
+    /// methods built on them) cause a dump, failing to write it does not change the
+    /// error returned. The file is overwritten by each new crash.
+    #[inline]
+    pub fn enable_crash_dumps<P: Into<PathBuf>>(&mut self, path: P) {
+        self.crash_dump = Some(path.into());
+    }
+
+    #[inline]
+    pub fn disable_crash_dumps(&mut self) {
+        self.crash_dump = None;
+    }
+
+    /// Writes the state of the machine after it failed with `err`.
+    pub fn write_crash_dump<O: Write>(&self, err: &Error<W>, mut out: O) -> io::Result<()> {
+        writeln!(out, "{}", HEADER)?;
+        for line in err.report().to_string().lines() {
+            writeln!(out, "# {}", line)?;
+        }
+        writeln!(out, "error: {}", err)?;
+        writeln!(out, "ip: {}", self.ip)?;
+        writeln!(out, "rbo: {}", self.rbo)?;
+        writeln!(out, "halted: {}", self.halted)?;
+        match self.mem.limit() {
+            Some(limit) => writeln!(out, "limit: {}", limit)?,
+            None => writeln!(out, "limit: none")?,
+        }
+        writeln!(out, "length: {}", self.mem.len())?;
+        field(&mut out, "inputs", &join(&self.inputs))?;
+        field(&mut out, "outputs", &join(&self.outputs))?;
+        let recent: Vec<_> = err
+            .recent()
+            .iter()
+            .map(|insn| insn.addr.to_string())
+            .collect();
+        field(&mut out, "recent", &recent.join(" "))?;
+        writeln!(out, "memory:")?;
+        for (start, page) in self.mem.pages() {
+            for (i, cells) in page.chunks(LINE_CELLS).enumerate() {
+                if cells.iter().any(|cell| !cell.is_zero()) {
+                    writeln!(out, "{}: {}", start + i * LINE_CELLS, join(cells))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn save_crash_dump<P: AsRef<Path>>(&self, err: &Error<W>, path: P) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write_crash_dump(err, &mut out)?;
+        out.flush()
+    }
+
+    /// Dumps the machine if crash dumps are enabled, returns `err` unchanged.
+    pub(super) fn crashed(&self, err: Error<W>) -> Error<W> {
+        if let Some(path) = &self.crash_dump {
+            // the error matters more than the dump
+            let _ = self.save_crash_dump(&err, path);
+        }
+        err
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::ErrorKind;
+    use super::*;
+
+    use std::{env, fs, process};
+
+    /// Reads two values, outputs their sum, writes past a page boundary and fails on
+    /// an illegal opcode.
+    const CODE: &[i64] = &[3, 20, 3, 21, 1, 20, 21, 22, 4, 22, 1101, 0, -9, 1500, 42];
+
+    fn crash() -> (Computer, Error) {
+        let mut computer = Computer::new(CODE, Some(2000));
+        computer.push_inputs(vec![4, 5, 6]);
+        computer.run_buffered().unwrap_err();
+        computer.push_input(-7);
+        let err = computer.run_buffered().unwrap_err();
+        (computer, err)
+    }
+
+    fn dump(computer: &Computer, err: &Error) -> String {
+        let mut out = Vec::new();
+        computer.write_crash_dump(err, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn read(dump: &str) -> io::Result<CrashDump> {
+        CrashDump::read_from(dump.as_bytes())
+    }
+
+    #[test]
+    fn round_trip() {
+        let (computer, err) = crash();
+        assert_eq!(err.location(), 14);
+        let text = dump(&computer, &err);
+        assert!(text.starts_with("# intcode crash dump v1\n# IntCode error at 14"));
+        assert!(text.contains("\ninputs: 6 -7\noutputs: 9\n"));
+        assert!(text.contains("\n1488: 0 0 0 0 0 0 0 0 0 0 0 0 -9 0 0 0\n"));
+
+        let dump = read(&text).unwrap();
+        assert_eq!(dump.error(), err.to_string());
+        assert_eq!(dump.ip(), 14);
+        assert_eq!(dump.rbo(), 0);
+        assert!(!dump.is_halted());
+        let recent: Vec<i64> = err.recent().iter().map(|insn| insn.addr).collect();
+        assert_eq!(dump.recent(), &recent[..]);
+        assert_eq!(dump.pending_inputs(), &[6, -7]);
+        assert_eq!(dump.outputs(), &[9]);
+        assert_eq!(dump.memory(), computer.memory());
+        assert_eq!(dump.memory().len(), 1501);
+        assert_eq!(dump.memory().limit(), Some(2000));
+        assert_eq!(dump.disasm().next().unwrap().to_string(), "    0: IPT [20]");
+
+        // empty queues, unbounded memory
+        let mut computer = Computer::new(&[99], None);
+        computer.run(None).unwrap();
+        let err = computer.error(ErrorKind::BudgetExhausted);
+        let dump = read(&self::dump(&computer, &err)).unwrap();
+        assert!(dump.is_halted());
+        assert!(dump.pending_inputs().is_empty() && dump.outputs().is_empty());
+        assert_eq!(dump.recent(), [0]);
+        assert_eq!(dump.memory().limit(), None);
+        assert_eq!(dump.memory(), computer.memory());
+    }
+
+    #[test]
+    fn files() {
+        let path = env::temp_dir().join(format!("intcode-crash-{}", process::id()));
+        let mut computer = Computer::new(CODE, None);
+        computer.enable_crash_dumps(&path);
+        computer.push_inputs(vec![1, 2]);
+        let err = computer.run_buffered().unwrap_err();
+        let dump = CrashDump::<i64>::load(&path);
+        fs::remove_file(&path).unwrap();
+        let dump = dump.unwrap();
+        assert_eq!(dump.error(), err.to_string());
+        assert_eq!(dump.memory(), computer.memory());
+
+        // a dump that cannot be written does not change the error
+        computer.enable_crash_dumps(env::temp_dir().join("missing").join("dump"));
+        let again = computer.run_buffered().unwrap_err();
+        assert_eq!(again.to_string(), err.to_string());
+        computer.disable_crash_dumps();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn truncated() {
+        let (computer, err) = crash();
+        let text = dump(&computer, &err);
+        let memory = text.find("\nmemory:").unwrap();
+        for (end, _) in text.match_indices('\n').filter(|&(i, _)| i <= memory) {
+            assert!(read(&text[..end]).is_err(), "{}", &text[..end]);
+        }
+        assert!(read(&text[..memory + 9]).is_ok());
+    }
+
+    #[test]
+    fn corrupt() {
+        let (computer, err) = crash();
+        let text = dump(&computer, &err);
+        let error = |text: &str| read(text).unwrap_err().to_string();
+
+        assert_eq!(error(""), "not an Intcode crash dump");
+        assert_eq!(
+            error(&text.replacen("v1", "v2", 1)),
+            "not an Intcode crash dump"
+        );
+        for key in &[
+            "error", "ip", "rbo", "halted", "limit", "length", "inputs", "recent",
+        ] {
+            let line = text
+                .lines()
+                .find(|l| l.starts_with(&format!("{}:", key)))
+                .unwrap();
+            assert_eq!(
+                error(&text.replace(&format!("{}\n", line), "")),
+                format!("missing crash dump key `{}`", key)
+            );
+        }
+        assert_eq!(
+            error(&text.replace("rbo: 0", "rb: 0")),
+            "unknown crash dump key `rb`"
+        );
+        assert_eq!(
+            error(&text.replace("halted: false", "halted: 0")),
+            "invalid value `0`"
+        );
+        assert_eq!(
+            error(&text.replace("inputs: 6 -7", "inputs: 6 x")),
+            "invalid value `x`"
+        );
+        assert_eq!(
+            error(&format!("{}oops\n", text)),
+            "invalid crash dump line `oops`"
+        );
+        assert_eq!(
+            error(&format!("{}1999: 0 1\n", text)),
+            "memory cell beyond the memory limit"
+        );
+        assert!(read(&format!("{}1999: 1 0\n", text)).is_ok());
+        assert_eq!(
+            error(&format!("{}{}: 0 1\n", text, usize::MAX)),
+            format!("invalid value `{}`", usize::MAX)
+        );
+    }
+}
@@ -0,0 +1,954 @@
+//! Decompiler from Intcode to structured pseudocode.
+//!
+//! Works on the [control-flow graph](super::cfg) of a program:
+//!
+//! - Functions start at address 0 (`main`) and at the targets of calls, unconditional
+//!   jumps after which the caller resumes at a return address it saved (see
+//!   [`Cfg::entries`]).
+//! - The relative base is tracked through each function, so that relative operands name
+//!   stack slots: `v3` is the cell 3 words past the relative base at the entry of the
+//!   function, whatever the `RBO` adjustments in between. A call shows the slot where the
+//!   frame of the callee starts: its `v0`, usually the return address, which an indirect
+//!   jump reads to `return`. Position operands are named after their address, `m42`, and
+//!   relative operands where the relative base is unknown are shown as `rb[3]`.
+//! - Loops are recovered from back edges and `if`/`else` regions from post-dominators,
+//!   control flow that does not fit is rendered with `goto`. So are jumps and
+//!   fall-throughs to an address without instructions, marked as invalid targets.
+//!
+//! ```text
+//! fn f14() {
+//!     v3 = v1 < 2
+//!     if v1 >= 2 {
+//!         v5 = v1 - 1
+//!         f14() // frame at v4
+//!         v3 = v6
+//!         ...
+//!         return
+//!     } else {
+//!         v2 = v1
+//!         return
+//!     }
+//! }
+//! ```
+
+use super::{
+    cfg::{self, Block, Cfg, Terminator},
+    disasm::Line,
+    Mode, Opcode,
+};
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    convert::TryFrom,
+    fmt::{self, Write},
+};
+
+/// Virtual node following every exit of a function, for post-dominators.
+const EXIT: usize = usize::MAX;
+
+/// Condition of a conditional jump.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Cond {
+    lhs: String,
+    op: &'static str,
+    rhs: String,
+}
+
+impl Cond {
+    fn negate(&self) -> Cond {
+        let op = match self.op {
+            "<" => ">=",
+            ">=" => "<",
+            "==" => "!=",
+            _ => "==",
+        };
+        Cond {
+            lhs: self.lhs.clone(),
+            op,
+            rhs: self.rhs.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Cond {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} {}", self.lhs, self.op, self.rhs)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Target {
+    Block(usize),
+    Return,
+    /// Jump to an address computed at run time
+    Indirect(String),
+}
+
+/// How control leaves a block, within its function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Flow {
+    Goto(Target),
+    Branch {
+        cond: Cond,
+        taken: Target,
+        not_taken: usize,
+    },
+    Call {
+        callee: String,
+        frame: String,
+        ret: usize,
+    },
+    Halt,
+    /// Runs into a word that is not an instruction, at this address
+    Invalid(usize),
+}
+
+impl Flow {
+    fn successors(&self) -> Vec<usize> {
+        match *self {
+            Flow::Goto(Target::Block(to)) => vec![to],
+            Flow::Branch {
+                taken: Target::Block(taken),
+                not_taken,
+                ..
+            } => vec![taken, not_taken],
+            Flow::Branch { not_taken, .. } => vec![not_taken],
+            Flow::Call { ret, .. } => vec![ret],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Whether the function may end after the block.
+    fn exits(&self) -> bool {
+        match self {
+            Flow::Goto(Target::Block(_)) | Flow::Call { .. } => false,
+            Flow::Branch { taken, .. } => !matches!(taken, Target::Block(_)),
+            _ => true,
+        }
+    }
+}
+
+/// A block with its statements.
+struct Node {
+    stmts: Vec<String>,
+    flow: Flow,
+}
+
+/// Relative base, as an offset from its value at the entry of the function.
+type Offset = Option<i64>;
+
+/// Name of the cell designated by a parameter.
+fn operand(mode: Mode, param: i64, offset: Offset) -> String {
+    match mode {
+        Mode::Immediate => param.to_string(),
+        Mode::Position if param < 0 => format!("m[{}]", param),
+        Mode::Position => format!("m{}", param),
+        Mode::Relative => match offset.and_then(|o| o.checked_add(param)) {
+            Some(slot) if slot < 0 => format!("vm{}", -slot),
+            Some(slot) => format!("v{}", slot),
+            None => format!("rb[{}]", param),
+        },
+    }
+}
+
+/// Name of the cell written by a destination parameter.
+#[inline]
+fn destination(mode: Mode, param: i64, offset: Offset) -> String {
+    match mode {
+        // written like a position parameter
+        Mode::Immediate => operand(Mode::Position, param, offset),
+        _ => operand(mode, param, offset),
+    }
+}
+
+fn parts(line: &Line) -> (Opcode, [Mode; 3], [i64; 3]) {
+    match *line {
+        Line::Instruction { insn, params, .. } => (insn.opcode, insn.modes, params),
+        Line::Data { .. } => unreachable!("blocks only hold instructions"),
+    }
+}
+
+/// Pseudocode of a non-jump instruction, `None` if it has none. Updates `offset` on
+/// `RBO`.
+fn statement(line: &Line, offset: &mut Offset) -> Option<String> {
+    let (opcode, modes, params) = parts(line);
+    let arg = |i: usize| operand(modes[i], params[i], *offset);
+    let dest = |i: usize| destination(modes[i], params[i], *offset);
+    let expr = match opcode {
+        Opcode::Add => match (arg(0), arg(1)) {
+            (a, b) if b == "0" => a,
+            (a, b) if a == "0" => b,
+            (a, b) if modes[1] == Mode::Immediate && params[1] < 0 => {
+                format!("{} - {}", a, &b[1..])
+            }
+            (a, b) => format!("{} + {}", a, b),
+        },
+        Opcode::Mul => match (arg(0), arg(1)) {
+            (a, b) if b == "1" => a,
+            (a, b) if a == "1" => b,
+            (a, b) if b == "-1" => format!("-{}", a),
+            (a, b) => format!("{} * {}", a, b),
+        },
+        Opcode::LessThan => format!("{} < {}", arg(0), arg(1)),
+        Opcode::Equals => format!("{} == {}", arg(0), arg(1)),
+        Opcode::Input => return Some(format!("{} = input()", dest(0))),
+        Opcode::Output => return Some(format!("output({})", arg(0))),
+        Opcode::AdjustBase => {
+            return match (modes[0], *offset) {
+                (Mode::Immediate, Some(o)) => {
+                    *offset = o.checked_add(params[0]);
+                    None
+                }
+                _ => {
+                    let stmt = format!("rb += {}", arg(0));
+                    *offset = None;
+                    Some(stmt)
+                }
+            };
+        }
+        Opcode::JumpIfTrue | Opcode::JumpIfFalse | Opcode::Halt => return None,
+    };
+    Some(format!("{} = {}", dest(2), expr))
+}
+
+/// Condition under which the jump ending `lines` is taken. A comparison computing the
+/// tested value right before the jump is shown instead of the value.
+fn condition(lines: &[Line], offset: Offset) -> Cond {
+    let (opcode, modes, params) = parts(&lines[lines.len() - 1]);
+    let tested = operand(modes[0], params[0], offset);
+    let compared = lines.len().checked_sub(2).and_then(|i| {
+        let (op, modes, params) = parts(&lines[i]);
+        let op = match op {
+            Opcode::LessThan => "<",
+            Opcode::Equals => "==",
+            _ => return None,
+        };
+        let (lhs, rhs) = (
+            operand(modes[0], params[0], offset),
+            operand(modes[1], params[1], offset),
+        );
+        let dest = destination(modes[2], params[2], offset);
+        // the comparison must not overwrite its own operands
+        if dest == tested && dest != lhs && dest != rhs {
+            Some(Cond { lhs, op, rhs })
+        } else {
+            None
+        }
+    });
+    let cond = compared.unwrap_or_else(|| Cond {
+        lhs: tested,
+        op: "!=",
+        rhs: "0".to_owned(),
+    });
+    if opcode == Opcode::JumpIfFalse {
+        cond.negate()
+    } else {
+        cond
+    }
+}
+
+/// Blocks after which the caller of a function resumes.
+fn is_call(block: &Block, returns: &BTreeSet<usize>) -> bool {
+    match block.terminator {
+        Terminator::Jump | Terminator::Indirect { conditional: false } => {
+            returns.contains(&block.end())
+        }
+        _ => false,
+    }
+}
+
+#[inline]
+fn jump_target(block: &Block) -> Option<usize> {
+    block
+        .jump()
+        .and_then(cfg::target)
+        .and_then(|t| usize::try_from(t).ok())
+}
+
+/// Successors of `block` within its function, calls resume at their return address.
+fn successors(block: &Block, returns: &BTreeSet<usize>) -> Vec<usize> {
+    if is_call(block, returns) {
+        return vec![block.end()];
+    }
+    match block.terminator {
+        Terminator::Fallthrough | Terminator::Indirect { conditional: true } => {
+            vec![block.end()]
+        }
+        Terminator::Jump => jump_target(block).into_iter().collect(),
+        Terminator::Branch => jump_target(block)
+            .into_iter()
+            .chain(Some(block.end()))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Translates `block`, entered with the relative base at `offset`.
+fn node(block: &Block, mut offset: Offset, returns: &BTreeSet<usize>) -> Node {
+    let call = is_call(block, returns);
+    let mut stmts = Vec::new();
+    for line in &block.lines {
+        // the return address saved by a call is implied by the call
+        if call && cfg::constant(line) == Some(block.end() as i64) {
+            continue;
+        }
+        stmts.extend(statement(line, &mut offset));
+    }
+    let jump = block.jump().map(parts);
+    let target = || match jump {
+        Some((_, modes, params)) if modes[1] == Mode::Immediate => match jump_target(block) {
+            Some(to) => Target::Block(to),
+            None => Target::Indirect(params[1].to_string()),
+        },
+        // the return address is the first slot of the frame
+        Some((_, modes, params))
+            if modes[1] == Mode::Relative
+                && offset.and_then(|o| o.checked_add(params[1])) == Some(0) =>
+        {
+            Target::Return
+        }
+        Some((_, modes, params)) => Target::Indirect(operand(modes[1], params[1], offset)),
+        None => unreachable!("not a jump"),
+    };
+
+    let flow = match block.terminator {
+        _ if call => Flow::Call {
+            callee: match target() {
+                Target::Block(to) => format!("f{}", to),
+                Target::Return => "*v0".to_owned(),
+                Target::Indirect(expr) => format!("*{}", expr),
+            },
+            frame: operand(Mode::Relative, 0, offset),
+            ret: block.end(),
+        },
+        Terminator::Fallthrough => Flow::Goto(Target::Block(block.end())),
+        Terminator::Jump | Terminator::Indirect { conditional: false } => Flow::Goto(target()),
+        Terminator::Branch | Terminator::Indirect { conditional: true } => Flow::Branch {
+            cond: condition(&block.lines, offset),
+            taken: target(),
+            not_taken: block.end(),
+        },
+        Terminator::Halt => Flow::Halt,
+        Terminator::Invalid => Flow::Invalid(block.end()),
+    };
+    Node { stmts, flow }
+}
+
+/// Dominator sets of `nodes` in the graph given by `preds`, from `entry`.
+fn dominators(
+    nodes: &BTreeSet<usize>,
+    entry: usize,
+    preds: &BTreeMap<usize, Vec<usize>>,
+) -> BTreeMap<usize, BTreeSet<usize>> {
+    let mut dom: BTreeMap<_, _> = nodes.iter().map(|&n| (n, nodes.clone())).collect();
+    dom.insert(entry, Some(entry).into_iter().collect());
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &n in nodes.iter().filter(|&&n| n != entry) {
+            let mut set: Option<BTreeSet<usize>> = None;
+            for p in preds[&n].iter().filter(|p| nodes.contains(p)) {
+                set = Some(match set {
+                    Some(set) => set.intersection(&dom[p]).copied().collect(),
+                    None => dom[p].clone(),
+                });
+            }
+            let mut set = set.unwrap_or_default();
+            set.insert(n);
+            if set != dom[&n] {
+                dom.insert(n, set);
+                changed = true;
+            }
+        }
+    }
+    dom
+}
+
+/// A natural loop.
+struct Loop {
+    body: BTreeSet<usize>,
+    /// Where control goes when leaving the loop, other exits use `goto`
+    exit: Option<usize>,
+}
+
+/// A loop being rendered.
+#[derive(Debug, Copy, Clone)]
+struct Context {
+    header: usize,
+    exit: Option<usize>,
+    /// Indentation of the loop statement
+    depth: usize,
+}
+
+enum Out {
+    Line(usize, String),
+    Label(usize, usize),
+}
+
+/// Structured rendering of one function.
+struct Function<'a> {
+    nodes: &'a BTreeMap<usize, Node>,
+    ipdom: BTreeMap<usize, usize>,
+    loops: BTreeMap<usize, Loop>,
+    emitted: BTreeSet<usize>,
+    gotos: BTreeSet<usize>,
+    out: Vec<Out>,
+}
+
+impl<'a> Function<'a> {
+    fn new(entry: usize, nodes: &'a BTreeMap<usize, Node>) -> Function<'a> {
+        let set: BTreeSet<usize> = nodes.keys().copied().collect();
+        let mut preds: BTreeMap<usize, Vec<usize>> = set.iter().map(|&n| (n, Vec::new())).collect();
+        // predecessors in the reverse graph, ending at EXIT
+        let mut rev_preds: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for (&n, node) in nodes {
+            let succs = node.flow.successors();
+            for &s in &succs {
+                preds.entry(s).or_default().push(n);
+            }
+            let mut rev = succs;
+            if node.flow.exits() {
+                rev.push(EXIT);
+            }
+            rev_preds.insert(n, rev);
+        }
+        let dom = dominators(&set, entry, &preds);
+
+        // post-dominators, of the nodes from which the function can end
+        let mut ending: BTreeSet<usize> = Some(EXIT).into_iter().collect();
+        let mut pending = vec![EXIT];
+        while let Some(n) = pending.pop() {
+            let from = match n {
+                EXIT => nodes
+                    .iter()
+                    .filter(|(_, node)| node.flow.exits())
+                    .map(|(&n, _)| n)
+                    .collect(),
+                _ => preds[&n].clone(),
+            };
+            for p in from {
+                if ending.insert(p) {
+                    pending.push(p);
+                }
+            }
+        }
+        rev_preds.insert(EXIT, Vec::new());
+        let pdom = dominators(&ending, EXIT, &rev_preds);
+        let ipdom = pdom
+            .iter()
+            .filter_map(|(&n, set)| {
+                let p = set
+                    .iter()
+                    .filter(|&&p| p != n)
+                    .max_by_key(|&&p| pdom[&p].len())?;
+                Some((n, *p)).filter(|&(_, p)| p != EXIT)
+            })
+            .collect();
+
+        let mut loops: BTreeMap<usize, Loop> = BTreeMap::new();
+        for (&n, node) in nodes {
+            for h in node.flow.successors() {
+                if !dom[&n].contains(&h) {
+                    continue;
+                }
+                // back edge: the nodes reaching n without going through h
+                let body = &mut loops
+                    .entry(h)
+                    .or_insert_with(|| Loop {
+                        body: Some(h).into_iter().collect(),
+                        exit: None,
+                    })
+                    .body;
+                let mut pending = vec![n];
+                while let Some(m) = pending.pop() {
+                    if body.insert(m) {
+                        pending.extend(preds[&m].iter().copied());
+                    }
+                }
+            }
+        }
+        let mut function = Function {
+            nodes,
+            ipdom,
+            loops,
+            emitted: BTreeSet::new(),
+            gotos: BTreeSet::new(),
+            out: Vec::new(),
+        };
+        for (&h, lp) in &mut function.loops {
+            let exits: BTreeSet<usize> = lp
+                .body
+                .iter()
+                .flat_map(|n| nodes[n].flow.successors())
+                .filter(|s| !lp.body.contains(s))
+                .collect();
+            lp.exit = function
+                .ipdom
+                .get(&h)
+                .filter(|p| exits.contains(p))
+                .or_else(|| exits.iter().next())
+                .copied();
+        }
+        function
+    }
+
+    #[inline]
+    fn line<S: Into<String>>(&mut self, depth: usize, line: S) {
+        self.out.push(Out::Line(depth, line.into()));
+    }
+
+    fn goto(&mut self, to: usize, depth: usize) {
+        self.gotos.insert(to);
+        self.line(depth, format!("goto L{}", to));
+    }
+
+    /// Renders the code from `start` until `stop`, the end of the function or of the
+    /// innermost loop.
+    fn region(
+        &mut self,
+        start: Option<usize>,
+        stop: Option<usize>,
+        loops: &[Context],
+        depth: usize,
+    ) {
+        let mut node = start;
+        while let Some(n) = node {
+            if Some(n) == stop {
+                return;
+            }
+            if let Some(lp) = loops.last() {
+                if n == lp.header {
+                    // implied at the end of the loop body
+                    if depth != lp.depth + 1 {
+                        self.line(depth, "continue");
+                    }
+                    return;
+                }
+                if Some(n) == lp.exit {
+                    self.line(depth, "break");
+                    return;
+                }
+            }
+            if !self.nodes.contains_key(&n) {
+                // a jump or fall-through to an address without instructions
+                self.line(depth, format!("goto {} // invalid target", n));
+                return;
+            }
+            if self.emitted.contains(&n)
+                || loops.iter().any(|lp| lp.header == n || lp.exit == Some(n))
+            {
+                self.goto(n, depth);
+                return;
+            }
+            self.out.push(Out::Label(depth, n));
+            node = if self.loops.contains_key(&n) {
+                self.emit_loop(n, loops, depth)
+            } else {
+                self.emit_node(n, loops, depth)
+            };
+        }
+    }
+
+    fn emit_loop(&mut self, header: usize, loops: &[Context], depth: usize) -> Option<usize> {
+        let exit = self.loops[&header].exit;
+        let mut inner = loops.to_vec();
+        inner.push(Context {
+            header,
+            exit,
+            depth,
+        });
+
+        // a header only testing whether to stay in the loop makes a `while`
+        let node = &self.nodes[&header];
+        if let (
+            true,
+            Flow::Branch {
+                cond,
+                taken: Target::Block(taken),
+                not_taken,
+            },
+        ) = (node.stmts.is_empty(), &node.flow)
+        {
+            let body = &self.loops[&header].body;
+            let stay = if Some(*not_taken) == exit && body.contains(taken) {
+                Some((*taken, cond.clone()))
+            } else if Some(*taken) == exit && body.contains(not_taken) {
+                Some((*not_taken, cond.negate()))
+            } else {
+                None
+            };
+            if let Some((stay, cond)) = stay {
+                self.emitted.insert(header);
+                self.line(depth, format!("while {} {{", cond));
+                self.region(Some(stay), None, &inner, depth + 1);
+                self.line(depth, "}");
+                return exit;
+            }
+        }
+
+        self.line(depth, "loop {");
+        let next = self.emit_node(header, &inner, depth + 1);
+        self.region(next, None, &inner, depth + 1);
+        self.line(depth, "}");
+        exit
+    }
+
+    /// Renders a block, returns the node following it in the same region.
+    fn emit_node(&mut self, n: usize, loops: &[Context], depth: usize) -> Option<usize> {
+        self.emitted.insert(n);
+        let node = &self.nodes[&n];
+        for stmt in &node.stmts {
+            self.out.push(Out::Line(depth, stmt.clone()));
+        }
+        match &node.flow {
+            Flow::Goto(Target::Block(to)) => Some(*to),
+            Flow::Goto(Target::Return) => {
+                self.line(depth, "return");
+                None
+            }
+            Flow::Goto(Target::Indirect(expr)) => {
+                self.line(depth, format!("goto *{}", expr));
+                None
+            }
+            Flow::Halt => {
+                self.line(depth, "halt");
+                None
+            }
+            Flow::Invalid(addr) => {
+                self.line(depth, format!("// invalid instruction at {}", addr));
+                None
+            }
+            Flow::Call { callee, frame, ret } => {
+                self.line(depth, format!("{}() // frame at {}", callee, frame));
+                Some(*ret)
+            }
+            Flow::Branch {
+                cond,
+                taken,
+                not_taken,
+            } => self.emit_branch(n, cond, taken, *not_taken, loops, depth),
+        }
+    }
+
+    fn emit_branch(
+        &mut self,
+        n: usize,
+        cond: &Cond,
+        taken: &Target,
+        not_taken: usize,
+        loops: &[Context],
+        depth: usize,
+    ) -> Option<usize> {
+        let lp = loops.last();
+        // statement leaving the region when jumping to `target`, if any
+        let leave = |target: &Target| match target {
+            Target::Return => Some("return".to_owned()),
+            Target::Indirect(expr) => Some(format!("goto *{}", expr)),
+            Target::Block(to) if lp.is_some_and(|lp| lp.header == *to) => {
+                Some("continue".to_owned())
+            }
+            Target::Block(to) if lp.is_some_and(|lp| lp.exit == Some(*to)) => {
+                Some("break".to_owned())
+            }
+            Target::Block(_) => None,
+        };
+        let guard = |this: &mut Self, cond: &Cond, stmt: String| {
+            this.line(depth, format!("if {} {{", cond));
+            this.line(depth + 1, stmt);
+            this.line(depth, "}");
+        };
+        let (leave_taken, leave_not_taken) = (leave(taken), leave(&Target::Block(not_taken)));
+        // `continue` is implied by going on to the end of the loop body
+        match (taken, leave_not_taken) {
+            (&Target::Block(taken), Some(stmt)) if leave_taken.iter().all(|s| s == "continue") => {
+                guard(self, &cond.negate(), stmt);
+                return Some(taken);
+            }
+            _ => (),
+        }
+        if let Some(stmt) = leave_taken {
+            guard(self, cond, stmt);
+            return Some(not_taken);
+        }
+        let taken = match taken {
+            Target::Block(to) => *to,
+            _ => unreachable!(),
+        };
+
+        // both branches meet again at the immediate post-dominator
+        let mut join = self.ipdom.get(&n).copied();
+        if let Some(lp) = lp {
+            let body = &self.loops[&lp.header].body;
+            if join.is_some_and(|j| j == lp.header || !body.contains(&j)) {
+                join = None;
+            }
+        }
+        if Some(taken) == join {
+            self.line(depth, format!("if {} {{", cond.negate()));
+            self.region(Some(not_taken), join, loops, depth + 1);
+        } else {
+            self.line(depth, format!("if {} {{", cond));
+            self.region(Some(taken), join, loops, depth + 1);
+            if Some(not_taken) != join {
+                self.line(depth, "} else {");
+                self.region(Some(not_taken), join, loops, depth + 1);
+            }
+        }
+        self.line(depth, "}");
+        join
+    }
+
+    /// Renders the function, blocks only reached through `goto` come last.
+    fn render(mut self, entry: usize, name: &str) -> String {
+        self.region(Some(entry), None, &[], 1);
+        while let Some(&to) = self.gotos.iter().find(|to| !self.emitted.contains(to)) {
+            self.out.push(Out::Label(1, to));
+            if self.loops.contains_key(&to) {
+                let next = self.emit_loop(to, &[], 1);
+                self.region(next, None, &[], 1);
+            } else {
+                self.region(Some(to), None, &[], 1);
+            }
+        }
+
+        let mut res = format!("fn {}() {{\n", name);
+        for out in &self.out {
+            match *out {
+                Out::Line(depth, ref line) => {
+                    writeln!(res, "{:1$}{2}", "", depth * 4, line).unwrap();
+                }
+                Out::Label(depth, addr) if self.gotos.contains(&addr) => {
+                    writeln!(res, "{:1$}L{2}:", "", (depth - 1) * 4, addr).unwrap();
+                }
+                Out::Label(..) => (),
+            }
+        }
+        res.push_str("}\n");
+        res
+    }
+}
+
+/// Decompiles the program of `cfg` to pseudocode, see the [module
+/// documentation](self).
+pub fn decompile(cfg: &Cfg) -> String {
+    let returns: BTreeSet<usize> = cfg.entries().filter(|&addr| addr != 0).collect();
+    let mut entries: BTreeSet<usize> = cfg.block(0).map(|_| 0).into_iter().collect();
+    for block in cfg.blocks() {
+        if block.terminator == Terminator::Jump && is_call(block, &returns) {
+            entries.extend(jump_target(block).filter(|&to| cfg.block(to).is_some()));
+        }
+    }
+
+    let mut res = String::new();
+    for &entry in &entries {
+        // relative base at the entry of each block
+        let mut offsets: BTreeMap<usize, Offset> = BTreeMap::new();
+        offsets.insert(entry, Some(0));
+        let mut pending = vec![entry];
+        while let Some(addr) = pending.pop() {
+            let block = cfg.block(addr).unwrap();
+            let mut offset = offsets[&addr];
+            for line in &block.lines {
+                statement(line, &mut offset);
+            }
+            for succ in successors(block, &returns) {
+                if cfg.block(succ).is_none() {
+                    continue;
+                }
+                let merged = match offsets.get(&succ) {
+                    Some(&known) if known == offset => continue,
+                    // reached with different relative bases
+                    Some(_) => None,
+                    None => offset,
+                };
+                offsets.insert(succ, merged);
+                pending.push(succ);
+            }
+        }
+        let nodes: BTreeMap<usize, Node> = offsets
+            .iter()
+            .map(|(&addr, &offset)| (addr, node(cfg.block(addr).unwrap(), offset, &returns)))
+            .collect();
+
+        let name = match entry {
+            0 => "main".to_owned(),
+            entry => format!("f{}", entry),
+        };
+        if !res.is_empty() {
+            res.push('\n');
+        }
+        res.push_str(&Function::new(entry, &nodes).render(entry, &name));
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        asm::assemble,
+        examples::{DAY05, FIB, NESTED_LOOPS},
+        Computer,
+    };
+    use super::*;
+
+    fn decompile_code(code: &[i64]) -> String {
+        decompile(&Cfg::code(code))
+    }
+
+    #[test]
+    fn invalid_targets() {
+        // jump past the image
+        assert_eq!(
+            decompile_code(&[1105, 1, 7, 99]),
+            "fn main() {\n    goto 7 // invalid target\n}\n"
+        );
+        // the fall-through runs past the image
+        assert_eq!(
+            decompile_code(&[1005, 10, 0]),
+            "fn main() {\n    while m10 != 0 {\n    }\n    goto 3 // invalid target\n}\n"
+        );
+        // jump to a data word
+        assert_eq!(
+            decompile_code(&[1105, 1, 3, 42]),
+            "fn main() {\n    goto 3 // invalid target\n}\n"
+        );
+        // a branch to a data word
+        assert_eq!(
+            decompile_code(&[1005, 8, 7, 104, 1, 99, 0, 42, 0]),
+            "\
+fn main() {
+    if m8 != 0 {
+        goto 7 // invalid target
+    }
+    output(1)
+    halt
+}
+"
+        );
+        // patched in the memory of a machine, like the debugger does
+        let mut computer = Computer::new(&[3, 9, 4, 9, 1005, 9, 0, 99, 0, 0], None);
+        computer.write_raw(6, 12).unwrap();
+        assert_eq!(
+            decompile(&Cfg::memory(computer.memory())),
+            "\
+fn main() {
+    m9 = input()
+    output(m9)
+    if m9 != 0 {
+        goto 12 // invalid target
+    }
+    halt
+}
+"
+        );
+        // runs into a data word
+        assert_eq!(
+            decompile_code(&[104, 1, 42]),
+            "fn main() {\n    output(1)\n    // invalid instruction at 2\n}\n"
+        );
+    }
+
+    #[test]
+    fn calls() {
+        assert_eq!(
+            decompile_code(&assemble(FIB).unwrap()),
+            "\
+fn main() {
+    v70 = input()
+    f14() // frame at v69
+    output(v71)
+    halt
+}
+
+fn f14() {
+    v3 = v1 < 2
+    if v1 >= 2 {
+        v5 = v1 - 1
+        f14() // frame at v4
+        v3 = v6
+        v5 = v1 - 2
+        f14() // frame at v4
+        v2 = v3 + v6
+        return
+    } else {
+        v2 = v1
+        return
+    }
+}
+"
+        );
+    }
+
+    #[test]
+    fn loops() {
+        assert_eq!(
+            decompile_code(&assemble(NESTED_LOOPS).unwrap()),
+            "\
+fn main() {
+    m53 = input()
+    m54 = 0
+    loop {
+        m56 = m54 < m53
+        if m54 >= m53 {
+            break
+        }
+        m55 = 0
+        loop {
+            m56 = m55 < m54
+            if m55 >= m54 {
+                break
+            }
+            m56 = m55 == 3
+            if m55 != 3 {
+                output(m54)
+            } else {
+                output(m55)
+            }
+            m55 = m55 + 1
+        }
+        m54 = m54 + 1
+    }
+    halt
+}
+"
+        );
+        // a header only testing the condition
+        assert_eq!(
+            decompile_code(&[1005, 10, 6, 1105, 1, 0, 104, 1, 99, 0, 0]),
+            "\
+fn main() {
+    while m10 == 0 {
+    }
+    output(1)
+    halt
+}
+"
+        );
+    }
+
+    #[test]
+    fn branches() {
+        assert_eq!(
+            decompile_code(DAY05),
+            "\
+fn main() {
+    m21 = input()
+    m20 = m21 == 8
+    if m21 == 8 {
+        m20 = m21 * 125
+        output(m20)
+    } else {
+        m20 = 8 < m21
+        if 8 >= m21 {
+            output(999)
+        } else {
+            m20 = 1000 + 1
+            output(m20)
+        }
+    }
+    halt
+}
+"
+        );
+    }
+}
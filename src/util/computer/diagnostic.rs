@@ -0,0 +1,318 @@
+//! Machine state attached to errors, to tell what the program was doing when it failed.
+
+use super::{Computer, Error, ErrorKind, Instruction, Mode, Word};
+
+use std::{collections::VecDeque, convert::TryFrom, fmt};
+
+/// Executed instructions remembered by default.
+const TRAIL_LEN: usize = 8;
+/// Cells dumped on each side of the faulting address.
+const DUMP_RADIUS: i64 = 4;
+
+/// An instruction as found in memory when the error happened.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Decoded<W = i64> {
+    pub addr: i64,
+    pub word: W,
+    /// `None` if the word is not a core instruction
+    pub instruction: Option<Instruction>,
+    /// Raw parameter words
+    pub params: Vec<W>,
+}
+
+/// Formatted like a [disassembly](super::disasm) line: `   12: ADD [rb+3], #5, [42]`.
+impl<W: Word> fmt::Display for Decoded<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let insn = match self.instruction {
+            Some(insn) => insn,
+            None => return write!(f, "{:5}: DB {}", self.addr, self.word),
+        };
+        write!(f, "{:5}: {}", self.addr, insn.opcode.mnemonic())?;
+        for (i, (mode, param)) in insn.modes.iter().zip(&self.params).enumerate() {
+            f.write_str(if i == 0 { " " } else { ", " })?;
+            match mode {
+                Mode::Immediate => write!(f, "#{}", param)?,
+                Mode::Position => write!(f, "[{}]", param)?,
+                Mode::Relative if *param < W::default() => write!(f, "[rb{}]", param)?,
+                Mode::Relative => write!(f, "[rb+{}]", param)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// State of the machine when an [`Error`] was created.
+#[derive(Debug, Clone)]
+pub(super) struct Context<W> {
+    instruction: Option<Decoded<W>>,
+    rbo: i64,
+    recent: Vec<Decoded<W>>,
+    fault: i64,
+    dump: Vec<(i64, W)>,
+}
+
+/// Addresses of the last executed instructions.
+#[derive(Debug, Clone)]
+pub(super) struct Trail {
+    capacity: usize,
+    ips: VecDeque<i64>,
+}
+
+impl Default for Trail {
+    #[inline]
+    fn default() -> Trail {
+        Trail {
+            capacity: TRAIL_LEN,
+            ips: VecDeque::with_capacity(TRAIL_LEN),
+        }
+    }
+}
+
+impl<W: Word> Computer<W> {
+    /// Sets the number of executed instructions reported by errors, 8 by default.
+    pub fn set_error_trail(&mut self, len: usize) {
+        self.trail.capacity = len;
+        while self.trail.ips.len() > len {
+            self.trail.ips.pop_front();
+        }
+    }
+
+    /// Cell at `addr`, without going through [`read_raw`](Computer::read_raw) which
+    /// would create another error.
+    #[inline]
+    fn peek(&self, addr: i64) -> Option<W> {
+        usize::try_from(addr)
+            .ok()
+            .and_then(|addr| self.mem.get(addr))
+    }
+
+    /// Instruction at `addr`, `None` if it is outside of memory.
+    fn decode_at(&self, addr: i64) -> Option<Decoded<W>> {
+        let word = self.peek(addr)?;
+        let instruction = word
+            .to_i64()
+            .and_then(|word| Instruction::decode(word).ok());
+        let arity = instruction.map_or(0, |insn| insn.opcode.arity());
+        let params = (1..=arity as i64)
+            .map(|i| self.peek(addr + i).unwrap_or_default())
+            .collect();
+        Some(Decoded {
+            addr,
+            word,
+            instruction,
+            params,
+        })
+    }
+
+    /// Captures the state of the machine for an error of kind `kind`.
+    pub(super) fn context(&self, kind: &ErrorKind<W>) -> Context<W> {
+        let fault = match *kind {
+            ErrorKind::InvalidRead(addr) | ErrorKind::InvalidWrite(addr, _) => addr,
+            _ => self.ip,
+        };
+        let dump = (fault.saturating_sub(DUMP_RADIUS)..=fault.saturating_add(DUMP_RADIUS))
+            .filter_map(|addr| Some((addr, self.peek(addr)?)))
+            .collect();
+        Context {
+            instruction: self.decode_at(self.ip),
+            rbo: self.rbo,
+            recent: self
+                .trail
+                .ips
+                .iter()
+                .filter_map(|&ip| self.decode_at(ip))
+                .collect(),
+            fault,
+            dump,
+        }
+    }
+
+    /// Records the instruction at `ip` as executed.
+    #[inline]
+    pub(super) fn trail_push(&mut self, ip: i64) {
+        if self.trail.capacity == 0 {
+            return;
+        }
+        if self.trail.ips.len() == self.trail.capacity {
+            self.trail.ips.pop_front();
+        }
+        self.trail.ips.push_back(ip);
+    }
+
+    #[inline]
+    pub(super) fn trail_pop(&mut self) {
+        self.trail.ips.pop_back();
+    }
+
+    #[inline]
+    pub(super) fn trail_clear(&mut self) {
+        self.trail.ips.clear();
+    }
+}
+
+impl<W> Error<W> {
+    /// Instruction at the error location, `None` if it is outside of memory or the
+    /// error carries no machine state.
+    #[inline]
+    pub fn instruction(&self) -> Option<&Decoded<W>> {
+        self.context.as_ref()?.instruction.as_ref()
+    }
+
+    /// Relative base offset when the error happened, `None` if the error carries no
+    /// machine state.
+    #[inline]
+    pub fn rbo(&self) -> Option<i64> {
+        self.context.as_ref().map(|context| context.rbo)
+    }
+
+    /// Last instructions executed before the error, oldest first.
+    ///
+    /// They are decoded from the memory at the time of the error, an instruction that
+    /// was overwritten since it ran shows its new contents.
+    #[inline]
+    pub fn recent(&self) -> &[Decoded<W>] {
+        self.context.as_ref().map_or(&[], |context| &context.recent)
+    }
+
+    /// Address of the invalid read or write, the error location for other errors.
+    #[inline]
+    pub fn fault_address(&self) -> i64 {
+        self.context
+            .as_ref()
+            .map_or(self.location, |context| context.fault)
+    }
+
+    /// Memory cells around [`fault_address`](Error::fault_address) as `(addr, value)`
+    /// pairs, cells outside of memory are left out.
+    #[inline]
+    pub fn memory_dump(&self) -> &[(i64, W)] {
+        self.context.as_ref().map_or(&[], |context| &context.dump)
+    }
+
+    /// Multi-line description of the error and the machine state.
+    #[inline]
+    pub fn report(&self) -> Report<'_, W> {
+        Report(self)
+    }
+}
+
+/// Display adapter returned by [`Error::report`].
+pub struct Report<'a, W>(&'a Error<W>);
+
+impl<W: Word> fmt::Display for Report<'_, W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let err = self.0;
+        let rbo = match err.rbo() {
+            Some(rbo) => rbo,
+            None => return write!(f, "{}", err),
+        };
+        writeln!(f, "{}", err)?;
+        match err.instruction() {
+            Some(insn) => writeln!(f, "instruction:\n {}", insn)?,
+            None => writeln!(f, "instruction: outside of memory")?,
+        }
+        writeln!(f, "relative base: {}", rbo)?;
+        if !err.recent().is_empty() {
+            writeln!(f, "last instructions:")?;
+            for insn in err.recent() {
+                writeln!(f, " {}", insn)?;
+            }
+        }
+        write!(f, "memory around {}:", err.fault_address())?;
+        if err.memory_dump().is_empty() {
+            write!(f, " none")?;
+        }
+        for (addr, value) in err.memory_dump() {
+            let marker = if *addr == err.fault_address() {
+                '>'
+            } else {
+                ' '
+            };
+            write!(f, "\n{}{:5}: {}", marker, addr, value)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Breakpoint, Computer};
+    use super::*;
+
+    #[test]
+    fn decoded() {
+        let computer = Computer::new(&[21101, 5, -3, 7, 204, -1, 42], None);
+        assert_eq!(
+            computer.decode_at(0).unwrap().to_string(),
+            "    0: ADD #5, #-3, [rb+7]"
+        );
+        assert_eq!(
+            computer.decode_at(4).unwrap().to_string(),
+            "    4: OUT [rb-1]"
+        );
+        assert_eq!(computer.decode_at(6).unwrap().to_string(), "    6: DB 42");
+        assert_eq!(computer.decode_at(-1), None);
+    }
+
+    #[test]
+    fn faults() {
+        // counts down from 2 at address 12, then reads at -1
+        let code = [109, 3, 1001, 12, -1, 12, 1005, 12, 2, 4, -1, 99, 2];
+        let mut computer = Computer::new(&code, None);
+        computer.set_error_trail(3);
+        let err = computer.run(None).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::InvalidRead(-1)));
+        assert_eq!(err.location(), 9);
+        assert_eq!(err.instruction().unwrap().addr, 9);
+        assert_eq!(err.rbo(), Some(3));
+        let recent: Vec<i64> = err.recent().iter().map(|insn| insn.addr).collect();
+        assert_eq!(recent, [6, 2, 6]);
+        assert_eq!(err.fault_address(), -1);
+        assert_eq!(err.memory_dump(), [(0, 109), (1, 3), (2, 1001), (3, 12)]);
+        assert_eq!(
+            err.report().to_string(),
+            "IntCode error at 9: tried to read value outside memory bounds at address -1
+instruction:
+     9: OUT [-1]
+relative base: 3
+last instructions:
+     6: JNZ [12], #2
+     2: ADD [12], #-1, [12]
+     6: JNZ [12], #2
+memory around -1:
+     0: 109
+     1: 3
+     2: 1001
+     3: 12"
+        );
+
+        // the dump is centered on the instruction for other errors
+        let err = Computer::new(&[1105, 1, 100], None).run(None).unwrap_err();
+        assert_eq!(err.fault_address(), 100);
+        assert_eq!(err.instruction().unwrap().word, 0);
+        assert!(err.report().to_string().contains("\n>  100: 0\n"));
+    }
+
+    #[test]
+    fn stops_carry_no_state() {
+        let mut computer = Computer::new(&[3, 0, 99], None);
+        let err = computer.resume(None).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::NoInput));
+        assert_eq!(err.rbo(), None);
+        assert!(err.instruction().is_none());
+        assert!(err.recent().is_empty() && err.memory_dump().is_empty());
+        assert_eq!(err.fault_address(), 0);
+        assert_eq!(err.report().to_string(), err.to_string());
+
+        let err = computer.resume_get(Some(1)).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::NoOutput));
+        assert_eq!(err.rbo(), None);
+
+        let mut computer = Computer::new(&[104, 1, 99], None);
+        computer.add_breakpoint(Breakpoint::Address(2));
+        computer.resume(None).unwrap();
+        let err = computer.resume(None).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Interrupted(_)));
+        assert_eq!(err.report().to_string(), err.to_string());
+    }
+}
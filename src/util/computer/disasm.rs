@@ -0,0 +1,247 @@
+//! Parameter-mode aware listings of Intcode programs.
+//!
+//! Each line shows the address of an instruction followed by its mnemonic and operands,
+//! e.g. `   12: ADD [rb+3], #5, [42]`. Words that are not the canonical encoding of an
+//! instruction are listed as data: `   15: DB 1234`.
+//!
+//! Listings can be fed back to the [assembler](super::asm).
+
+use super::{Computer, Instruction, Memory, Mode, Operand};
+
+use std::fmt::{self, Write};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Line {
+    Instruction {
+        addr: usize,
+        insn: Instruction,
+        /// Raw parameter words, only the first `insn.opcode.arity()` are meaningful
+        params: [i64; 3],
+    },
+    Data {
+        addr: usize,
+        value: i64,
+    },
+}
+
+impl Line {
+    #[inline]
+    pub fn addr(&self) -> usize {
+        match *self {
+            Line::Instruction { addr, .. } | Line::Data { addr, .. } => addr,
+        }
+    }
+
+    /// Whether the line is a conditional jump.
+    #[inline]
+    pub fn is_branch(&self) -> bool {
+        match self {
+            Line::Instruction { insn, .. } => insn.opcode.is_branch(),
+            Line::Data { .. } => false,
+        }
+    }
+
+    /// Number of memory cells covered by the line.
+    #[inline]
+    pub fn size(&self) -> usize {
+        match self {
+            Line::Instruction { insn, .. } => insn.size(),
+            Line::Data { .. } => 1,
+        }
+    }
+
+    pub fn operands(&self) -> impl Iterator<Item = Operand> + '_ {
+        let (modes, params): (&[_], &[_]) = match self {
+            Line::Instruction { insn, params, .. } => (&insn.modes, &params[..insn.opcode.arity()]),
+            Line::Data { .. } => (&[], &[]),
+        };
+        modes
+            .iter()
+            .zip(params)
+            .map(|(&mode, &param)| Operand(mode, param))
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Line::Instruction { addr, insn, .. } => {
+                write!(f, "{:5}: {}", addr, insn.opcode.mnemonic())?;
+                for (i, operand) in self.operands().enumerate() {
+                    write!(f, "{}{}", if i == 0 { " " } else { ", " }, operand)?;
+                }
+                Ok(())
+            }
+            Line::Data { addr, value } => write!(f, "{:5}: DB {}", addr, value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Source<'a> {
+    Code(&'a [i64]),
+    Memory(&'a Memory),
+}
+
+impl Source<'_> {
+    #[inline]
+    fn get(&self, addr: usize) -> i64 {
+        match self {
+            Source::Code(code) => code[addr],
+            Source::Memory(mem) => mem.get(addr).unwrap_or(0),
+        }
+    }
+
+    #[inline]
+    fn end(&self) -> usize {
+        match self {
+            Source::Code(code) => code.len(),
+            Source::Memory(mem) => mem.len(),
+        }
+    }
+}
+
+/// Whether `word` is the encoding the assembler produces for `insn`: no leading mode
+/// digits for parameters the instruction does not have.
+#[inline]
+fn is_canonical(insn: Instruction, word: i64) -> bool {
+    insn.encode() == word
+        && insn.modes[insn.opcode.arity()..]
+            .iter()
+            .all(|&mode| mode == Mode::Position)
+}
+
+/// Linear sweep disassembler, yields one [`Line`] per instruction or data word.
+#[derive(Debug, Clone)]
+pub struct Disasm<'a> {
+    source: Source<'a>,
+    addr: usize,
+}
+
+impl<'a> Disasm<'a> {
+    /// Disassembles a program image, such as the one given to [`Computer::new`].
+    #[inline]
+    pub fn code(code: &'a [i64]) -> Disasm<'a> {
+        Disasm {
+            source: Source::Code(code),
+            addr: 0,
+        }
+    }
+
+    /// Disassembles the memory of a machine, skipping pages that were never written to.
+    #[inline]
+    pub fn memory(mem: &'a Memory) -> Disasm<'a> {
+        Disasm {
+            source: Source::Memory(mem),
+            addr: 0,
+        }
+    }
+
+    /// Starts the sweep at `addr` instead of the beginning of memory.
+    #[inline]
+    pub fn starting_at(mut self, addr: usize) -> Disasm<'a> {
+        self.addr = addr;
+        self
+    }
+}
+
+impl Iterator for Disasm<'_> {
+    type Item = Line;
+
+    fn next(&mut self) -> Option<Line> {
+        if let Source::Memory(mem) = self.source {
+            self.addr = mem.next_mapped(self.addr)?;
+        }
+        let addr = self.addr;
+        let end = self.source.end();
+        if addr >= end {
+            return None;
+        }
+
+        let word = self.source.get(addr);
+        let line = match Instruction::decode(word) {
+            Ok(insn) if is_canonical(insn, word) && addr + insn.size() <= end => {
+                let mut params = [0; 3];
+                for (i, param) in params.iter_mut().take(insn.opcode.arity()).enumerate() {
+                    *param = self.source.get(addr + 1 + i);
+                }
+                Line::Instruction { addr, insn, params }
+            }
+            _ => Line::Data { addr, value: word },
+        };
+        self.addr += line.size();
+        Some(line)
+    }
+}
+
+/// Renders the full listing of `lines`, one per line.
+pub fn listing<I>(lines: I) -> String
+where
+    I: IntoIterator<Item = Line>,
+{
+    lines.into_iter().fold(String::new(), |mut res, line| {
+        writeln!(res, "{}", line).unwrap();
+        res
+    })
+}
+
+impl Computer {
+    /// Disassembles the current contents of memory, self-modifications included.
+    #[inline]
+    pub fn disasm(&self) -> Disasm<'_> {
+        Disasm::memory(self.memory())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::computer::{asm::assemble, examples};
+
+    #[test]
+    fn lines() {
+        let expected = "    0: ADD [9], [10], [3]
+    4: MUL [3], [11], [0]
+    8: HLT
+    9: DB 30
+   10: DB 40
+   11: DB 50
+";
+        assert_eq!(listing(Disasm::code(examples::DAY02)), expected);
+
+        let listing = listing(Disasm::code(&[21108, -1, 5, 3, 204, 7, 1005]));
+        assert_eq!(
+            listing,
+            "    0: CEQ #-1, #5, [rb+3]\n    4: OUT [rb+7]\n    6: DB 1005\n"
+        );
+    }
+
+    #[test]
+    fn non_canonical_words_are_data() {
+        // mode digit for the missing third parameter of JNZ, then an unknown mode
+        let code = [11105, 304, 99];
+        let listing = listing(Disasm::code(&code));
+        assert_eq!(listing, "    0: DB 11105\n    1: DB 304\n    2: HLT\n");
+        assert_eq!(assemble(&listing).unwrap(), code);
+    }
+
+    #[test]
+    fn memory_skips_unmapped_pages() {
+        let mut computer = Computer::new(&[1105, 1, 0], None);
+        computer.write_raw(5000, 99).unwrap();
+        let addrs: Vec<usize> = computer.disasm().map(|line| line.addr()).collect();
+        assert_eq!(&addrs[..2], &[0, 3]);
+        assert_eq!(addrs.last(), Some(&5000));
+        assert!(addrs.iter().all(|addr| !(1024..4096).contains(addr)));
+        let start = computer.disasm().starting_at(5000).next().unwrap();
+        assert_eq!(start.to_string(), " 5000: HLT");
+    }
+
+    #[test]
+    fn round_trip() {
+        for (name, code) in examples::all() {
+            let source = listing(Disasm::code(&code));
+            assert_eq!(assemble(&source).unwrap(), code, "{}", name);
+        }
+    }
+}
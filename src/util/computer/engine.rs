@@ -0,0 +1,406 @@
+//! Execution engines: the reference interpreter and a faster one caching decoded
+//! instructions.
+
+use super::{Arithmetic, Computer, Instruction, Mode, Opcode, Result, Stopped, Word};
+
+use std::convert::TryFrom;
+
+/// Addresses past this one are decoded on every execution instead of being cached.
+const CACHE_LIMIT: usize = 1 << 20;
+
+/// How [`Computer::run`] executes instructions.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Engine {
+    /// Decodes and runs one instruction at a time, with every check and hook
+    Interpreter,
+    /// Keeps the decoded instruction of each address and runs them in a tight loop
+    ///
+    /// The cached entry of an address is dropped when it is written to, so
+    /// self-modifying programs behave the same. The engine only runs while nothing
+    /// observes the individual instructions (breakpoints, watchpoints, tracing, history,
+    /// loop detection, profiling, coverage, a restricted instruction set), the
+    /// interpreter takes over otherwise. Any instruction that fails, reads input past
+    /// the end of the queue or uses an extension opcode is also left to the
+    /// interpreter, so results and errors are the same with both engines.
+    Cached,
+}
+
+impl Default for Engine {
+    #[inline]
+    fn default() -> Engine {
+        Engine::Interpreter
+    }
+}
+
+/// Decoded instructions by address.
+#[derive(Debug, Clone, Default)]
+pub(super) struct Cache {
+    slots: Vec<Option<Instruction>>,
+}
+
+impl Cache {
+    /// Drops the instruction decoded at `addr`, after a write to it.
+    #[inline]
+    pub(super) fn invalidate(&mut self, addr: i64) {
+        if let Some(slot) = usize::try_from(addr)
+            .ok()
+            .and_then(|addr| self.slots.get_mut(addr))
+        {
+            *slot = None;
+        }
+    }
+
+    #[inline]
+    pub(super) fn clear(&mut self) {
+        self.slots.clear();
+    }
+}
+
+impl<W: Word> Computer<W> {
+    #[inline]
+    pub fn engine(&self) -> Engine {
+        self.engine
+    }
+
+    /// Selects the execution engine, [`Engine::Interpreter`] by default.
+    #[inline]
+    pub fn set_engine(&mut self, engine: Engine) {
+        self.engine = engine;
+        self.cache.clear();
+    }
+
+    /// Whether the cached engine may run, see [`Engine::Cached`].
+    #[inline]
+    pub(super) fn can_run_cached(&self) -> bool {
+        self.engine == Engine::Cached
+            && self.probes_idle()
+            && self.tracer.is_none()
+            && self.history.is_none()
+            && !self.detects_loops()
+            && self.profiler.is_none()
+            && self.coverage.is_none()
+            && self.isa_is_default()
+    }
+
+    /// Runs the program with the cached engine, like [`run`](Computer::run).
+    pub(super) fn run_cached(&mut self) -> Result<Stopped<W>, W> {
+        loop {
+            if self.budget() != Some(0) {
+                match self.fast_step() {
+                    Some(None) => {
+                        self.spend();
+                        continue;
+                    }
+                    Some(Some(Stopped::NeedsInput)) => return Ok(Stopped::NeedsInput),
+                    Some(Some(stop)) => {
+                        self.spend();
+                        return Ok(stop);
+                    }
+                    None => (),
+                }
+            }
+            if let Some(stop) = self.execute().map_err(|err| self.crashed(err))? {
+                return Ok(stop);
+            }
+        }
+    }
+
+    #[inline]
+    fn spend(&mut self) {
+        if let Some(budget) = self.budget() {
+            self.set_budget(Some(budget - 1));
+        }
+    }
+
+    /// Decoded instruction at `ip`, `None` if it is not a core instruction.
+    #[inline]
+    fn cached_instruction(&mut self, ip: i64) -> Option<Instruction> {
+        let index = usize::try_from(ip).ok()?;
+        if let Some(&Some(insn)) = self.cache.slots.get(index) {
+            return Some(insn);
+        }
+        let word = self.load(ip)?.to_i64()?;
+        let insn = Instruction::decode(word).ok()?;
+        if index < CACHE_LIMIT {
+            if index >= self.cache.slots.len() {
+                self.cache.slots.resize(index + 1, None);
+            }
+            self.cache.slots[index] = Some(insn);
+        }
+        Some(insn)
+    }
+
+    #[inline]
+    fn load(&self, addr: i64) -> Option<W> {
+        usize::try_from(addr)
+            .ok()
+            .and_then(|addr| self.mem.get(addr))
+    }
+
+    #[inline]
+    fn fast_address(&self, mode: Mode, index: i64) -> Option<i64> {
+        let offset = self.load(index)?.to_i64()?;
+        match mode {
+            Mode::Immediate | Mode::Position => Some(offset),
+            Mode::Relative => self.rbo.checked_add(offset),
+        }
+    }
+
+    #[inline]
+    fn fast_read(&self, mode: Mode, index: i64) -> Option<W> {
+        match mode {
+            Mode::Immediate => self.load(index),
+            _ => self.load(self.fast_address(mode, index)?),
+        }
+    }
+
+    /// Address designated by the destination parameter at `index`, if it can be written.
+    #[inline]
+    fn fast_destination(&self, mode: Mode, index: i64) -> Option<i64> {
+        let addr = self.fast_address(mode, index)?;
+        if addr >= 0 && self.mem.in_bounds(addr as usize) {
+            Some(addr)
+        } else {
+            None
+        }
+    }
+
+    /// Writes to an address returned by `fast_destination`.
+    #[inline]
+    fn fast_write(&mut self, addr: i64, value: W) {
+        self.mem.set(addr as usize, value);
+        self.cache.invalidate(addr);
+    }
+
+    #[inline]
+    fn fast_arithmetic(&self, opcode: Opcode, lhs: &W, rhs: &W) -> Option<W> {
+        match (opcode, self.arithmetic) {
+            (Opcode::Add, Arithmetic::Checked) => lhs.checked_add(rhs),
+            (Opcode::Add, Arithmetic::Wrapping) => Some(lhs.wrapping_add(rhs)),
+            (Opcode::Add, Arithmetic::Saturating) => Some(lhs.saturating_add(rhs)),
+            (_, Arithmetic::Checked) => lhs.checked_mul(rhs),
+            (_, Arithmetic::Wrapping) => Some(lhs.wrapping_mul(rhs)),
+            (_, Arithmetic::Saturating) => Some(lhs.saturating_mul(rhs)),
+        }
+    }
+
+    /// Runs the instruction at `ip` if it can complete, returns `None` without changing
+    /// the machine state otherwise.
+    #[inline]
+    fn fast_step(&mut self) -> Option<Option<Stopped<W>>> {
+        let ip = self.ip;
+        let Instruction { opcode, modes } = self.cached_instruction(ip)?;
+        let stop = match opcode {
+            Opcode::Add | Opcode::Mul => {
+                let p1 = self.fast_read(modes[0], ip + 1)?;
+                let p2 = self.fast_read(modes[1], ip + 2)?;
+                let value = self.fast_arithmetic(opcode, &p1, &p2)?;
+                let dest = self.fast_destination(modes[2], ip + 3)?;
+                self.fast_write(dest, value);
+                self.ip += 4;
+                None
+            }
+            Opcode::Input => {
+                let dest = self.fast_destination(modes[0], ip + 1)?;
+                let value = match self.inputs.pop_front() {
+                    Some(value) => value,
+                    None => return Some(Some(Stopped::NeedsInput)),
+                };
+                self.fast_write(dest, value);
+                self.ip += 2;
+                None
+            }
+            Opcode::Output => {
+                let p1 = self.fast_read(modes[0], ip + 1)?;
+                self.ip += 2;
+                Some(Stopped::Output(p1))
+            }
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse => {
+                let zero = self.fast_read(modes[0], ip + 1)?.is_zero();
+                self.ip = if zero == (opcode == Opcode::JumpIfFalse) {
+                    self.fast_read(modes[1], ip + 2)?.to_i64()?
+                } else {
+                    ip + 3
+                };
+                None
+            }
+            Opcode::LessThan | Opcode::Equals => {
+                let p1 = self.fast_read(modes[0], ip + 1)?;
+                let p2 = self.fast_read(modes[1], ip + 2)?;
+                let dest = self.fast_destination(modes[2], ip + 3)?;
+                let set = if opcode == Opcode::LessThan {
+                    p1 < p2
+                } else {
+                    p1 == p2
+                };
+                self.fast_write(dest, W::from(set as i32));
+                self.ip += 4;
+                None
+            }
+            Opcode::AdjustBase => {
+                let p1 = self.fast_read(modes[0], ip + 1)?;
+                self.rbo = self.rbo.checked_add(p1.to_i64()?)?;
+                self.ip += 2;
+                None
+            }
+            Opcode::Halt => {
+                self.halted = true;
+                Some(Stopped::Halted)
+            }
+        };
+        self.trail_push(ip);
+        self.probe_done();
+        Some(stop)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::examples::{self, NESTED_LOOPS, SELF_MODIFYING};
+    use super::super::{asm::assemble, Access, Breakpoint, Watchpoint};
+    use super::*;
+
+    /// Results of `run` until the machine halts, blocks or fails.
+    fn results(computer: &mut Computer) -> Vec<String> {
+        let mut results = Vec::new();
+        for _ in 0..10_000 {
+            match computer.run(None) {
+                Ok(stop) => {
+                    results.push(format!("{:?}", stop));
+                    if stop == Stopped::Halted || stop == Stopped::NeedsInput {
+                        break;
+                    }
+                }
+                Err(err) => {
+                    results.push(format!("{} at {}", err, err.location()));
+                    break;
+                }
+            }
+        }
+        results
+    }
+
+    /// Runs `code` set up by `setup` with both engines, checks they stop the same way
+    /// in the same state.
+    fn compare<F>(code: &[i64], setup: F) -> Vec<String>
+    where
+        F: Fn(&mut Computer),
+    {
+        let mut interpreter = Computer::new(code, None);
+        let mut cached = Computer::new(code, None);
+        setup(&mut interpreter);
+        setup(&mut cached);
+        cached.set_engine(Engine::Cached);
+        assert_eq!(cached.engine(), Engine::Cached);
+        let expected = results(&mut interpreter);
+        assert_eq!(results(&mut cached), expected, "{:?}", code);
+        assert!(cached == interpreter, "{:?}", code);
+        assert_eq!(cached.budget(), interpreter.budget());
+        expected
+    }
+
+    #[test]
+    fn examples() {
+        for (name, code) in examples::all() {
+            for input in 0..12 {
+                let results = compare(&code, |computer| computer.push_input(input));
+                assert!(
+                    !results.last().unwrap().contains("error"),
+                    "{}: {:?}",
+                    name,
+                    results
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn self_modifying() {
+        let results = compare(SELF_MODIFYING, |_| ());
+        assert_eq!(results, ["Output(7)", "Output(12)", "Halted"]);
+
+        // overwrites its next instruction with an illegal opcode
+        let results = compare(&[1101, 40, 2, 4, 1, 0, 0, 0, 99], |_| ());
+        assert_eq!(results, ["IntCode error at 4: illegal opcode 42 at 4"]);
+
+        // patched between runs
+        let mut computer = Computer::new(&[104, 1, 1105, 1, 0], None);
+        computer.set_engine(Engine::Cached);
+        assert_eq!(computer.run(None).unwrap(), Stopped::Output(1));
+        computer.write_raw(1, 2).unwrap();
+        assert_eq!(computer.run(None).unwrap(), Stopped::Output(2));
+        computer.write_raw(0, 99).unwrap();
+        assert_eq!(computer.run(None).unwrap(), Stopped::Halted);
+    }
+
+    #[test]
+    fn budgets() {
+        let code = assemble(NESTED_LOOPS).unwrap();
+        let mut computer = Computer::new(&code, None);
+        computer.set_budget(Some(1000));
+        computer.push_input(3);
+        results(&mut computer);
+        let total = 1000 - computer.budget().unwrap();
+        for budget in 0..=total {
+            let results = compare(&code, |computer| {
+                computer.push_input(3);
+                computer.set_budget(Some(budget));
+            });
+            let last = results.last().unwrap();
+            assert_eq!(
+                last.contains("budget exhausted"),
+                budget < total,
+                "{}",
+                budget
+            );
+        }
+    }
+
+    #[test]
+    fn blocked_input() {
+        let code = examples::DAY07;
+        let results = compare(code, |_| ());
+        assert_eq!(results, ["NeedsInput"]);
+        let results = compare(code, |computer| computer.push_inputs(vec![5, 0]));
+        assert_eq!(results, ["Output(1)", "NeedsInput"]);
+    }
+
+    #[test]
+    fn probes() {
+        let code = assemble(NESTED_LOOPS).unwrap();
+        compare(&code, |computer| {
+            computer.push_input(4);
+            computer.add_breakpoint(Breakpoint::Address(17));
+        });
+        compare(&code, |computer| {
+            computer.push_input(4);
+            computer.add_breakpoint(Breakpoint::Opcode(Opcode::Output));
+        });
+        let results = compare(&code, |computer| {
+            computer.push_input(4);
+            computer.add_watchpoint(Watchpoint::new(code.len() as i64 - 3, Access::Write));
+        });
+        assert!(results.iter().any(|result| result.starts_with("Watch")));
+    }
+
+    #[test]
+    fn errors() {
+        for code in &[
+            &[3, -1][..],
+            &[4, 100_000_000_000][..],
+            &[109, -5, 204, 0, 99],
+            &[1102, i64::MAX, 2, 0, 99],
+            &[1105, 1, -3],
+            &[109, i64::MAX, 109, 1],
+            &[5, 99],
+            &[204, 0, 88_888],
+        ] {
+            let results = compare(code, |computer| computer.push_input(1));
+            assert!(
+                results.last().unwrap().starts_with("IntCode error"),
+                "{:?}",
+                results
+            );
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//! Intcode programs shared by the tests: the examples of the puzzles, and a few
+//! assembled programs exercising calls and self-modification.
+
+use super::asm::assemble;
+
+/// Day 2: computes `3500` at address 0.
+pub const DAY02: &[i64] = &[1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50];
+
+/// Day 5: outputs 999 if the input is below 8, 1000 if it equals 8, 1001 if above.
+pub const DAY05: &[i64] = &[
+    3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36, 98, 0, 0,
+    1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000, 1, 20, 4, 20, 1105,
+    1, 46, 98, 99,
+];
+
+/// Day 7: the amplifier of the feedback loop example.
+pub const DAY07: &[i64] = &[
+    3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1, 28, 1005,
+    28, 6, 99, 0, 0, 5,
+];
+
+/// Day 9: outputs a copy of itself, using memory past the program.
+pub const QUINE: &[i64] = &[
+    109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+];
+
+/// Day 9: outputs a 16-digit number.
+pub const DAY09: &[i64] = &[1102, 34_915_192, 34_915_192, 7, 4, 7, 99, 0];
+
+/// Outputs `7`, turns its first instruction into a multiplication and outputs `12`.
+pub const SELF_MODIFYING: &[i64] = &[
+    1, 24, 25, 26, 4, 26, 1006, 27, 23, 1101, 0, 2, 0, 1101, 0, 0, 27, 1105, 1, 0, 0, 0, 0, 99, 3,
+    4, 0, 1,
+];
+
+/// Reads `n` and outputs the `n`th Fibonacci number, computed by a recursive function
+/// with its frames on the relative base stack.
+pub const FIB: &str = "\
+        RBO #stack
+        IPT [rb+1]
+        ADD #done, #0, [rb+0]
+        JNZ #1, #fib
+done:   OUT [rb+2]
+        HLT
+fib:    CLT [rb+1], #2, [rb+3]
+        JPZ [rb+3], #rec
+        ADD [rb+1], #0, [rb+2]
+        JNZ #1, [rb+0]
+rec:    ADD [rb+1], #-1, [rb+5]
+        ADD #r1, #0, [rb+4]
+        RBO #4
+        JNZ #1, #fib
+r1:     RBO #-4
+        ADD [rb+6], #0, [rb+3]
+        ADD [rb+1], #-2, [rb+5]
+        ADD #r2, #0, [rb+4]
+        RBO #4
+        JNZ #1, #fib
+r2:     RBO #-4
+        ADD [rb+3], [rb+6], [rb+2]
+        JNZ #1, [rb+0]
+stack:  DB 0
+";
+
+/// Outputs, for each `i` below its input and each `j` below `i`, `j` if it is 3 and
+/// `i` otherwise.
+pub const NESTED_LOOPS: &str = "\
+        IPT [n]
+        ADD #0, #0, [i]
+outer:  CLT [i], [n], [t]
+        JPZ [t], #end
+        ADD #0, #0, [j]
+inner:  CLT [j], [i], [t]
+        JPZ [t], #next
+        CEQ [j], #3, [t]
+        JPZ [t], #other
+        OUT [j]
+        JNZ #1, #cont
+other:  OUT [i]
+cont:   ADD [j], #1, [j]
+        JNZ #1, #inner
+next:   ADD [i], #1, [i]
+        JNZ #1, #outer
+end:    HLT
+n:      DB 0
+i:      DB 0
+j:      DB 0
+t:      DB 0
+";
+
+/// Every example program, assembled.
+pub fn all() -> Vec<(&'static str, Vec<i64>)> {
+    vec![
+        ("day02", DAY02.to_vec()),
+        ("day05", DAY05.to_vec()),
+        ("day07", DAY07.to_vec()),
+        ("quine", QUINE.to_vec()),
+        ("day09", DAY09.to_vec()),
+        ("self-modifying", SELF_MODIFYING.to_vec()),
+        ("fib", assemble(FIB).unwrap()),
+        ("nested loops", assemble(NESTED_LOOPS).unwrap()),
+    ]
+}
@@ -0,0 +1,452 @@
+//! Additional opcodes, run by the handlers of registered [`Extension`]s.
+//!
+//! Extension opcodes use the regular instruction format: the two low digits of the word
+//! are the opcode, the following digits are the modes of up to three parameters. An
+//! opcode of the core instruction set always takes precedence, so extensions only see
+//! the words the core would reject as illegal. Strict mode applies to extension
+//! opcodes, profiles do not.
+
+use super::{Access, Action, Computer, Error, ErrorKind, Mode, Opcode, Result, Word};
+
+use std::{fmt, sync::Arc};
+
+/// Description of an extension opcode.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct OpcodeSpec {
+    /// Between 0 and 99, not used by the core instruction set
+    pub code: i64,
+    pub mnemonic: &'static str,
+    /// Number of parameters following the opcode, at most 3
+    pub arity: usize,
+    /// Index of the parameter the instruction writes to, if any
+    pub destination: Option<usize>,
+}
+
+impl OpcodeSpec {
+    /// Number of memory cells taken by the instruction, opcode included.
+    #[inline]
+    pub fn size(self) -> usize {
+        self.arity + 1
+    }
+}
+
+/// What the machine does once an extension instruction completed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Effect<W = i64> {
+    /// Go on with the next instruction, or the one given to [`Machine::jump`]
+    Continue,
+    /// Stop with [`Stopped::Output`](super::Stopped::Output)
+    Output(W),
+    /// Halt the machine, the instruction pointer stays on the instruction
+    Halt,
+    /// Block until more input is available, the instruction is run again on resume
+    ///
+    /// The handler must not have changed the machine state before returning this.
+    NeedsInput,
+}
+
+/// A set of opcodes added to the instruction set.
+///
+/// Extensions are shared between clones of a machine, a handler needing state should
+/// keep it behind a lock or atomics.
+pub trait Extension<W: Word = i64>: Send + Sync {
+    fn opcodes(&self) -> &[OpcodeSpec];
+
+    /// Runs the instruction with opcode `code` at [`Machine::ip`].
+    fn execute(&self, code: i64, machine: &mut Machine<W>) -> Result<Effect<W>, W>;
+}
+
+/// Registered extensions of a [`Computer`].
+#[derive(Clone)]
+pub(super) struct Extensions<W> {
+    handlers: Vec<(OpcodeSpec, Arc<dyn Extension<W>>)>,
+}
+
+impl<W> Default for Extensions<W> {
+    #[inline]
+    fn default() -> Self {
+        Extensions {
+            handlers: Vec::new(),
+        }
+    }
+}
+
+impl<W> fmt::Debug for Extensions<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.handlers.iter().map(|(spec, _)| spec))
+            .finish()
+    }
+}
+
+impl<W> Extensions<W> {
+    #[inline]
+    fn find(&self, code: i64) -> Option<&(OpcodeSpec, Arc<dyn Extension<W>>)> {
+        self.handlers.iter().find(|(spec, _)| spec.code == code)
+    }
+}
+
+/// Access to the machine given to an extension handler.
+///
+/// Parameter reads and writes go through watchpoints, tracing and the undo history like
+/// the ones of core instructions.
+pub struct Machine<'a, W: Word = i64> {
+    computer: &'a mut Computer<W>,
+    spec: OpcodeSpec,
+    modes: [Mode; 3],
+    /// Address of the next instruction
+    next: i64,
+}
+
+impl<W: Word> Machine<'_, W> {
+    /// Address of the running instruction.
+    #[inline]
+    pub fn ip(&self) -> i64 {
+        self.computer.ip
+    }
+
+    #[inline]
+    pub fn spec(&self) -> OpcodeSpec {
+        self.spec
+    }
+
+    /// Mode of the parameter at `index`.
+    #[inline]
+    pub fn mode(&self, index: usize) -> Mode {
+        self.modes[index]
+    }
+
+    /// Value of the parameter at `index`.
+    pub fn param(&mut self, index: usize) -> Result<W, W> {
+        let at = self.param_index(index);
+        self.computer.read(self.modes[index], at)
+    }
+
+    /// Address designated by the parameter at `index`.
+    pub fn param_address(&self, index: usize) -> Result<i64, W> {
+        self.computer
+            .param_address(self.modes[index], self.param_index(index))
+    }
+
+    /// Writes `value` to the address designated by the parameter at `index`.
+    pub fn write_param(&mut self, index: usize, value: W) -> Result<(), W> {
+        let at = self.param_index(index);
+        self.computer.write(self.modes[index], at, value)
+    }
+
+    #[inline]
+    fn param_index(&self, index: usize) -> i64 {
+        assert!(index < self.spec.arity, "parameter {} out of range", index);
+        self.computer.ip + 1 + index as i64
+    }
+
+    /// Reads the cell at `addr`.
+    pub fn read(&mut self, addr: i64) -> Result<W, W> {
+        let value = self.computer.read_raw(addr)?;
+        self.computer.watch(Access::Read, addr, &value);
+        self.computer.trace_access(Access::Read, addr, &value);
+        Ok(value)
+    }
+
+    /// Writes to the cell at `addr`.
+    pub fn write(&mut self, addr: i64, value: W) -> Result<(), W> {
+        self.computer.store(addr, value)
+    }
+
+    #[inline]
+    pub fn rbo(&self) -> i64 {
+        self.computer.rbo
+    }
+
+    #[inline]
+    pub fn set_rbo(&mut self, rbo: i64) {
+        self.computer.rbo = rbo;
+        self.computer.trace_with(|step| step.rbo = Some(rbo));
+    }
+
+    /// Continues at `addr` instead of the next instruction.
+    #[inline]
+    pub fn jump(&mut self, addr: i64) {
+        self.next = addr;
+    }
+
+    /// Pops the next input value, `None` if the input queue is empty.
+    pub fn input(&mut self) -> Option<W> {
+        let value = self.computer.inputs.pop_front()?;
+        self.computer
+            .trace_with(|step| step.input = Some(value.clone()));
+        self.computer.history_input(&value);
+        Some(value)
+    }
+
+    /// Creates an error located at the running instruction.
+    #[inline]
+    pub fn error(&self, kind: ErrorKind<W>) -> Error<W> {
+        self.computer.error(kind)
+    }
+
+    /// Read access to the rest of the machine.
+    #[inline]
+    pub fn computer(&self) -> &Computer<W> {
+        self.computer
+    }
+}
+
+impl<W: Word> Computer<W> {
+    /// Adds the opcodes of `extension` to the instruction set.
+    ///
+    /// # Panics
+    ///
+    /// If an opcode is out of range, taken by the core instruction set or another
+    /// extension, or has more than three parameters.
+    pub fn add_extension<E: Extension<W> + 'static>(&mut self, extension: E) {
+        let extension: Arc<dyn Extension<W>> = Arc::new(extension);
+        for &spec in extension.opcodes() {
+            assert!(
+                (0..100).contains(&spec.code) && Opcode::from_code(spec.code).is_none(),
+                "opcode {} is not available to extensions",
+                spec.code
+            );
+            assert!(
+                self.extensions.find(spec.code).is_none(),
+                "opcode {} is already registered",
+                spec.code
+            );
+            assert!(spec.arity <= 3, "{} has too many parameters", spec.mnemonic);
+            self.extensions
+                .handlers
+                .push((spec, Arc::clone(&extension)));
+        }
+    }
+
+    /// Opcodes added by extensions.
+    pub fn extension_opcodes(&self) -> impl Iterator<Item = &OpcodeSpec> + '_ {
+        self.extensions.handlers.iter().map(|(spec, _)| spec)
+    }
+
+    /// Number of parameters of the instruction `word`, 0 if it is not valid.
+    pub(super) fn arity_of(&self, word: i64) -> usize {
+        match Opcode::from_code(word % 100) {
+            Some(opcode) => opcode.arity(),
+            None => self
+                .extensions
+                .find(word % 100)
+                .map_or(0, |(spec, _)| spec.arity),
+        }
+    }
+
+    /// Runs the instruction at `ip` with an extension, `kind` is the error of the core
+    /// decoder returned when no extension handles it.
+    pub(super) fn run_extension(&mut self, kind: ErrorKind<W>) -> Result<Action<W>, W> {
+        if self.extensions.handlers.is_empty() {
+            return Err(self.error(kind));
+        }
+        let word = match self.read_raw(self.ip)?.to_i64() {
+            Some(word) => word,
+            None => return Err(self.error(kind)),
+        };
+        let (spec, extension) = match self.extensions.find(word % 100) {
+            Some((spec, extension)) => (*spec, Arc::clone(extension)),
+            None => return Err(self.error(kind)),
+        };
+        let mut modes = [Mode::Position; 3];
+        for (i, mode) in modes.iter_mut().enumerate().take(spec.arity) {
+            let code = word / 10i64.pow(i as u32 + 2) % 10;
+            *mode = Mode::from_code(code)
+                .ok_or_else(|| self.error(ErrorKind::InvalidParareterMode(code)))?;
+        }
+        self.check_operands(word, &modes[..spec.arity], spec.destination)?;
+
+        let mut machine = Machine {
+            next: self.ip + spec.size() as i64,
+            computer: self,
+            spec,
+            modes,
+        };
+        let effect = extension.execute(spec.code, &mut machine)?;
+        let next = machine.next;
+        Ok(match effect {
+            Effect::Continue => {
+                self.ip = next;
+                Action::Continue
+            }
+            Effect::Output(value) => {
+                self.trace_with(|step| step.output = Some(value.clone()));
+                self.ip = next;
+                Action::Output(value)
+            }
+            Effect::Halt => Action::Shutdown,
+            Effect::NeedsInput => Action::Input,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Engine, Profile, Stopped};
+    use super::*;
+
+    const fn spec(
+        code: i64,
+        mnemonic: &'static str,
+        arity: usize,
+        destination: Option<usize>,
+    ) -> OpcodeSpec {
+        OpcodeSpec {
+            code,
+            mnemonic,
+            arity,
+            destination,
+        }
+    }
+
+    const OPCODES: &[OpcodeSpec] = &[
+        spec(42, "DBL", 2, Some(1)),
+        spec(43, "EMT", 1, None),
+        spec(44, "HCD", 1, None),
+        spec(45, "RD", 1, Some(0)),
+        spec(46, "JMP", 1, None),
+        spec(48, "ERR", 0, None),
+    ];
+
+    /// Doubles, outputs, halts with a code written at address 0, reads, jumps and fails.
+    struct Sys;
+
+    impl Extension for Sys {
+        fn opcodes(&self) -> &[OpcodeSpec] {
+            OPCODES
+        }
+
+        fn execute(&self, code: i64, machine: &mut Machine) -> Result<Effect> {
+            Ok(match code {
+                42 => {
+                    let value = machine.param(0)?;
+                    machine.write_param(1, value * 2)?;
+                    Effect::Continue
+                }
+                43 => Effect::Output(machine.param(0)?),
+                44 => {
+                    let value = machine.param(0)?;
+                    machine.write(0, value)?;
+                    Effect::Halt
+                }
+                45 => match machine.input() {
+                    Some(value) => {
+                        machine.write_param(0, value)?;
+                        Effect::Continue
+                    }
+                    None => Effect::NeedsInput,
+                },
+                46 => {
+                    let addr = machine.param(0)?;
+                    machine.jump(addr);
+                    Effect::Continue
+                }
+                _ => return Err(machine.error(ErrorKind::InvalidAddress(-1))),
+            })
+        }
+    }
+
+    /// Reads a value, outputs its double, then jumps over a halt to halt with code 7.
+    const PROGRAM: &[i64] = &[45, 20, 42, 20, 21, 43, 21, 146, 11, 99, 99, 144, 7, 99];
+
+    fn computer(code: &[i64]) -> Computer {
+        let mut computer = Computer::new(code, None);
+        computer.add_extension(Sys);
+        computer
+    }
+
+    #[test]
+    fn opcodes() {
+        for &engine in &[Engine::Interpreter, Engine::Cached] {
+            let mut computer = computer(PROGRAM);
+            computer.set_engine(engine);
+            assert_eq!(computer.run(None).unwrap(), Stopped::NeedsInput);
+            assert_eq!(computer.ip(), 0);
+            assert_eq!(computer.run(Some(5)).unwrap(), Stopped::Output(10));
+            assert_eq!(computer.run(None).unwrap(), Stopped::Halted);
+            assert_eq!(computer.ip(), 11);
+            assert_eq!(computer.read_raw(0).unwrap(), 7);
+            assert_eq!(computer.run(None).unwrap(), Stopped::Halted);
+        }
+        let codes: Vec<i64> = computer(&[]).extension_opcodes().map(|s| s.code).collect();
+        assert_eq!(codes, [42, 43, 44, 45, 46, 48]);
+    }
+
+    #[test]
+    fn modes() {
+        // relative destination, immediate output
+        let mut computer = computer(&[109, 10, 2042, 9, 1, 143, 5, 99, 0, 21]);
+        assert_eq!(computer.run(None).unwrap(), Stopped::Output(5));
+        assert_eq!(computer.read_raw(11).unwrap(), 42);
+
+        let err = self::computer(&[342, 0, 0]).run(None).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::InvalidParareterMode(3)));
+
+        // profiles do not apply, strict mode does
+        let mut computer = self::computer(&[143, 1, 1042, 0, 0, 99]);
+        computer.set_profile(Profile::Day02);
+        assert_eq!(computer.run(None).unwrap(), Stopped::Output(1));
+        computer.set_strict(true);
+        let err = computer.run(None).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::ImmediateWrite));
+        assert_eq!(err.location(), 2);
+        let mut computer = self::computer(&[10_143, 1]);
+        computer.set_strict(true);
+        let err = computer.run(None).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::ExtraModeDigits(10_143)));
+    }
+
+    #[test]
+    fn errors() {
+        let err = computer(&[47, 99]).run(None).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::IllegalOpcode(47)));
+        let mut computer = computer(&[104, 1, 48]);
+        assert_eq!(computer.run(None).unwrap(), Stopped::Output(1));
+        let err = computer.run(None).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::InvalidAddress(-1)));
+        assert_eq!(err.location(), 2);
+        // core opcodes take precedence
+        let err = Computer::new(&[43, 1], None).run(None).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::IllegalOpcode(43)));
+    }
+
+    #[test]
+    fn history_and_clones() {
+        let mut computer = computer(PROGRAM);
+        computer.enable_history(10);
+        computer.push_input(5);
+        let clone = computer.clone();
+        assert_eq!(computer.run(None).unwrap(), Stopped::Output(10));
+        assert!(computer.run_back_to(0));
+        assert_eq!(computer.read_raw(20).unwrap(), 0);
+        assert_eq!(computer.read_raw(21).unwrap(), 0);
+        assert_eq!(computer.pending_inputs().len(), 1);
+        assert!(computer == clone);
+        let mut clone = clone;
+        assert_eq!(clone.run(None).unwrap(), Stopped::Output(10));
+    }
+
+    #[test]
+    #[should_panic(expected = "opcode 1 is not available to extensions")]
+    fn core_opcodes_are_reserved() {
+        struct Add;
+        impl Extension for Add {
+            fn opcodes(&self) -> &[OpcodeSpec] {
+                const ADD: &[OpcodeSpec] = &[spec(1, "ADD", 3, Some(2))];
+                ADD
+            }
+            fn execute(&self, _: i64, _: &mut Machine) -> Result<Effect> {
+                Ok(Effect::Continue)
+            }
+        }
+        Computer::new(&[], None).add_extension(Add);
+    }
+
+    #[test]
+    #[should_panic(expected = "opcode 42 is already registered")]
+    fn opcodes_are_unique() {
+        let mut computer = computer(&[]);
+        computer.add_extension(Sys);
+    }
+}
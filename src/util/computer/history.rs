@@ -0,0 +1,260 @@
+//! Undo log of the executed instructions, for reverse execution.
+
+use super::{Computer, Word};
+
+use std::collections::VecDeque;
+
+/// Machine state changed by one instruction, enough to undo it.
+#[derive(Debug, Clone, Default)]
+struct Undo<W> {
+    ip: i64,
+    rbo: i64,
+    halted: bool,
+    /// Memory size, which a write past the end grows
+    len: usize,
+    /// Written address and its previous value
+    write: Option<(i64, W)>,
+    /// Consumed input value
+    input: Option<W>,
+}
+
+#[derive(Debug)]
+pub(super) struct History<W> {
+    capacity: usize,
+    entries: VecDeque<Undo<W>>,
+    /// Entry of the instruction being run
+    current: Undo<W>,
+}
+
+impl<W: Word> Computer<W> {
+    /// Starts recording an undo log of at most `capacity` instructions, the oldest entries
+    /// are dropped once it is full. Replaces the current log if any.
+    pub fn enable_history(&mut self, capacity: usize) {
+        self.history = Some(History {
+            capacity,
+            entries: VecDeque::with_capacity(capacity.min(1 << 16)),
+            current: Undo::default(),
+        });
+    }
+
+    #[inline]
+    pub fn disable_history(&mut self) {
+        self.history = None;
+    }
+
+    /// Forgets the recorded instructions, keeping the history enabled.
+    #[inline]
+    pub fn history_clear(&mut self) {
+        if let Some(history) = &mut self.history {
+            history.entries.clear();
+        }
+    }
+
+    /// Number of instructions that can be undone.
+    #[inline]
+    pub fn history_len(&self) -> usize {
+        self.history.as_ref().map_or(0, |h| h.entries.len())
+    }
+
+    /// Undoes the last executed instruction, returns `false` if the history is empty.
+    ///
+    /// Memory, including its size, registers and consumed inputs are restored, values
+    /// already output are not taken back.
+    pub fn step_back(&mut self) -> bool {
+        let undo = match self.history.as_mut().and_then(|h| h.entries.pop_back()) {
+            Some(undo) => undo,
+            None => return false,
+        };
+        if let Some((addr, value)) = undo.write {
+            self.mem.set(addr as usize, value);
+            self.cache.invalidate(addr);
+        }
+        self.mem.set_len(undo.len);
+        if let Some(input) = undo.input {
+            self.inputs.push_front(input);
+        }
+        self.ip = undo.ip;
+        self.rbo = undo.rbo;
+        self.halted = undo.halted;
+        self.trail_pop();
+        self.probe_reset();
+        self.watchdog_reset();
+        true
+    }
+
+    /// Steps back until the instruction pointer is at `addr`.
+    ///
+    /// Returns `false` if the history ran out first, leaving the machine at the oldest
+    /// recorded state.
+    pub fn run_back_to(&mut self, addr: i64) -> bool {
+        while self.step_back() {
+            if self.ip == addr {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Steps back to the last instruction that wrote to `addr`, before it ran.
+    ///
+    /// Returns `false` if the history ran out first, leaving the machine at the oldest
+    /// recorded state.
+    pub fn run_back_to_write(&mut self, addr: i64) -> bool {
+        loop {
+            let wrote = match self.history.as_ref().and_then(|h| h.entries.back()) {
+                Some(undo) => undo.write.as_ref().is_some_and(|(a, _)| *a == addr),
+                None => return false,
+            };
+            self.step_back();
+            if wrote {
+                return true;
+            }
+        }
+    }
+
+    /// Starts recording the instruction at `ip`.
+    #[inline]
+    pub(super) fn history_begin(&mut self) {
+        if let Some(history) = &mut self.history {
+            history.current = Undo {
+                ip: self.ip,
+                rbo: self.rbo,
+                halted: self.halted,
+                len: self.mem.len(),
+                write: None,
+                input: None,
+            };
+        }
+    }
+
+    /// Saves the value at `addr` before the current instruction overwrites it.
+    #[inline]
+    pub(super) fn history_write(&mut self, addr: i64) {
+        if let Some(history) = &mut self.history {
+            if addr >= 0 {
+                if let Some(old) = self.mem.get(addr as usize) {
+                    history.current.write = Some((addr, old));
+                }
+            }
+        }
+    }
+
+    #[inline]
+    pub(super) fn history_input(&mut self, value: &W) {
+        if let Some(history) = &mut self.history {
+            history.current.input = Some(value.clone());
+        }
+    }
+
+    /// Commits the entry of the completed instruction.
+    #[inline]
+    pub(super) fn history_end(&mut self) {
+        if let Some(history) = &mut self.history {
+            if history.capacity == 0 {
+                return;
+            }
+            if history.entries.len() == history.capacity {
+                history.entries.pop_front();
+            }
+            history
+                .entries
+                .push_back(std::mem::take(&mut history.current));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::examples::{FIB, QUINE};
+    use super::super::{asm::assemble, Memory, Stopped};
+    use super::*;
+
+    #[test]
+    fn step_back() {
+        // reads a value, doubles it, outputs it, halts
+        let mut computer = Computer::new(&[3, 9, 102, 2, 9, 9, 4, 9, 99, 0], None);
+        computer.enable_history(10);
+        assert!(!computer.step_back());
+        assert_eq!(computer.run(Some(21)).unwrap(), Stopped::Output(42));
+        assert_eq!(computer.run(None).unwrap(), Stopped::Halted);
+        assert_eq!(computer.history_len(), 4);
+
+        assert!(computer.step_back());
+        assert!(!computer.is_halted());
+        assert_eq!(computer.ip(), 8);
+        assert!(computer.step_back());
+        assert!(computer.step_back());
+        assert_eq!(computer.read_raw(9).unwrap(), 21);
+        assert!(computer.step_back());
+        assert_eq!(computer.read_raw(9).unwrap(), 0);
+        assert_eq!(computer.pending_inputs().iter().collect::<Vec<_>>(), [&21]);
+        assert_eq!(computer.ip(), 0);
+        assert!(!computer.step_back());
+
+        // replays the same way
+        assert_eq!(computer.run(None).unwrap(), Stopped::Output(42));
+    }
+
+    #[test]
+    fn memory_size() {
+        let mut computer = Computer::new(QUINE, None);
+        computer.enable_history(100);
+        assert_eq!(computer.memory().len(), 16);
+        while computer.run(None).unwrap() != Stopped::Halted {}
+        assert_eq!(computer.memory().len(), 102);
+        while computer.step_back() {}
+        assert_eq!(computer.memory().len(), 16);
+        assert_eq!(computer.memory(), &Memory::new(QUINE, None));
+
+        // the relative base is restored too
+        let mut computer = Computer::new(&assemble(FIB).unwrap(), None);
+        let len = computer.memory().len();
+        computer.enable_history(1000);
+        assert_eq!(computer.run(Some(6)).unwrap(), Stopped::Output(8));
+        assert!(computer.memory().len() > len);
+        while computer.step_back() {}
+        assert_eq!(computer.memory().len(), len);
+        assert_eq!(computer.rbo(), 0);
+        assert_eq!(computer.run(None).unwrap(), Stopped::Output(8));
+    }
+
+    #[test]
+    fn run_back() {
+        let mut computer = Computer::new(QUINE, None);
+        computer.enable_history(1000);
+        while computer.run(None).unwrap() != Stopped::Halted {}
+
+        // the counter at 100 is incremented by the instruction at 4
+        assert!(computer.run_back_to_write(100));
+        assert_eq!(computer.ip(), 4);
+        assert_eq!(computer.read_raw(100).unwrap(), 15);
+        assert!(computer.run_back_to(2));
+        assert_eq!(computer.read_raw(100).unwrap(), 15);
+        assert!(!computer.run_back_to(-1));
+        assert_eq!(computer.ip(), 0);
+        assert_eq!(computer.read_raw(100).unwrap(), 0);
+        assert!(!computer.run_back_to_write(100));
+    }
+
+    #[test]
+    fn capacity() {
+        let mut computer = Computer::new(QUINE, None);
+        computer.enable_history(3);
+        computer.resume_get(None).unwrap();
+        computer.resume_get(None).unwrap();
+        assert_eq!(computer.history_len(), 3);
+        assert!(!computer.run_back_to(100));
+        assert_eq!(computer.history_len(), 0);
+
+        computer.enable_history(0);
+        computer.resume_get(None).unwrap();
+        assert!(!computer.step_back());
+
+        computer.enable_history(10);
+        computer.resume_get(None).unwrap();
+        computer.history_clear();
+        assert!(!computer.step_back());
+        computer.disable_history();
+        assert_eq!(computer.history_len(), 0);
+    }
+}
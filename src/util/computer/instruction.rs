@@ -0,0 +1,180 @@
+use super::ErrorKind;
+
+use std::fmt;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Opcode {
+    Add = 1,
+    Mul,
+    Input,
+    Output,
+    JumpIfTrue,
+    JumpIfFalse,
+    LessThan,
+    Equals,
+    AdjustBase,
+    Halt = 99,
+}
+
+impl Opcode {
+    pub fn from_code(code: i64) -> Option<Opcode> {
+        match code {
+            1 => Some(Opcode::Add),
+            2 => Some(Opcode::Mul),
+            3 => Some(Opcode::Input),
+            4 => Some(Opcode::Output),
+            5 => Some(Opcode::JumpIfTrue),
+            6 => Some(Opcode::JumpIfFalse),
+            7 => Some(Opcode::LessThan),
+            8 => Some(Opcode::Equals),
+            9 => Some(Opcode::AdjustBase),
+            99 => Some(Opcode::Halt),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn code(self) -> i64 {
+        self as i64
+    }
+
+    /// Number of parameters following the opcode.
+    #[inline]
+    pub fn arity(self) -> usize {
+        match self {
+            Opcode::Add | Opcode::Mul | Opcode::LessThan | Opcode::Equals => 3,
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse => 2,
+            Opcode::Input | Opcode::Output | Opcode::AdjustBase => 1,
+            Opcode::Halt => 0,
+        }
+    }
+
+    /// Index of the parameter the instruction writes to, if any.
+    #[inline]
+    pub fn destination(self) -> Option<usize> {
+        match self {
+            Opcode::Add | Opcode::Mul | Opcode::LessThan | Opcode::Equals => Some(2),
+            Opcode::Input => Some(0),
+            _ => None,
+        }
+    }
+
+    /// Whether the instruction is a conditional jump.
+    #[inline]
+    pub fn is_branch(self) -> bool {
+        self == Opcode::JumpIfTrue || self == Opcode::JumpIfFalse
+    }
+
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            Opcode::Add => "ADD",
+            Opcode::Mul => "MUL",
+            Opcode::Input => "IPT",
+            Opcode::Output => "OUT",
+            Opcode::JumpIfTrue => "JNZ",
+            Opcode::JumpIfFalse => "JPZ",
+            Opcode::LessThan => "CLT",
+            Opcode::Equals => "CEQ",
+            Opcode::AdjustBase => "RBO",
+            Opcode::Halt => "HLT",
+        }
+    }
+
+    pub fn from_mnemonic(mnemonic: &str) -> Option<Opcode> {
+        OPCODES
+            .iter()
+            .copied()
+            .find(|op| op.mnemonic().eq_ignore_ascii_case(mnemonic))
+    }
+}
+
+/// Every opcode of the instruction set.
+pub const OPCODES: [Opcode; 10] = [
+    Opcode::Add,
+    Opcode::Mul,
+    Opcode::Input,
+    Opcode::Output,
+    Opcode::JumpIfTrue,
+    Opcode::JumpIfFalse,
+    Opcode::LessThan,
+    Opcode::Equals,
+    Opcode::AdjustBase,
+    Opcode::Halt,
+];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Mode {
+    Immediate,
+    Position,
+    Relative,
+}
+
+impl Mode {
+    pub fn from_code(code: i64) -> Option<Mode> {
+        match code {
+            0 => Some(Mode::Position),
+            1 => Some(Mode::Immediate),
+            2 => Some(Mode::Relative),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn code(self) -> i64 {
+        match self {
+            Mode::Position => 0,
+            Mode::Immediate => 1,
+            Mode::Relative => 2,
+        }
+    }
+}
+
+/// A decoded instruction word: the opcode and the modes of its three parameter slots.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Instruction {
+    pub opcode: Opcode,
+    pub modes: [Mode; 3],
+}
+
+impl Instruction {
+    pub fn decode(word: i64) -> std::result::Result<Instruction, ErrorKind> {
+        let mode = |code| Mode::from_code(code).ok_or(ErrorKind::InvalidParareterMode(code));
+        let modes = [
+            mode(word / 100 % 10)?,
+            mode(word / 1000 % 10)?,
+            mode(word / 10000 % 10)?,
+        ];
+        let opcode = Opcode::from_code(word % 100).ok_or(ErrorKind::IllegalOpcode(word % 100))?;
+        Ok(Instruction { opcode, modes })
+    }
+
+    /// Inverse of [`decode`](Instruction::decode).
+    pub fn encode(self) -> i64 {
+        self.opcode.code()
+            + self.modes[0].code() * 100
+            + self.modes[1].code() * 1000
+            + self.modes[2].code() * 10000
+    }
+
+    /// Number of memory cells taken by the instruction, opcode included.
+    #[inline]
+    pub fn size(self) -> usize {
+        self.opcode.arity() + 1
+    }
+}
+
+/// An instruction parameter, formatted as `#5` (immediate), `[42]` (position) or
+/// `[rb+3]` (relative).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Operand(pub Mode, pub i64);
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Operand(Mode::Immediate, value) => write!(f, "#{}", value),
+            Operand(Mode::Position, addr) => write!(f, "[{}]", addr),
+            Operand(Mode::Relative, offset) if offset < 0 => write!(f, "[rb{}]", offset),
+            Operand(Mode::Relative, offset) => write!(f, "[rb+{}]", offset),
+        }
+    }
+}
@@ -0,0 +1,297 @@
+//! Peripherals an Intcode program can be wired to, see [`Computer::run_device`].
+//!
+//! [`Computer::run_device`]: super::Computer::run_device
+
+use std::{
+    collections::VecDeque,
+    io::{self, Read, Write},
+    sync::mpsc::{Receiver, Sender, SyncSender},
+};
+
+/// Provides values to the read instruction.
+pub trait InputSource {
+    /// Returns the next input value, or `None` if the source has nothing left to give.
+    fn next_input(&mut self) -> io::Result<Option<i64>>;
+}
+
+/// Receives the values of the write instruction.
+pub trait OutputSink {
+    fn output(&mut self, value: i64) -> io::Result<()>;
+
+    /// Called before the machine blocks on a read.
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<T: InputSource + ?Sized> InputSource for &mut T {
+    #[inline]
+    fn next_input(&mut self) -> io::Result<Option<i64>> {
+        (**self).next_input()
+    }
+}
+
+impl<T: OutputSink + ?Sized> OutputSink for &mut T {
+    #[inline]
+    fn output(&mut self, value: i64) -> io::Result<()> {
+        (**self).output(value)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        (**self).flush()
+    }
+}
+
+impl InputSource for VecDeque<i64> {
+    #[inline]
+    fn next_input(&mut self) -> io::Result<Option<i64>> {
+        Ok(self.pop_front())
+    }
+}
+
+impl OutputSink for VecDeque<i64> {
+    #[inline]
+    fn output(&mut self, value: i64) -> io::Result<()> {
+        self.push_back(value);
+        Ok(())
+    }
+}
+
+impl OutputSink for Vec<i64> {
+    #[inline]
+    fn output(&mut self, value: i64) -> io::Result<()> {
+        self.push(value);
+        Ok(())
+    }
+}
+
+/// Blocks until a value is received, a disconnected channel counts as exhausted.
+impl InputSource for Receiver<i64> {
+    #[inline]
+    fn next_input(&mut self) -> io::Result<Option<i64>> {
+        Ok(self.recv().ok())
+    }
+}
+
+impl OutputSink for Sender<i64> {
+    #[inline]
+    fn output(&mut self, value: i64) -> io::Result<()> {
+        self.send(value).map_err(disconnected)
+    }
+}
+
+impl OutputSink for SyncSender<i64> {
+    #[inline]
+    fn output(&mut self, value: i64) -> io::Result<()> {
+        self.send(value).map_err(disconnected)
+    }
+}
+
+fn disconnected<E>(_: E) -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "channel receiver disconnected")
+}
+
+/// Input source backed by an iterator, see [`source_iter`].
+#[derive(Debug, Clone)]
+pub struct IterSource<I>(I);
+
+#[inline]
+pub fn source_iter<I>(values: I) -> IterSource<I::IntoIter>
+where
+    I: IntoIterator<Item = i64>,
+{
+    IterSource(values.into_iter())
+}
+
+impl<I: Iterator<Item = i64>> InputSource for IterSource<I> {
+    #[inline]
+    fn next_input(&mut self) -> io::Result<Option<i64>> {
+        Ok(self.0.next())
+    }
+}
+
+/// Input source backed by a closure, see [`source_fn`].
+#[derive(Debug, Clone)]
+pub struct FnSource<F>(F);
+
+#[inline]
+pub fn source_fn<F>(f: F) -> FnSource<F>
+where
+    F: FnMut() -> Option<i64>,
+{
+    FnSource(f)
+}
+
+impl<F: FnMut() -> Option<i64>> InputSource for FnSource<F> {
+    #[inline]
+    fn next_input(&mut self) -> io::Result<Option<i64>> {
+        Ok((self.0)())
+    }
+}
+
+/// Output sink backed by a closure, see [`sink_fn`].
+#[derive(Debug, Clone)]
+pub struct FnSink<F>(F);
+
+#[inline]
+pub fn sink_fn<F>(f: F) -> FnSink<F>
+where
+    F: FnMut(i64),
+{
+    FnSink(f)
+}
+
+impl<F: FnMut(i64)> OutputSink for FnSink<F> {
+    #[inline]
+    fn output(&mut self, value: i64) -> io::Result<()> {
+        (self.0)(value);
+        Ok(())
+    }
+}
+
+/// Adapts byte streams to ASCII-speaking programs.
+///
+/// As a source each byte read is one input value.
+/// As a sink values in the ASCII range are written as characters and anything else as a
+/// decimal number on its own line.
+#[derive(Debug)]
+pub struct Ascii<T>(pub T);
+
+impl<R: Read> InputSource for Ascii<R> {
+    fn next_input(&mut self) -> io::Result<Option<i64>> {
+        let mut byte = [0];
+        loop {
+            match self.0.read(&mut byte) {
+                Ok(0) => return Ok(None),
+                Ok(_) => return Ok(Some(i64::from(byte[0]))),
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => (),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+impl<W: Write> OutputSink for Ascii<W> {
+    fn output(&mut self, value: i64) -> io::Result<()> {
+        if (0..=127).contains(&value) {
+            self.0.write_all(&[value as u8])
+        } else {
+            writeln!(self.0, "{}", value)
+        }
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// Joins a separate source and sink into a single device.
+#[derive(Debug)]
+pub(super) struct Pair<S, O>(pub S, pub O);
+
+impl<S: InputSource, O> InputSource for Pair<S, O> {
+    #[inline]
+    fn next_input(&mut self) -> io::Result<Option<i64>> {
+        self.0.next_input()
+    }
+}
+
+impl<S, O: OutputSink> OutputSink for Pair<S, O> {
+    #[inline]
+    fn output(&mut self, value: i64) -> io::Result<()> {
+        self.1.output(value)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.1.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::computer::{Computer, ErrorKind, Stopped};
+    use std::{sync::mpsc, thread};
+
+    /// Outputs twice each input until it reads 0.
+    const DOUBLER: &[i64] = &[
+        3, 15, 1006, 15, 14, 102, 2, 15, 15, 4, 15, 1105, 1, 0, 99, 0,
+    ];
+    const ECHO: &[i64] = &[3, 7, 4, 7, 1105, 1, 0, 0];
+
+    #[test]
+    fn queues() {
+        let mut inputs: VecDeque<i64> = vec![1, 2, 3].into();
+        let mut outputs = Vec::new();
+        let mut computer = Computer::new(DOUBLER, None);
+        let stop = computer.run_with(&mut inputs, &mut outputs).unwrap();
+        assert_eq!(stop, Stopped::NeedsInput);
+        assert_eq!(outputs, vec![2, 4, 6]);
+
+        let stop = computer
+            .run_with(source_iter(vec![5, 0, 7]), &mut outputs)
+            .unwrap();
+        assert_eq!(stop, Stopped::Halted);
+        assert_eq!(outputs, vec![2, 4, 6, 10]);
+    }
+
+    #[test]
+    fn closures() {
+        let mut next = 0;
+        let mut sum = 0;
+        let source = source_fn(|| {
+            next += 1;
+            Some(next).filter(|&n| n <= 3)
+        });
+        let stop = Computer::new(DOUBLER, None)
+            .run_with(source, sink_fn(|v| sum += v))
+            .unwrap();
+        assert_eq!(stop, Stopped::NeedsInput);
+        assert_eq!(sum, 12);
+    }
+
+    #[test]
+    fn ascii() {
+        let mut out = Vec::new();
+        let stop = Computer::new(ECHO, None)
+            .run_with(Ascii(&b"hi"[..]), Ascii(&mut out))
+            .unwrap();
+        assert_eq!(stop, Stopped::NeedsInput);
+        assert_eq!(out, b"hi");
+
+        let mut out = Vec::new();
+        Computer::new(&[104, 72, 104, 1000, 104, 10, 99], None)
+            .run_with(Ascii(io::empty()), Ascii(&mut out))
+            .unwrap();
+        assert_eq!(out, b"H1000\n\n");
+    }
+
+    #[test]
+    fn channels() {
+        let (in_tx, in_rx) = mpsc::channel();
+        let (out_tx, out_rx) = mpsc::channel();
+        let machine =
+            thread::spawn(move || Computer::new(ECHO, None).run_with(in_rx, out_tx).unwrap());
+        in_tx.send(21).unwrap();
+        assert_eq!(out_rx.recv().unwrap(), 21);
+        drop(in_tx);
+        assert_eq!(machine.join().unwrap(), Stopped::NeedsInput);
+    }
+
+    #[test]
+    fn sink_errors() {
+        let (tx, rx) = mpsc::channel();
+        drop(rx);
+        let err = Computer::new(&[104, 1, 99], None)
+            .run_with(source_iter(None), tx)
+            .unwrap_err();
+        match err.kind() {
+            ErrorKind::Io(err) => assert_eq!(err.kind(), io::ErrorKind::BrokenPipe),
+            kind => panic!("unexpected error {:?}", kind),
+        }
+    }
+}
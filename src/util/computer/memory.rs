@@ -0,0 +1,279 @@
+use super::Word;
+
+use std::{
+    collections::BTreeMap,
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+/// Number of cells in a memory page.
+pub const PAGE_SIZE: usize = 1024;
+
+/// Pages below this index are stored in a flat table, the others in a sparse map.
+const DENSE_PAGES: usize = 1 << 14;
+
+type Page<W> = Arc<[W; PAGE_SIZE]>;
+
+/// Paged Intcode memory that grows on write.
+///
+/// Unwritten cells read as zero and pages are only allocated when a non-zero value is
+/// stored in them, so programs touching very high addresses stay cheap.
+///
+/// Pages are shared between clones and copied on write, so cloning costs one pointer
+/// per allocated page. Two memories compare equal when all their cells hold the same
+/// values and they have the same limit, hashing uses [`digest`](Memory::digest).
+#[derive(Debug, Clone, Default)]
+pub struct Memory<W = i64> {
+    dense: Vec<Option<Page<W>>>,
+    sparse: BTreeMap<usize, Page<W>>,
+    /// One past the highest address written so far
+    len: usize,
+    limit: Option<usize>,
+    /// Sum of the hashes of the non-zero cells, kept up to date on writes
+    digest: u64,
+}
+
+#[inline]
+fn new_page<W: Word>() -> Page<W> {
+    Arc::new(std::array::from_fn(|_| W::default()))
+}
+
+/// Hash of a cell, zero for a zero cell so that unallocated pages do not count.
+#[inline]
+fn cell_hash<W: Word>(addr: usize, value: &W) -> u64 {
+    if value.is_zero() {
+        return 0;
+    }
+    // splitmix64 finalizer
+    let mut h = (addr as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ value.fingerprint();
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    h ^ (h >> 31)
+}
+
+impl<W: Word> Memory<W> {
+    /// Loads `code` at address 0. `limit` is not a preallocated size but a hard cap:
+    /// addresses from `limit` up cannot be read or written.
+    pub fn new(code: &[W], limit: Option<usize>) -> Memory<W> {
+        let mut mem = Memory {
+            dense: Vec::new(),
+            sparse: BTreeMap::new(),
+            len: 0,
+            limit,
+            digest: 0,
+        };
+        for (addr, value) in code.iter().enumerate() {
+            mem.set(addr, value.clone());
+        }
+        mem.len = code.len();
+        mem
+    }
+
+    /// Size of the addressed region: one past the highest address ever written.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of addressable cells, unbounded if `None`.
+    #[inline]
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+
+    /// Hash of the memory contents, updated in constant time on each write.
+    #[inline]
+    pub fn digest(&self) -> u64 {
+        self.digest
+    }
+
+    #[inline]
+    pub(super) fn in_bounds(&self, addr: usize) -> bool {
+        match self.limit {
+            Some(limit) => addr < limit,
+            None => true,
+        }
+    }
+
+    #[inline]
+    fn page(&self, index: usize) -> Option<&Page<W>> {
+        if index < DENSE_PAGES {
+            self.dense.get(index).and_then(Option::as_ref)
+        } else {
+            self.sparse.get(&index)
+        }
+    }
+
+    fn page_mut(&mut self, index: usize) -> &mut [W; PAGE_SIZE] {
+        if index < DENSE_PAGES {
+            if self.dense.len() <= index {
+                self.dense.resize_with(index + 1, || None);
+            }
+            Arc::make_mut(self.dense[index].get_or_insert_with(|| new_page()))
+        } else {
+            Arc::make_mut(self.sparse.entry(index).or_insert_with(|| new_page()))
+        }
+    }
+
+    /// Returns `addr` if its page is allocated, otherwise the start of the next allocated
+    /// page.
+    ///
+    /// Cells outside of allocated pages have never held anything but zero.
+    pub fn next_mapped(&self, addr: usize) -> Option<usize> {
+        let index = addr / PAGE_SIZE;
+        if self.page(index).is_some() {
+            return Some(addr);
+        }
+        (index..self.dense.len())
+            .find(|&i| self.dense[i].is_some())
+            .or_else(|| {
+                self.sparse
+                    .range(index.max(DENSE_PAGES)..)
+                    .next()
+                    .map(|(&i, _)| i)
+            })
+            .map(|i| i * PAGE_SIZE)
+    }
+
+    /// Allocated pages in address order, with the address of their first cell.
+    pub fn pages(&self) -> impl Iterator<Item = (usize, &[W; PAGE_SIZE])> + '_ {
+        let dense = self
+            .dense
+            .iter()
+            .enumerate()
+            .filter_map(|(i, page)| page.as_ref().map(|page| (i, page)));
+        dense
+            .chain(self.sparse.iter().map(|(&i, page)| (i, page)))
+            .map(|(i, page)| (i * PAGE_SIZE, &**page))
+    }
+
+    /// Pages holding at least one non-zero cell.
+    fn nonzero_pages(&self) -> impl Iterator<Item = (usize, &[W; PAGE_SIZE])> + '_ {
+        self.pages()
+            .filter(|(_, page)| page.iter().any(|cell| !cell.is_zero()))
+    }
+
+    #[inline]
+    pub(super) fn set_len(&mut self, len: usize) {
+        self.len = len;
+    }
+
+    /// Reads the cell at `addr`, returns `None` if it lies beyond the memory limit.
+    #[inline]
+    pub fn get(&self, addr: usize) -> Option<W> {
+        if !self.in_bounds(addr) {
+            return None;
+        }
+        Some(
+            self.page(addr / PAGE_SIZE)
+                .map_or_else(W::default, |page| page[addr % PAGE_SIZE].clone()),
+        )
+    }
+
+    /// Writes `value` at `addr`, returns `false` if it lies beyond the memory limit.
+    #[inline]
+    pub fn set(&mut self, addr: usize, value: W) -> bool {
+        if !self.in_bounds(addr) {
+            return false;
+        }
+        if !value.is_zero() || self.page(addr / PAGE_SIZE).is_some() {
+            let new_hash = cell_hash(addr, &value);
+            let cell = &mut self.page_mut(addr / PAGE_SIZE)[addr % PAGE_SIZE];
+            let old = std::mem::replace(cell, value);
+            self.digest = self
+                .digest
+                .wrapping_sub(cell_hash(addr, &old))
+                .wrapping_add(new_hash);
+        }
+        self.len = self.len.max(addr + 1);
+        true
+    }
+}
+
+impl<W: Word> PartialEq for Memory<W> {
+    fn eq(&self, other: &Memory<W>) -> bool {
+        self.limit == other.limit
+            && self.digest == other.digest
+            && self.nonzero_pages().eq(other.nonzero_pages())
+    }
+}
+
+impl<W: Word> Eq for Memory<W> {}
+
+impl<W: Word> Hash for Memory<W> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.limit.hash(state);
+        self.digest.hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grows_on_write() {
+        let mut mem = Memory::new(&[1, 2, 3], None);
+        assert_eq!(mem.len(), 3);
+        assert_eq!(mem.get(1_000_000), Some(0));
+        assert_eq!(mem.len(), 3);
+        assert!(mem.set(1_000_000, 7));
+        assert_eq!(mem.get(1_000_000), Some(7));
+        assert_eq!(mem.len(), 1_000_001);
+    }
+
+    #[test]
+    fn zero_writes_do_not_allocate() {
+        let mut mem = Memory::<i64>::new(&[], None);
+        mem.set(5 * PAGE_SIZE, 0);
+        assert_eq!(mem.pages().count(), 0);
+        assert_eq!(mem.len(), 5 * PAGE_SIZE + 1);
+    }
+
+    #[test]
+    fn sparse_pages() {
+        let high = DENSE_PAGES * PAGE_SIZE + 3;
+        let mut mem = Memory::new(&[1], None);
+        mem.set(high, 42);
+        assert_eq!(mem.get(high), Some(42));
+        let starts: Vec<usize> = mem.pages().map(|(start, _)| start).collect();
+        assert_eq!(starts, vec![0, DENSE_PAGES * PAGE_SIZE]);
+        assert_eq!(mem.next_mapped(PAGE_SIZE), Some(DENSE_PAGES * PAGE_SIZE));
+        assert_eq!(mem.next_mapped(high + PAGE_SIZE), None);
+    }
+
+    #[test]
+    fn limit_is_a_cap() {
+        let mut mem = Memory::new(&[1, 2], Some(4));
+        assert_eq!(mem.get(3), Some(0));
+        assert_eq!(mem.get(4), None);
+        assert!(mem.set(3, 1));
+        assert!(!mem.set(4, 1));
+        assert_eq!(mem.len(), 4);
+    }
+
+    #[test]
+    fn equality_ignores_allocation() {
+        let mut a = Memory::new(&[1, 2], None);
+        let b = Memory::new(&[1, 2], None);
+        a.set(3 * PAGE_SIZE, 9);
+        assert_ne!(a, b);
+        a.set(3 * PAGE_SIZE, 0);
+        assert_eq!(a, b);
+        assert_eq!(a.digest(), b.digest());
+    }
+
+    #[test]
+    fn clones_copy_on_write() {
+        let mut a = Memory::new(&[1, 2], None);
+        let b = a.clone();
+        a.set(0, 5);
+        assert_eq!(a.get(0), Some(5));
+        assert_eq!(b.get(0), Some(1));
+    }
+}
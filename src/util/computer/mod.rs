@@ -0,0 +1,947 @@
+pub mod aot;
+mod arithmetic;
+pub mod asm;
+mod bigint;
+mod breakpoint;
+pub mod cfg;
+pub mod coverage;
+mod crash;
+pub mod decompiler;
+mod diagnostic;
+pub mod disasm;
+mod engine;
+#[cfg(test)]
+mod examples;
+mod extension;
+mod history;
+mod instruction;
+pub mod io;
+mod memory;
+mod profile;
+mod profiler;
+mod snapshot;
+pub mod trace;
+mod watchdog;
+mod word;
+
+pub use self::arithmetic::Arithmetic;
+pub use self::bigint::{BigInt, ParseBigIntError};
+pub use self::breakpoint::{Access, Breakpoint, WatchHit, Watchpoint};
+pub use self::cfg::Cfg;
+pub use self::coverage::Coverage;
+pub use self::crash::CrashDump;
+pub use self::diagnostic::{Decoded, Report};
+pub use self::engine::Engine;
+pub use self::extension::{Effect, Extension, Machine, OpcodeSpec};
+pub use self::instruction::{Instruction, Mode, Opcode, Operand, OPCODES};
+pub use self::io::{InputSource, OutputSink};
+pub use self::memory::{Memory, PAGE_SIZE};
+pub use self::profile::Profile;
+pub use self::profiler::{Counter, Profiler};
+pub use self::snapshot::Snapshot;
+pub use self::word::{parse_code, Word};
+
+use std::{
+    collections::{vec_deque, VecDeque},
+    error, fmt,
+    hash::{Hash, Hasher},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+/// An Intcode machine with memory cells of type `W`.
+#[derive(Debug)]
+pub struct Computer<W: Word = i64> {
+    mem: Memory<W>,
+    /// Instruction pointer
+    ip: i64,
+    /// Relative base offset
+    rbo: i64,
+    halted: bool,
+    inputs: VecDeque<W>,
+    outputs: VecDeque<W>,
+    probes: breakpoint::Probes<W>,
+    tracer: Option<trace::Tracer<W>>,
+    history: Option<history::History<W>>,
+    watchdog: watchdog::Watchdog<W>,
+    arithmetic: Arithmetic,
+    extensions: extension::Extensions<W>,
+    isa: profile::Isa,
+    trail: diagnostic::Trail,
+    /// Path of the crash dumps
+    crash_dump: Option<PathBuf>,
+    profiler: Option<Arc<Mutex<Profiler>>>,
+    coverage: Option<Arc<Mutex<Coverage>>>,
+    engine: Engine,
+    cache: engine::Cache,
+}
+
+/// Clones share memory pages until either machine writes to them.
+///
+/// The clone keeps the breakpoints, watchpoints, instruction budget, loop detection,
+/// arithmetic policy, extensions, profile, crash dump path and engine, but neither the
+/// trace sink, the undo history nor the decoded instructions. A profiled machine and its
+/// clones share their counts, and their coverage.
+impl<W: Word> Clone for Computer<W> {
+    fn clone(&self) -> Computer<W> {
+        Computer {
+            mem: self.mem.clone(),
+            ip: self.ip,
+            rbo: self.rbo,
+            halted: self.halted,
+            inputs: self.inputs.clone(),
+            outputs: self.outputs.clone(),
+            probes: self.probes.clone(),
+            tracer: None,
+            history: None,
+            watchdog: self.watchdog.clone(),
+            arithmetic: self.arithmetic,
+            extensions: self.extensions.clone(),
+            isa: self.isa,
+            trail: self.trail.clone(),
+            crash_dump: self.crash_dump.clone(),
+            profiler: self.profiler.clone(),
+            coverage: self.coverage.clone(),
+            engine: self.engine,
+            cache: engine::Cache::default(),
+        }
+    }
+}
+
+/// Machines are equal when their memory, registers and I/O queues are, breakpoints,
+/// watchpoints, tracing, history and the watchdog are ignored.
+impl<W: Word> PartialEq for Computer<W> {
+    fn eq(&self, other: &Computer<W>) -> bool {
+        self.ip == other.ip
+            && self.rbo == other.rbo
+            && self.halted == other.halted
+            && self.inputs == other.inputs
+            && self.outputs == other.outputs
+            && self.mem == other.mem
+    }
+}
+
+impl<W: Word> Eq for Computer<W> {}
+
+impl<W: Word> Hash for Computer<W> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.ip.hash(state);
+        self.rbo.hash(state);
+        self.halted.hash(state);
+        self.inputs.hash(state);
+        self.outputs.hash(state);
+        self.mem.hash(state);
+    }
+}
+
+impl<W: Word> Computer<W> {
+    /// Loads `code` into a fresh machine.
+    ///
+    /// Memory grows as the program writes to it, `memory_limit` optionally caps the number
+    /// of addressable cells: it does not preallocate anything, and reads or writes at or
+    /// beyond it fail with [`InvalidRead`](ErrorKind::InvalidRead) or
+    /// [`InvalidWrite`](ErrorKind::InvalidWrite). Pass `None` unless the program must be
+    /// confined.
+    pub fn new(code: &[W], memory_limit: Option<usize>) -> Computer<W> {
+        Computer {
+            mem: Memory::new(code, memory_limit),
+            ip: 0,
+            halted: false,
+            rbo: 0,
+            inputs: VecDeque::new(),
+            outputs: VecDeque::new(),
+            probes: breakpoint::Probes::default(),
+            tracer: None,
+            history: None,
+            watchdog: watchdog::Watchdog::default(),
+            arithmetic: Arithmetic::default(),
+            extensions: extension::Extensions::default(),
+            isa: profile::Isa::default(),
+            trail: diagnostic::Trail::default(),
+            crash_dump: None,
+            profiler: None,
+            coverage: None,
+            engine: Engine::default(),
+            cache: engine::Cache::default(),
+        }
+    }
+
+    #[inline]
+    fn error(&self, kind: ErrorKind<W>) -> Error<W> {
+        Error {
+            location: self.ip,
+            context: kind.is_fault().then(|| Box::new(self.context(&kind))),
+            kind,
+        }
+    }
+
+    /// Address of the next instruction to run.
+    #[inline]
+    pub fn ip(&self) -> i64 {
+        self.ip
+    }
+
+    /// Relative base offset, used by relative mode parameters.
+    #[inline]
+    pub fn rbo(&self) -> i64 {
+        self.rbo
+    }
+
+    #[inline]
+    pub fn memory(&self) -> &Memory<W> {
+        &self.mem
+    }
+
+    #[inline]
+    pub fn read_raw(&self, index: i64) -> Result<W, W> {
+        if index < 0 {
+            return Err(self.error(ErrorKind::InvalidRead(index)));
+        }
+        self.mem
+            .get(index as usize)
+            .ok_or_else(|| self.error(ErrorKind::InvalidRead(index)))
+    }
+
+    #[inline]
+    pub fn write_raw(&mut self, index: i64, value: W) -> Result<(), W> {
+        if index >= 0 && self.mem.in_bounds(index as usize) {
+            self.mem.set(index as usize, value);
+            self.cache.invalidate(index);
+            Ok(())
+        } else {
+            Err(self.error(ErrorKind::InvalidWrite(index, value)))
+        }
+    }
+
+    /// Converts a word used as an address or relative base adjustment.
+    #[inline]
+    fn address(&self, value: &W) -> Result<i64, W> {
+        value
+            .to_i64()
+            .ok_or_else(|| self.error(ErrorKind::InvalidAddress(value.clone())))
+    }
+
+    /// Address designated by the parameter at `index`.
+    #[inline]
+    fn param_address(&self, mode: Mode, index: i64) -> Result<i64, W> {
+        let param = self.read_raw(index)?;
+        let offset = self.address(&param)?;
+        match mode {
+            Mode::Immediate | Mode::Position => Ok(offset),
+            Mode::Relative => self
+                .rbo
+                .checked_add(offset)
+                .ok_or_else(|| self.error(ErrorKind::InvalidAddress(param))),
+        }
+    }
+
+    #[inline]
+    fn read(&mut self, mode: Mode, index: i64) -> Result<W, W> {
+        if mode == Mode::Immediate {
+            return self.read_raw(index);
+        }
+        let addr = self.param_address(mode, index)?;
+        let value = self.read_raw(addr)?;
+        self.watch(Access::Read, addr, &value);
+        self.trace_access(Access::Read, addr, &value);
+        Ok(value)
+    }
+
+    #[inline]
+    fn write(&mut self, mode: Mode, index: i64, value: W) -> Result<(), W> {
+        let addr = self.param_address(mode, index)?;
+        self.store(addr, value)
+    }
+
+    /// Data write made by an instruction.
+    #[inline]
+    fn store(&mut self, addr: i64, value: W) -> Result<(), W> {
+        self.history_write(addr);
+        self.write_raw(addr, value.clone())?;
+        self.watch(Access::Write, addr, &value);
+        self.trace_access(Access::Write, addr, &value);
+        Ok(())
+    }
+
+    #[inline]
+    /// Decodes a core instruction, the error is only built if no extension handles the
+    /// word.
+    fn decode_instruction(word: W) -> std::result::Result<(i64, Instruction), ErrorKind<W>> {
+        let word = word.to_i64().ok_or(ErrorKind::InvalidInstruction(word))?;
+        let instruction = Instruction::decode(word).map_err(ErrorKind::from_decode)?;
+        Ok((word, instruction))
+    }
+
+    #[inline]
+    fn run_instruction(&mut self) -> Result<Action<W>, W> {
+        let ip = self.ip;
+        let Instruction { opcode, modes } = match Self::decode_instruction(self.read_raw(ip)?) {
+            Ok((word, instruction)) => {
+                self.check_instruction(word, instruction)?;
+                instruction
+            }
+            Err(kind) => return self.run_extension(kind),
+        };
+        match opcode {
+            Opcode::Add => {
+                // add: p3 = p1 + p2
+                let (p1, p2) = (self.read(modes[0], ip + 1)?, self.read(modes[1], ip + 2)?);
+                self.write(modes[2], ip + 3, self.add(&p1, &p2)?)?;
+                self.ip += 4;
+            }
+            Opcode::Mul => {
+                // mul: p3 = p1 * p2
+                let (p1, p2) = (self.read(modes[0], ip + 1)?, self.read(modes[1], ip + 2)?);
+                self.write(modes[2], ip + 3, self.mul(&p1, &p2)?)?;
+                self.ip += 4;
+            }
+            Opcode::Input => {
+                // ipt: p1 = <input>
+                let i = match self.inputs.pop_front() {
+                    Some(i) => i,
+                    None => return Ok(Action::Input),
+                };
+                self.trace_with(|step| step.input = Some(i.clone()));
+                self.history_input(&i);
+                self.write(modes[0], ip + 1, i)?;
+                self.ip += 2;
+            }
+            Opcode::Output => {
+                // out: p1 -> <output>
+                let p1 = self.read(modes[0], ip + 1)?;
+                self.trace_with(|step| step.output = Some(p1.clone()));
+                self.ip += 2;
+                return Ok(Action::Output(p1));
+            }
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse => {
+                // jnz: if p1 != 0 { ip = p2 }
+                // jpz: if p1 == 0 { ip = p2 }
+                let taken =
+                    self.read(modes[0], ip + 1)?.is_zero() == (opcode == Opcode::JumpIfFalse);
+                self.ip = if taken {
+                    let p2 = self.read(modes[1], ip + 2)?;
+                    self.address(&p2)?
+                } else {
+                    ip + 3
+                };
+                return Ok(Action::Branch(taken));
+            }
+            Opcode::LessThan => {
+                // clt: p3 = p1 < p2 ? 1 : 0
+                let (p1, p2) = (self.read(modes[0], ip + 1)?, self.read(modes[1], ip + 2)?);
+                self.write(modes[2], ip + 3, W::from((p1 < p2) as i32))?;
+                self.ip += 4
+            }
+            Opcode::Equals => {
+                // ceq: p3 = p1 == p2 ? 1 : 0
+                let (p1, p2) = (self.read(modes[0], ip + 1)?, self.read(modes[1], ip + 2)?);
+                self.write(modes[2], ip + 3, W::from((p1 == p2) as i32))?;
+                self.ip += 4
+            }
+            Opcode::AdjustBase => {
+                // rbo: rbo = p1
+                let p1 = self.read(modes[0], ip + 1)?;
+                let rbo = self
+                    .address(&p1)?
+                    .checked_add(self.rbo)
+                    .ok_or_else(|| self.error(ErrorKind::InvalidAddress(p1)))?;
+                self.rbo = rbo;
+                self.trace_with(|step| step.rbo = Some(rbo));
+                self.ip += 2;
+            }
+            Opcode::Halt => return Ok(Action::Shutdown),
+        };
+        Ok(Action::Continue)
+    }
+
+    #[inline]
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Appends a value to the input queue.
+    #[inline]
+    pub fn push_input(&mut self, value: W) {
+        self.inputs.push_back(value);
+    }
+
+    #[inline]
+    pub fn push_inputs<I>(&mut self, values: I)
+    where
+        I: IntoIterator<Item = W>,
+    {
+        self.inputs.extend(values);
+    }
+
+    /// Values queued for the read instruction.
+    #[inline]
+    pub fn pending_inputs(&self) -> &VecDeque<W> {
+        &self.inputs
+    }
+
+    /// Appends the ASCII code of each byte of `s` to the input queue.
+    #[inline]
+    pub fn push_str(&mut self, s: &str) {
+        self.inputs.extend(s.bytes().map(|b| W::from(i32::from(b))));
+    }
+
+    /// Pops the oldest value of the output buffer.
+    #[inline]
+    pub fn take_output(&mut self) -> Option<W> {
+        self.outputs.pop_front()
+    }
+
+    #[inline]
+    pub fn drain_outputs(&mut self) -> vec_deque::Drain<'_, W> {
+        self.outputs.drain(..)
+    }
+
+    /// Runs the program until it outputs a value, halts, waits for input while the input
+    /// queue is empty or hits a breakpoint or watchpoint.
+    ///
+    /// `inputs` are appended to the input queue, values the program does not read yet
+    /// are kept for the next calls.
+    /// When blocked on input the instruction pointer stays on the read instruction, so
+    /// calling `run` again with more inputs picks up where the program left off.
+    pub fn run<I>(&mut self, inputs: I) -> Result<Stopped<W>, W>
+    where
+        I: IntoIterator<Item = W>,
+    {
+        self.inputs.extend(inputs);
+        if self.halted {
+            return Ok(Stopped::Halted);
+        }
+        if self.can_run_cached() {
+            return self.run_cached();
+        }
+
+        loop {
+            if let Some(stop) = self.probe() {
+                return Ok(stop);
+            }
+            if let Some(stop) = self.execute().map_err(|err| self.crashed(err))? {
+                return Ok(stop);
+            }
+        }
+    }
+
+    /// Runs a single instruction, regardless of breakpoints.
+    ///
+    /// Returns `None` if the instruction completed without output or triggering a
+    /// watchpoint.
+    pub fn step(&mut self) -> Result<Option<Stopped<W>>, W> {
+        if self.halted {
+            return Ok(Some(Stopped::Halted));
+        }
+        match self.execute().map_err(|err| self.crashed(err))? {
+            Some(stop) => Ok(Some(stop)),
+            None => Ok(self.probe()),
+        }
+    }
+
+    #[inline]
+    fn execute(&mut self) -> Result<Option<Stopped<W>>, W> {
+        self.watchdog_begin()?;
+        self.trace_begin();
+        self.history_begin();
+        let ip = self.ip;
+        let word = self.profiling_begin();
+        let action = self.run_instruction()?;
+        if matches!(action, Action::Input) {
+            self.watchdog_refund();
+        } else {
+            let branch = match action {
+                Action::Branch(taken) => Some(taken),
+                _ => None,
+            };
+            self.trail_push(ip);
+            self.profiling_end(ip, word, branch);
+            self.coverage_end(ip, branch);
+            self.probe_done();
+            self.history_end();
+            self.trace_end()
+                .map_err(|err| self.error(ErrorKind::Io(err)))?;
+            self.watchdog_end(matches!(action, Action::Output(_) | Action::Shutdown))?;
+        }
+        Ok(match action {
+            Action::Input => Some(Stopped::NeedsInput),
+            Action::Output(out) => Some(Stopped::Output(out)),
+            Action::Shutdown => {
+                self.halted = true;
+                Some(Stopped::Halted)
+            }
+            Action::Continue | Action::Branch(_) => None,
+        })
+    }
+
+    /// Runs the program until it halts, waits for input or hits a breakpoint or
+    /// watchpoint, storing its outputs in the output buffer.
+    pub fn run_buffered(&mut self) -> Result<Stopped<W>, W> {
+        loop {
+            match self.run(None)? {
+                Stopped::Output(out) => self.outputs.push_back(out),
+                state => return Ok(state),
+            }
+        }
+    }
+
+    /// Like [`run`](Computer::run), but treats a blocked read, breakpoints and watchpoints
+    /// as errors.
+    pub fn resume<I>(&mut self, inputs: I) -> Result<Option<W>, W>
+    where
+        I: IntoIterator<Item = W>,
+    {
+        match self.run(inputs)? {
+            Stopped::Output(out) => Ok(Some(out)),
+            Stopped::Halted => Ok(None),
+            Stopped::NeedsInput => Err(self.error(ErrorKind::NoInput)),
+            stop => Err(self.error(ErrorKind::Interrupted(stop))),
+        }
+    }
+
+    #[inline]
+    pub fn resume_get<I>(&mut self, inputs: I) -> Result<W, W>
+    where
+        I: IntoIterator<Item = W>,
+    {
+        match self.resume(inputs) {
+            Ok(None) => Err(self.error(ErrorKind::NoOutput)),
+            Ok(Some(o)) => Ok(o),
+            Err(err) => Err(err),
+        }
+    }
+
+    #[inline]
+    pub fn resume_iter<I>(mut self, inputs: I) -> ResumeIter<W>
+    where
+        I: IntoIterator<Item = W>,
+    {
+        self.push_inputs(inputs);
+        ResumeIter {
+            computer: self,
+            finished: false,
+        }
+    }
+}
+
+impl Computer {
+    /// Runs the program against `device` until it halts, `device` runs out of input or a
+    /// breakpoint or watchpoint is hit.
+    pub fn run_device<D>(&mut self, device: &mut D) -> Result<Stopped>
+    where
+        D: InputSource + OutputSink,
+    {
+        loop {
+            match self.run(None)? {
+                Stopped::Output(out) => device
+                    .output(out)
+                    .map_err(|err| self.error(ErrorKind::Io(err)))?,
+                Stopped::NeedsInput => {
+                    match device
+                        .flush()
+                        .and_then(|_| device.next_input())
+                        .map_err(|err| self.error(ErrorKind::Io(err)))?
+                    {
+                        Some(value) => self.push_input(value),
+                        None => return Ok(Stopped::NeedsInput),
+                    }
+                }
+                state => {
+                    device
+                        .flush()
+                        .map_err(|err| self.error(ErrorKind::Io(err)))?;
+                    return Ok(state);
+                }
+            }
+        }
+    }
+
+    /// Runs the program until it halts, `input` runs dry or a breakpoint or watchpoint is
+    /// hit, sending its outputs to `output`.
+    #[inline]
+    pub fn run_with<S, O>(&mut self, input: S, output: O) -> Result<Stopped>
+    where
+        S: InputSource,
+        O: OutputSink,
+    {
+        self.run_device(&mut io::Pair(input, output))
+    }
+}
+
+#[derive(Debug)]
+enum Action<W> {
+    Shutdown,
+    Continue,
+    /// A conditional jump completed, jumping or not
+    Branch(bool),
+    Input,
+    Output(W),
+}
+
+/// Reason why [`Computer::run`] returned control to the caller.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Stopped<W = i64> {
+    /// The program produced a value
+    Output(W),
+    /// The program is blocked on a read instruction
+    NeedsInput,
+    /// The program reached its halt instruction
+    Halted,
+    /// The next instruction has a breakpoint set on it
+    Breakpoint(Breakpoint),
+    /// The previous instruction triggered a watchpoint
+    Watch(WatchHit<W>),
+}
+
+/// An error of a [`Computer`], with the state of the machine when it happened.
+///
+/// `Display` renders a one-line message, [`report`](Error::report) the full state. The
+/// state is only captured for faults of the program, not for
+/// [`NoInput`](ErrorKind::NoInput), [`NoOutput`](ErrorKind::NoOutput) and
+/// [`Interrupted`](ErrorKind::Interrupted) which callers routinely handle.
+#[derive(Debug)]
+pub struct Error<W = i64> {
+    location: i64,
+    kind: ErrorKind<W>,
+    context: Option<Box<diagnostic::Context<W>>>,
+}
+
+#[derive(Debug)]
+pub enum ErrorKind<W = i64> {
+    IllegalOpcode(i64),
+    InvalidRead(i64),
+    InvalidWrite(i64, W),
+    InvalidParareterMode(i64),
+    NoInput,
+    NoOutput,
+    Io(std::io::Error),
+    Interrupted(Stopped<W>),
+    /// The instruction budget was spent
+    BudgetExhausted,
+    /// The machine state repeated without I/O, with this period in instructions
+    InfiniteLoop(u64),
+    /// Checked arithmetic overflowed, with the operands
+    Overflow(W, W),
+    /// A word used as an address or relative base adjustment does not fit in an `i64`,
+    /// or the resulting address overflows
+    InvalidAddress(W),
+    /// An instruction word does not fit in an `i64`
+    InvalidInstruction(W),
+    /// The opcode is not part of the selected [`Profile`]
+    UnsupportedOpcode(Opcode),
+    /// The parameter mode is not part of the selected [`Profile`]
+    UnsupportedMode(Mode),
+    /// Strict mode: the destination parameter is in immediate mode
+    ImmediateWrite,
+    /// Strict mode: the instruction word has non-zero mode digits for parameters it
+    /// does not have
+    ExtraModeDigits(i64),
+}
+
+impl<W> Error<W> {
+    /// Address of the instruction that failed.
+    #[inline]
+    pub fn location(&self) -> i64 {
+        self.location
+    }
+
+    #[inline]
+    pub fn kind(&self) -> &ErrorKind<W> {
+        &self.kind
+    }
+}
+
+pub type Result<T, W = i64> = std::result::Result<T, Error<W>>;
+
+impl<W> ErrorKind<W> {
+    fn description(&self) -> &str {
+        match self {
+            ErrorKind::IllegalOpcode(_) => "illegal opcode",
+            ErrorKind::NoInput => "reached read instruction with no user input",
+            ErrorKind::NoOutput => "program did not return a value",
+            ErrorKind::InvalidRead(_) => "tried to read value outside memory bounds",
+            ErrorKind::InvalidWrite(_, _) => "tried to write value outside memory bounds",
+            ErrorKind::InvalidParareterMode(_) => "invalid parameter mode",
+            ErrorKind::Io(_) => "device I/O failed",
+            ErrorKind::Interrupted(_) => "execution paused by a breakpoint or watchpoint",
+            ErrorKind::BudgetExhausted => "instruction budget exhausted",
+            ErrorKind::InfiniteLoop(_) => "program is stuck in an infinite loop",
+            ErrorKind::Overflow(_, _) => "arithmetic overflow",
+            ErrorKind::InvalidAddress(_) => "invalid address",
+            ErrorKind::InvalidInstruction(_) => "instruction word out of range",
+            ErrorKind::UnsupportedOpcode(_) => "opcode not supported by the profile",
+            ErrorKind::UnsupportedMode(_) => "parameter mode not supported by the profile",
+            ErrorKind::ImmediateWrite => "write to an immediate mode parameter",
+            ErrorKind::ExtraModeDigits(_) => "extra mode digits in instruction",
+        }
+    }
+
+    /// Whether the error is a fault of the program, rather than the machine stopping
+    /// where the caller did not expect it.
+    #[inline]
+    fn is_fault(&self) -> bool {
+        !matches!(
+            self,
+            ErrorKind::NoInput | ErrorKind::NoOutput | ErrorKind::Interrupted(_)
+        )
+    }
+
+    /// Converts an error returned by [`Instruction::decode`].
+    fn from_decode(kind: ErrorKind) -> ErrorKind<W> {
+        match kind {
+            ErrorKind::IllegalOpcode(op) => ErrorKind::IllegalOpcode(op),
+            ErrorKind::InvalidParareterMode(mode) => ErrorKind::InvalidParareterMode(mode),
+            kind => unreachable!("not a decoding error: {:?}", kind),
+        }
+    }
+}
+
+impl<W: Word> error::Error for Error<W> {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self.kind {
+            ErrorKind::Io(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl<W: Word> fmt::Display for Error<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "IntCode error at {}: {}",
+            self.location,
+            self.kind.description()
+        )?;
+        match self.kind {
+            ErrorKind::IllegalOpcode(op) => write!(f, " {}", op),
+            ErrorKind::InvalidRead(addr) => write!(f, " at address {}", addr),
+            ErrorKind::InvalidWrite(addr, ref val) => {
+                write!(f, ", write value {} at address {}", val, addr)
+            }
+            ErrorKind::InvalidParareterMode(mode) => write!(f, " {}", mode),
+            ErrorKind::Io(ref err) => write!(f, ": {}", err),
+            ErrorKind::Interrupted(ref stop) => write!(f, " ({:?})", stop),
+            ErrorKind::InfiniteLoop(period) => write!(f, " of {} instructions", period),
+            ErrorKind::Overflow(ref lhs, ref rhs) => {
+                write!(f, " with operands {} and {}", lhs, rhs)
+            }
+            ErrorKind::InvalidAddress(ref value) | ErrorKind::InvalidInstruction(ref value) => {
+                write!(f, " {}", value)
+            }
+            ErrorKind::UnsupportedOpcode(opcode) => write!(f, " {}", opcode.mnemonic()),
+            ErrorKind::UnsupportedMode(mode) => write!(f, " {:?}", mode),
+            ErrorKind::ExtraModeDigits(word) => write!(f, " {}", word),
+            _ => Ok(()),
+        }
+    }
+}
+
+pub struct ResumeIter<W: Word = i64> {
+    computer: Computer<W>,
+    finished: bool,
+}
+
+impl<W: Word> Iterator for ResumeIter<W> {
+    type Item = Result<W, W>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        match self.computer.resume(None) {
+            Ok(Some(out)) => Some(Ok(out)),
+            Ok(None) => {
+                self.finished = true;
+                None
+            }
+            Err(err) => {
+                self.finished = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::examples::QUINE;
+    use super::*;
+
+    #[test]
+    fn memory_grows() {
+        let outputs: Vec<i64> = Computer::new(QUINE, None)
+            .resume_iter(None)
+            .map(|out| out.unwrap())
+            .collect();
+        assert_eq!(outputs, QUINE);
+    }
+
+    #[test]
+    fn memory_limit() {
+        // reads its counter at address 100 after the first output
+        let err = Computer::new(QUINE, Some(100))
+            .resume_iter(None)
+            .find_map(|out| out.err())
+            .unwrap();
+        assert!(matches!(err.kind(), ErrorKind::InvalidRead(100)));
+
+        let mut computer = Computer::new(&[1101, 1, 2, 10, 99], Some(10));
+        let err = computer.resume(None).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::InvalidWrite(10, 3)));
+        assert_eq!(err.location(), 0);
+        assert_eq!(computer.write_raw(9, 1).ok(), Some(()));
+        assert!(computer.read_raw(10).is_err());
+        assert!(computer.read_raw(-1).is_err());
+    }
+
+    #[test]
+    fn run_stops() {
+        // reads a value, outputs it twice, halts
+        let mut computer = Computer::new(&[3, 9, 4, 9, 4, 9, 99, 0, 0, 0], None);
+        assert_eq!(computer.run(None).unwrap(), Stopped::NeedsInput);
+        assert_eq!(computer.ip(), 0);
+        assert_eq!(computer.run(None).unwrap(), Stopped::NeedsInput);
+        assert_eq!(computer.run(Some(7)).unwrap(), Stopped::Output(7));
+        assert_eq!(computer.run(None).unwrap(), Stopped::Output(7));
+        assert_eq!(computer.run(None).unwrap(), Stopped::Halted);
+        assert!(computer.is_halted());
+        assert_eq!(computer.run(Some(1)).unwrap(), Stopped::Halted);
+    }
+
+    #[test]
+    fn resume_errors() {
+        let mut computer = Computer::new(&[3, 0, 99], None);
+        let err = computer.resume(None).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::NoInput));
+        assert_eq!(computer.resume(Some(1)).unwrap(), None);
+
+        let err = Computer::new(&[99], None).resume_get(None).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::NoOutput));
+        let err = Computer::new(&[42], None).run(None).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::IllegalOpcode(42)));
+    }
+
+    #[test]
+    fn input_queue_persists() {
+        // adds two inputs, outputs the sum, twice
+        let code = [3, 20, 3, 21, 1, 20, 21, 22, 4, 22, 1105, 1, 0];
+        let mut computer = Computer::new(&code, None);
+        assert_eq!(computer.run(vec![1, 2, 3]).unwrap(), Stopped::Output(3));
+        assert_eq!(computer.pending_inputs(), &[3]);
+        assert_eq!(computer.run(Some(4)).unwrap(), Stopped::Output(7));
+        assert!(computer.pending_inputs().is_empty());
+        assert_eq!(computer.run(None).unwrap(), Stopped::NeedsInput);
+    }
+
+    #[test]
+    fn output_buffer() {
+        let mut computer = Computer::new(&[3, 9, 4, 9, 1105, 1, 0, 99, 0, 0], None);
+        computer.push_str("ab");
+        assert_eq!(computer.run_buffered().unwrap(), Stopped::NeedsInput);
+        assert_eq!(computer.take_output(), Some(97));
+        computer.push_input(5);
+        assert_eq!(computer.run_buffered().unwrap(), Stopped::NeedsInput);
+        assert_eq!(computer.drain_outputs().collect::<Vec<_>>(), vec![98, 5]);
+        assert_eq!(computer.take_output(), None);
+    }
+
+    fn hash(computer: &Computer) -> u64 {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        computer.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn clones() {
+        let mut computer = Computer::new(QUINE, None);
+        computer.add_breakpoint(Breakpoint::Address(12));
+        computer.enable_history(10);
+        assert_eq!(computer.run(None).unwrap(), Stopped::Output(109));
+        let mut clone = computer.clone();
+        assert!(clone == computer);
+        assert_eq!(hash(&clone), hash(&computer));
+        assert_eq!(clone.history_len(), 0);
+
+        // the clone keeps the breakpoints, and its memory is its own
+        assert_eq!(
+            clone.run(None).unwrap(),
+            Stopped::Breakpoint(Breakpoint::Address(12))
+        );
+        assert_eq!(clone.read_raw(100).unwrap(), 1);
+        assert_eq!(computer.read_raw(100).unwrap(), 0);
+        assert!(clone != computer);
+        clone.write_raw(0, 0).unwrap();
+        assert_eq!(computer.read_raw(0).unwrap(), 109);
+        assert_eq!(
+            computer.run(None).unwrap(),
+            Stopped::Breakpoint(Breakpoint::Address(12))
+        );
+    }
+
+    #[test]
+    fn equality() {
+        // reads a value and clears it
+        let code = [3, 9, 1002, 9, 0, 9, 1105, 1, 0, 0];
+        let mut a = Computer::new(&code, None);
+        let mut b = Computer::new(&code, None);
+        a.push_input(5);
+        assert!(a != b);
+        b.push_input(7);
+        assert!(a != b);
+        a.step().unwrap();
+        b.step().unwrap();
+        assert!(a != b);
+        a.step().unwrap();
+        b.step().unwrap();
+        assert!(a == b);
+        assert_eq!(hash(&a), hash(&b));
+
+        // allocation and the memory size do not matter, the limit does
+        b.write_raw(5000, 0).unwrap();
+        assert!(a == b);
+        assert_eq!(hash(&a), hash(&b));
+        let bounded = Computer::new(&code, Some(10));
+        assert!(bounded != Computer::new(&code, None));
+        assert!(bounded == Computer::new(&code, Some(10)));
+
+        let mut c = a.clone();
+        c.rbo = 1;
+        assert!(a != c);
+        let mut c = a.clone();
+        c.outputs.push_back(0);
+        assert!(a != c);
+        let mut c = a.clone();
+        c.halted = true;
+        assert!(a != c);
+    }
+
+    // the shared profile is not part of the hash
+    #[allow(clippy::mutable_key_type)]
+    #[test]
+    fn state_search() {
+        // reads an input and loops back when it is zero, halts otherwise
+        let code = [3, 7, 1006, 7, 0, 99, 0, 0];
+        let start = Computer::new(&code, None);
+        let mut seen = std::collections::HashSet::new();
+        let mut queue = VecDeque::from(vec![start]);
+        let mut halted = 0;
+        while let Some(computer) = queue.pop_front() {
+            if !seen.insert(computer.clone()) {
+                continue;
+            }
+            for input in 0..3 {
+                let mut next = computer.clone();
+                match next.run(Some(input)).unwrap() {
+                    Stopped::Halted => halted += 1,
+                    Stopped::NeedsInput => queue.push_back(next),
+                    stop => panic!("unexpected {:?}", stop),
+                }
+            }
+        }
+        // a zero input brings the machine back to its start state
+        assert_eq!(seen.len(), 1);
+        assert_eq!(halted, 2);
+    }
+}
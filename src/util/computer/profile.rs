@@ -0,0 +1,218 @@
+//! Instruction set profiles and strict operand validation.
+
+use super::{Computer, ErrorKind, Instruction, Mode, Opcode, Result, Word};
+
+use std::fmt;
+
+/// Feature level of the instruction set, after the puzzle day that introduced it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Profile {
+    /// Add, multiply and halt, position mode only
+    Day02,
+    /// Adds I/O, jumps, comparisons and immediate mode
+    Day05,
+    /// Adds the relative base and relative mode
+    Day09,
+}
+
+impl Default for Profile {
+    #[inline]
+    fn default() -> Profile {
+        Profile::Day09
+    }
+}
+
+impl Profile {
+    pub fn supports_opcode(self, opcode: Opcode) -> bool {
+        match opcode {
+            Opcode::Add | Opcode::Mul | Opcode::Halt => true,
+            Opcode::AdjustBase => self >= Profile::Day09,
+            _ => self >= Profile::Day05,
+        }
+    }
+
+    pub fn supports_mode(self, mode: Mode) -> bool {
+        match mode {
+            Mode::Position => true,
+            Mode::Immediate => self >= Profile::Day05,
+            Mode::Relative => self >= Profile::Day09,
+        }
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Profile::Day02 => "day02",
+            Profile::Day05 => "day05",
+            Profile::Day09 => "day09",
+        })
+    }
+}
+
+/// Instruction set accepted by a [`Computer`].
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub(super) struct Isa {
+    profile: Profile,
+    strict: bool,
+}
+
+impl<W: Word> Computer<W> {
+    #[inline]
+    pub fn profile(&self) -> Profile {
+        self.isa.profile
+    }
+
+    /// Restricts the opcodes and parameter modes to `profile`, [`Profile::Day09`] (the
+    /// whole instruction set) by default.
+    ///
+    /// Running an instruction outside of the profile fails with
+    /// [`ErrorKind::UnsupportedOpcode`] or [`ErrorKind::UnsupportedMode`]. Extension
+    /// opcodes are not affected.
+    #[inline]
+    pub fn set_profile(&mut self, profile: Profile) {
+        self.isa.profile = profile;
+    }
+
+    #[inline]
+    pub fn is_strict(&self) -> bool {
+        self.isa.strict
+    }
+
+    /// Enables or disables strict operand validation.
+    ///
+    /// In strict mode, an immediate mode destination fails with
+    /// [`ErrorKind::ImmediateWrite`] instead of being treated as position mode, and a
+    /// non-zero mode digit past the parameters of the instruction fails with
+    /// [`ErrorKind::ExtraModeDigits`].
+    #[inline]
+    pub fn set_strict(&mut self, strict: bool) {
+        self.isa.strict = strict;
+    }
+
+    /// Whether the whole instruction set is accepted, without strict validation.
+    #[inline]
+    pub(super) fn isa_is_default(&self) -> bool {
+        self.isa == Isa::default()
+    }
+
+    /// Checks a core instruction against the profile and strict mode.
+    #[inline]
+    pub(super) fn check_instruction(&self, word: i64, instruction: Instruction) -> Result<(), W> {
+        if self.isa == Isa::default() {
+            return Ok(());
+        }
+        let Instruction { opcode, modes } = instruction;
+        if !self.isa.profile.supports_opcode(opcode) {
+            return Err(self.error(ErrorKind::UnsupportedOpcode(opcode)));
+        }
+        let modes = &modes[..opcode.arity()];
+        if let Some(&mode) = modes.iter().find(|&&m| !self.isa.profile.supports_mode(m)) {
+            return Err(self.error(ErrorKind::UnsupportedMode(mode)));
+        }
+        self.check_operands(word, modes, opcode.destination())
+    }
+
+    /// Strict mode checks of the instruction `word`, with the modes of its parameters.
+    pub(super) fn check_operands(
+        &self,
+        word: i64,
+        modes: &[Mode],
+        destination: Option<usize>,
+    ) -> Result<(), W> {
+        if !self.isa.strict {
+            return Ok(());
+        }
+        if destination.is_some_and(|d| modes[d] == Mode::Immediate) {
+            return Err(self.error(ErrorKind::ImmediateWrite));
+        }
+        if word / 10i64.pow(modes.len() as u32 + 2) != 0 {
+            return Err(self.error(ErrorKind::ExtraModeDigits(word)));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::examples::{DAY02, DAY05, QUINE};
+    use super::super::{Engine, Error, Stopped};
+    use super::*;
+
+    fn run(code: &[i64], profile: Profile, strict: bool) -> Result<Vec<i64>> {
+        let mut computer = Computer::new(code, None);
+        computer.set_profile(profile);
+        computer.set_strict(strict);
+        computer.set_engine(Engine::Cached);
+        computer.push_input(8);
+        let mut outputs = Vec::new();
+        loop {
+            match computer.run(None)? {
+                Stopped::Output(out) => outputs.push(out),
+                Stopped::Halted => return Ok(outputs),
+                stop => panic!("unexpected {:?}", stop),
+            }
+        }
+    }
+
+    fn error(code: &[i64], profile: Profile, strict: bool) -> Error {
+        run(code, profile, strict).unwrap_err()
+    }
+
+    #[test]
+    fn profiles() {
+        assert_eq!(Computer::new(DAY02, None).profile(), Profile::Day09);
+        assert!(Profile::Day02 < Profile::Day05 && Profile::Day05 < Profile::Day09);
+        assert_eq!(Profile::Day05.to_string(), "day05");
+
+        assert!(run(DAY02, Profile::Day02, true).is_ok());
+        assert!(matches!(
+            error(DAY05, Profile::Day02, false).kind(),
+            ErrorKind::UnsupportedOpcode(Opcode::Input)
+        ));
+        assert!(matches!(
+            error(&[1101, 1, 2, 0, 99], Profile::Day02, false).kind(),
+            ErrorKind::UnsupportedMode(Mode::Immediate)
+        ));
+        assert_eq!(run(DAY05, Profile::Day05, true).unwrap(), [1000]);
+        assert!(matches!(
+            error(QUINE, Profile::Day05, false).kind(),
+            ErrorKind::UnsupportedOpcode(Opcode::AdjustBase)
+        ));
+        assert!(matches!(
+            error(&[204, 0, 99], Profile::Day05, false).kind(),
+            ErrorKind::UnsupportedMode(Mode::Relative)
+        ));
+        assert_eq!(run(QUINE, Profile::Day09, true).unwrap(), QUINE);
+    }
+
+    #[test]
+    fn strict() {
+        // adds with an immediate destination, writing to 5 unless strict
+        let code = [11101, 1, 2, 5, 99, 0];
+        let mut computer = Computer::new(&code, None);
+        assert_eq!(computer.run(None).unwrap(), Stopped::Halted);
+        assert_eq!(computer.read_raw(5).unwrap(), 3);
+        let mut computer = Computer::new(&code, None);
+        computer.set_strict(true);
+        assert!(computer.is_strict());
+        let err = computer.run(None).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::ImmediateWrite));
+        assert_eq!(err.location(), 0);
+
+        assert!(run(&[3, 5, 99], Profile::Day09, true).is_ok());
+        assert!(matches!(
+            error(&[103, 5, 99], Profile::Day09, true).kind(),
+            ErrorKind::ImmediateWrite
+        ));
+        assert_eq!(run(&[1104, 7, 99], Profile::Day09, false).unwrap(), [7]);
+        assert!(matches!(
+            error(&[1104, 7, 99], Profile::Day09, true).kind(),
+            ErrorKind::ExtraModeDigits(1104)
+        ));
+        assert!(matches!(
+            error(&[104, 7, 199], Profile::Day09, true).kind(),
+            ErrorKind::ExtraModeDigits(199)
+        ));
+    }
+}
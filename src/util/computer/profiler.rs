@@ -0,0 +1,276 @@
+//! Execution profiler: instruction counts per opcode and per address.
+
+use super::{disasm::Disasm, Computer, Memory, Opcode, Word};
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    sync::{Arc, Mutex},
+};
+
+/// Executions of the instruction at an address.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Counter {
+    /// Opcode of the last execution
+    pub opcode: i64,
+    pub count: u64,
+    /// Executions that jumped, for conditional jumps
+    pub taken: u64,
+}
+
+/// Instruction counts collected by a profiling [`Computer`].
+#[derive(Debug, Clone, Default)]
+pub struct Profiler {
+    total: u64,
+    opcodes: BTreeMap<i64, Counter>,
+    addrs: HashMap<i64, Counter>,
+}
+
+#[inline]
+fn is_branch(opcode: i64) -> bool {
+    Opcode::from_code(opcode).is_some_and(Opcode::is_branch)
+}
+
+#[inline]
+fn mnemonic(opcode: i64) -> String {
+    Opcode::from_code(opcode).map_or_else(|| format!("OP{}", opcode), |op| op.mnemonic().to_owned())
+}
+
+#[inline]
+fn share(count: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 * 100.0 / total as f64
+    }
+}
+
+impl Profiler {
+    /// Number of executed instructions.
+    #[inline]
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Executions per opcode, by increasing opcode. `Counter::taken` sums the branches
+    /// taken by all the jumps with the opcode.
+    pub fn opcodes(&self) -> impl Iterator<Item = (i64, Counter)> + '_ {
+        self.opcodes
+            .iter()
+            .map(|(&opcode, &counter)| (opcode, counter))
+    }
+
+    #[inline]
+    pub fn address(&self, addr: i64) -> Option<Counter> {
+        self.addrs.get(&addr).copied()
+    }
+
+    /// Executed addresses, most executed first.
+    pub fn hot_spots(&self) -> Vec<(i64, Counter)> {
+        let mut spots: Vec<_> = self.addrs.iter().map(|(&a, &c)| (a, c)).collect();
+        spots.sort_by(|(a1, c1), (a2, c2)| c2.count.cmp(&c1.count).then(a1.cmp(a2)));
+        spots
+    }
+
+    /// Fraction of the executions of `opcode` that jumped, `None` if it is not a
+    /// conditional jump or never ran.
+    pub fn branch_ratio(&self, opcode: i64) -> Option<f64> {
+        let counter = self.opcodes.get(&opcode)?;
+        if is_branch(opcode) && counter.count > 0 {
+            Some(counter.taken as f64 / counter.count as f64)
+        } else {
+            None
+        }
+    }
+
+    /// Counts in the folded stack format of flame graph tools, one `MNEMONIC;addr count`
+    /// line per executed address.
+    pub fn folded(&self) -> String {
+        let mut spots: Vec<_> = self.addrs.iter().collect();
+        spots.sort_by_key(|&(&addr, _)| addr);
+        spots
+            .into_iter()
+            .fold(String::new(), |mut res, (addr, counter)| {
+                let name = mnemonic(counter.opcode);
+                writeln!(res, "{};{} {}", name, addr, counter.count).unwrap();
+                res
+            })
+    }
+
+    /// Renders the opcode counts and the `top` hot spots, with the disassembly of their
+    /// instruction in `mem`.
+    pub fn report(&self, mem: &Memory, top: usize) -> String {
+        let mut res = String::new();
+        let branch = |counter: &Counter| {
+            if is_branch(counter.opcode) {
+                format!("{:5.1}%", share(counter.taken, counter.count))
+            } else {
+                String::new()
+            }
+        };
+        writeln!(res, "{} instructions executed", self.total).unwrap();
+        writeln!(res, "opcode      count   share   taken").unwrap();
+        for (&opcode, counter) in &self.opcodes {
+            let line = format!(
+                "{:6} {:10} {:6.1}%  {}",
+                mnemonic(opcode),
+                counter.count,
+                share(counter.count, self.total),
+                branch(counter)
+            );
+            writeln!(res, "{}", line.trim_end()).unwrap();
+        }
+        writeln!(res, "hot spots:\n     count   share   taken  instruction").unwrap();
+        for (addr, counter) in self.hot_spots().into_iter().take(top) {
+            let line = match addr {
+                addr if addr >= 0 => Disasm::memory(mem).starting_at(addr as usize).next(),
+                _ => None,
+            };
+            let line = line
+                .filter(|line| line.addr() as i64 == addr)
+                .map_or_else(|| format!("{:5}: ?", addr), |line| line.to_string());
+            writeln!(
+                res,
+                "{:10} {:6.1}%  {:>6}  {}",
+                counter.count,
+                share(counter.count, self.total),
+                branch(&counter),
+                line
+            )
+            .unwrap();
+        }
+        res
+    }
+
+    fn record(&mut self, ip: i64, opcode: i64, taken: bool) {
+        self.total += 1;
+        for counter in [
+            self.opcodes.entry(opcode).or_default(),
+            self.addrs.entry(ip).or_default(),
+        ] {
+            counter.opcode = opcode;
+            counter.count += 1;
+            counter.taken += taken as u64;
+        }
+    }
+}
+
+impl<W: Word> Computer<W> {
+    /// Starts counting the executed instructions, resetting the counts if the machine was
+    /// already profiled.
+    ///
+    /// Clones of the machine keep adding to the same counts.
+    pub fn start_profiling(&mut self) {
+        self.profiler = Some(Arc::new(Mutex::new(Profiler::default())));
+    }
+
+    /// Stops profiling, returns the counts.
+    pub fn stop_profiling(&mut self) -> Option<Profiler> {
+        let profiler = self.profiler.take()?;
+        let profiler = profiler.lock().unwrap().clone();
+        Some(profiler)
+    }
+
+    /// Counts collected so far.
+    pub fn profiling_report(&self) -> Option<Profiler> {
+        self.profiler.as_ref().map(|p| p.lock().unwrap().clone())
+    }
+
+    /// Word of the instruction at `ip` if profiling, read before it runs in case it
+    /// overwrites itself.
+    #[inline]
+    pub(super) fn profiling_begin(&self) -> Option<i64> {
+        self.profiler.as_ref()?;
+        Some(
+            self.read_raw(self.ip)
+                .ok()
+                .and_then(|w| w.to_i64())
+                .unwrap_or(0),
+        )
+    }
+
+    /// Counts the completed instruction `word`, which started at `ip`. `branch` is the
+    /// outcome of a conditional jump, whether it jumped.
+    #[inline]
+    pub(super) fn profiling_end(&mut self, ip: i64, word: Option<i64>, branch: Option<bool>) {
+        if let (Some(profiler), Some(word)) = (&self.profiler, word) {
+            let taken = branch.unwrap_or(false);
+            profiler.lock().unwrap().record(ip, word % 100, taken);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{asm::assemble, examples::NESTED_LOOPS, Stopped};
+    use super::*;
+
+    fn profile(code: &[i64], inputs: &[i64]) -> Profiler {
+        let mut computer = Computer::new(code, None);
+        computer.start_profiling();
+        computer.push_inputs(inputs.iter().copied());
+        while let Stopped::Output(_) = computer.run(None).unwrap() {}
+        computer.stop_profiling().unwrap()
+    }
+
+    #[test]
+    fn branches() {
+        // jumps to the next instruction, then falls through to it
+        let profiler = profile(&[1105, 1, 3, 1106, 1, 7, 99, 99], &[]);
+        assert_eq!(profiler.total(), 3);
+        assert_eq!(profiler.address(0).unwrap().taken, 1);
+        assert_eq!(profiler.address(3).unwrap().taken, 0);
+        assert_eq!(profiler.branch_ratio(5), Some(1.0));
+        assert_eq!(profiler.branch_ratio(6), Some(0.0));
+        assert_eq!(profiler.branch_ratio(99), None);
+        assert_eq!(profiler.branch_ratio(1), None);
+        assert_eq!(profiler.address(6).unwrap().count, 1);
+        assert_eq!(profiler.address(7), None);
+    }
+
+    #[test]
+    fn counts() {
+        let code = assemble(NESTED_LOOPS).unwrap();
+        let profiler = profile(&code, &[5]);
+        let opcodes: u64 = profiler.opcodes().map(|(_, counter)| counter.count).sum();
+        assert_eq!(opcodes, profiler.total());
+
+        // the inner loop runs 10 times, the outer one 5
+        let spots = profiler.hot_spots();
+        assert_eq!(spots[0].1.count, 15);
+        assert!(spots.windows(2).all(|w| w[0].1.count >= w[1].1.count));
+        let (_, outer) = profiler.opcodes().find(|&(op, _)| op == 6).unwrap();
+        assert_eq!(outer.count, 6 + 15 + 10);
+        assert_eq!(outer.taken, 1 + 5 + 9);
+
+        let folded = profiler.folded();
+        assert!(folded.starts_with("IPT;0 1\nADD;2 1\nCLT;6 6\nJPZ;10 6\n"));
+        assert_eq!(folded.lines().count(), spots.len());
+
+        let report = profiler.report(&Memory::new(&code, None), 2);
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(
+            lines[0],
+            format!("{} instructions executed", profiler.total())
+        );
+        assert!(lines.contains(&"JPZ            31   27.9%   48.4%"));
+        assert!(lines.contains(&"JNZ            16   14.4%  100.0%"));
+        assert!(lines.contains(&"        15   13.5%   33.3%     21: JPZ [56], #45"));
+        assert_eq!(lines.len(), 2 + profiler.opcodes().count() + 2 + 2);
+    }
+
+    #[test]
+    fn clones_share_counts() {
+        let mut computer = Computer::new(&[104, 1, 99], None);
+        assert!(computer.profiling_report().is_none());
+        computer.start_profiling();
+        let mut clone = computer.clone();
+        computer.run(None).unwrap();
+        clone.run(None).unwrap();
+        assert_eq!(computer.profiling_report().unwrap().total(), 2);
+        computer.start_profiling();
+        assert_eq!(computer.profiling_report().unwrap().total(), 0);
+        assert_eq!(clone.stop_profiling().unwrap().total(), 2);
+        assert!(clone.profiling_report().is_none());
+    }
+}
@@ -0,0 +1,337 @@
+//! Saved machine states.
+//!
+//! # File format
+//!
+//! A snapshot file starts with the magic bytes `ICSN` and a version byte (`1`), followed
+//! by integers encoded as zigzag LEB128 varints, like binary traces:
+//!
+//! - `ip`, `rbo`, `halted` (`0` or `1`)
+//! - the memory limit plus one, `0` if memory is unbounded, and the memory length
+//! - the number of pending inputs and their values
+//! - the number of buffered outputs and their values
+//! - the number of allocated memory pages, then for each page the address of its first
+//!   cell and its [`PAGE_SIZE`] cells
+
+use super::{
+    trace::{invalid_data, read_varint, write_varint},
+    Computer, Memory, Word, PAGE_SIZE,
+};
+
+use std::{
+    collections::VecDeque,
+    convert::TryFrom,
+    fs::File,
+    io::{self, prelude::*, BufReader, BufWriter},
+    path::Path,
+};
+
+const MAGIC: &[u8; 4] = b"ICSN";
+const VERSION: u8 = 1;
+
+/// State of a [`Computer`]: memory, registers and I/O queues.
+///
+/// Breakpoints, watchpoints, traces and the undo history are not part of a snapshot.
+/// Only `i64` snapshots can be saved to a file.
+#[derive(Debug, Clone)]
+pub struct Snapshot<W = i64> {
+    mem: Memory<W>,
+    ip: i64,
+    rbo: i64,
+    halted: bool,
+    inputs: VecDeque<W>,
+    outputs: VecDeque<W>,
+}
+
+impl<W: Word> Snapshot<W> {
+    #[inline]
+    pub fn ip(&self) -> i64 {
+        self.ip
+    }
+
+    #[inline]
+    pub fn rbo(&self) -> i64 {
+        self.rbo
+    }
+
+    #[inline]
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    #[inline]
+    pub fn memory(&self) -> &Memory<W> {
+        &self.mem
+    }
+}
+
+impl Snapshot {
+    pub fn write_to<W: Write>(&self, mut out: W) -> io::Result<()> {
+        out.write_all(MAGIC)?;
+        out.write_all(&[VERSION])?;
+        let out = &mut out;
+        write_varint(out, self.ip)?;
+        write_varint(out, self.rbo)?;
+        write_varint(out, self.halted as i64)?;
+        write_varint(out, self.mem.limit().map_or(0, |limit| limit as i64 + 1))?;
+        write_varint(out, self.mem.len() as i64)?;
+        for queue in &[&self.inputs, &self.outputs] {
+            write_varint(out, queue.len() as i64)?;
+            for &value in queue.iter() {
+                write_varint(out, value)?;
+            }
+        }
+        write_varint(out, self.mem.pages().count() as i64)?;
+        for (addr, page) in self.mem.pages() {
+            write_varint(out, addr as i64)?;
+            for &value in page.iter() {
+                write_varint(out, value)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(mut input: R) -> io::Result<Snapshot> {
+        let mut header = [0; 5];
+        input.read_exact(&mut header)?;
+        if &header[..4] != MAGIC {
+            return Err(invalid_data("not an Intcode snapshot"));
+        }
+        if header[4] != VERSION {
+            return Err(invalid_data("unsupported snapshot version"));
+        }
+
+        let input = &mut input;
+        let ip = varint(input)?;
+        let rbo = varint(input)?;
+        let halted = varint(input)? != 0;
+        let limit = count(input)?.checked_sub(1);
+        let len = count(input)?;
+        let inputs = queue(input)?;
+        let outputs = queue(input)?;
+
+        let mut mem = Memory::new(&[], limit);
+        for _ in 0..count(input)? {
+            let start = count(input)?;
+            if start % PAGE_SIZE != 0 {
+                return Err(invalid_data("misaligned memory page"));
+            }
+            for addr in start..start + PAGE_SIZE {
+                if !mem.set(addr, varint(input)?) {
+                    return Err(invalid_data("memory page beyond the memory limit"));
+                }
+            }
+        }
+        mem.set_len(len);
+        Ok(Snapshot {
+            mem,
+            ip,
+            rbo,
+            halted,
+            inputs,
+            outputs,
+        })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write_to(&mut out)?;
+        out.flush()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Snapshot> {
+        Snapshot::read_from(BufReader::new(File::open(path)?))
+    }
+}
+
+fn varint<R: Read>(input: &mut R) -> io::Result<i64> {
+    read_varint(input)?.ok_or_else(|| io::ErrorKind::UnexpectedEof.into())
+}
+
+fn count<R: Read>(input: &mut R) -> io::Result<usize> {
+    usize::try_from(varint(input)?).map_err(|_| invalid_data("invalid count"))
+}
+
+fn queue<R: Read>(input: &mut R) -> io::Result<VecDeque<i64>> {
+    let len = count(input)?;
+    (0..len).map(|_| varint(input)).collect()
+}
+
+impl<W: Word> Computer<W> {
+    pub fn snapshot(&self) -> Snapshot<W> {
+        Snapshot {
+            mem: self.mem.clone(),
+            ip: self.ip,
+            rbo: self.rbo,
+            halted: self.halted,
+            inputs: self.inputs.clone(),
+            outputs: self.outputs.clone(),
+        }
+    }
+
+    /// Resets the machine to the state saved in `snapshot`.
+    ///
+    /// Breakpoints, watchpoints, tracing and the instruction budget are kept, the undo
+    /// history is cleared.
+    pub fn restore(&mut self, snapshot: &Snapshot<W>) {
+        self.mem = snapshot.mem.clone();
+        self.cache.clear();
+        self.ip = snapshot.ip;
+        self.rbo = snapshot.rbo;
+        self.halted = snapshot.halted;
+        self.inputs = snapshot.inputs.clone();
+        self.outputs = snapshot.outputs.clone();
+        self.history_clear();
+        self.trail_clear();
+        self.probe_reset();
+        self.watchdog_reset();
+    }
+
+    /// Creates a machine in the state saved in `snapshot`.
+    pub fn from_snapshot(snapshot: Snapshot<W>) -> Computer<W> {
+        let mut computer = Computer::new(&[], None);
+        computer.mem = snapshot.mem;
+        computer.ip = snapshot.ip;
+        computer.rbo = snapshot.rbo;
+        computer.halted = snapshot.halted;
+        computer.inputs = snapshot.inputs;
+        computer.outputs = snapshot.outputs;
+        computer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Stopped;
+    use super::*;
+
+    use std::{env, fs, process};
+
+    /// Echoes its inputs, stopped with pending inputs, buffered outputs and a sparse
+    /// memory.
+    fn echo() -> Computer {
+        let mut computer = Computer::new(&[3, 9, 4, 9, 1105, 1, 0, 99, 0, 0], Some(5000));
+        computer.push_inputs(vec![1, -2]);
+        assert_eq!(computer.run_buffered().unwrap(), Stopped::NeedsInput);
+        computer.push_inputs(vec![3, i64::MIN]);
+        computer.write_raw(3000, -7).unwrap();
+        computer
+    }
+
+    fn bytes(snapshot: &Snapshot) -> Vec<u8> {
+        let mut out = Vec::new();
+        snapshot.write_to(&mut out).unwrap();
+        out
+    }
+
+    fn assert_same(a: &Snapshot, b: &Snapshot) {
+        assert_eq!(a.ip(), b.ip());
+        assert_eq!(a.rbo(), b.rbo());
+        assert_eq!(a.is_halted(), b.is_halted());
+        assert_eq!(a.memory(), b.memory());
+        assert_eq!(a.memory().len(), b.memory().len());
+        assert_eq!(a.memory().limit(), b.memory().limit());
+        assert_eq!(a.inputs, b.inputs);
+        assert_eq!(a.outputs, b.outputs);
+    }
+
+    #[test]
+    fn round_trip() {
+        let snapshot = echo().snapshot();
+        assert_eq!(snapshot.memory().len(), 3001);
+        assert_eq!(snapshot.outputs, [1, -2]);
+        let read = Snapshot::read_from(&bytes(&snapshot)[..]).unwrap();
+        assert_same(&snapshot, &read);
+
+        let mut computer = Computer::from_snapshot(read);
+        assert_eq!(computer.run_buffered().unwrap(), Stopped::NeedsInput);
+        let outputs: Vec<i64> = computer.drain_outputs().collect();
+        assert_eq!(outputs, [1, -2, 3, i64::MIN]);
+        assert_eq!(computer.read_raw(3000).unwrap(), -7);
+        assert!(computer.read_raw(5000).is_err());
+
+        // halted machine, unbounded memory
+        let mut computer = Computer::new(&[99], None);
+        computer.run(None).unwrap();
+        let snapshot = computer.snapshot();
+        let read = Snapshot::read_from(&bytes(&snapshot)[..]).unwrap();
+        assert_same(&snapshot, &read);
+        assert!(read.is_halted());
+        assert_eq!(read.memory().limit(), None);
+    }
+
+    #[test]
+    fn files() {
+        let path = env::temp_dir().join(format!("intcode-snapshot-{}", process::id()));
+        let snapshot = echo().snapshot();
+        snapshot.save(&path).unwrap();
+        let loaded = Snapshot::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_same(&snapshot, &loaded.unwrap());
+        assert!(Snapshot::load(&path).is_err());
+    }
+
+    #[test]
+    fn restore() {
+        let mut computer = echo();
+        computer.enable_history(10);
+        let snapshot = computer.snapshot();
+        assert_eq!(computer.run_buffered().unwrap(), Stopped::NeedsInput);
+        computer.write_raw(3000, 0).unwrap();
+        computer.restore(&snapshot);
+        assert_eq!(computer.history_len(), 0);
+        assert_same(&computer.snapshot(), &snapshot);
+        assert_eq!(computer.run_buffered().unwrap(), Stopped::NeedsInput);
+        assert_eq!(computer.drain_outputs().count(), 4);
+    }
+
+    #[test]
+    fn truncated() {
+        let bytes = bytes(&echo().snapshot());
+        for len in 0..bytes.len() {
+            assert!(Snapshot::read_from(&bytes[..len]).is_err(), "{} bytes", len);
+        }
+    }
+
+    /// Snapshot file with the given header version and fields.
+    fn file(version: u8, fields: &[i64]) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        out.push(version);
+        for &field in fields {
+            write_varint(&mut out, field).unwrap();
+        }
+        out
+    }
+
+    #[test]
+    fn corrupt() {
+        let error = |bytes: &[u8]| Snapshot::read_from(bytes).unwrap_err().to_string();
+        let empty = [0, 0, 0, 0, 0, 0, 0, 0];
+        assert!(Snapshot::read_from(&file(VERSION, &empty)[..]).is_ok());
+
+        let mut bytes = file(VERSION, &empty);
+        bytes[..4].copy_from_slice(b"ICTR");
+        assert_eq!(error(&bytes), "not an Intcode snapshot");
+        assert_eq!(
+            error(&file(VERSION + 1, &empty)),
+            "unsupported snapshot version"
+        );
+        assert_eq!(
+            error(&file(VERSION, &[0, 0, 0, 0, 0, -1, 0, 0])),
+            "invalid count"
+        );
+        assert_eq!(
+            error(&file(VERSION, &[0, 0, 0, 0, 0, 0, 0, 1, 1])),
+            "misaligned memory page"
+        );
+
+        let page = PAGE_SIZE as i64;
+        let mut fields = vec![0, 0, 0, page + 1, 0, 0, 0, 1, page];
+        fields.extend(std::iter::repeat_n(1, PAGE_SIZE));
+        assert_eq!(
+            error(&file(VERSION, &fields)),
+            "memory page beyond the memory limit"
+        );
+        fields[3] = 2 * page + 1;
+        assert!(Snapshot::read_from(&file(VERSION, &fields)[..]).is_ok());
+    }
+}
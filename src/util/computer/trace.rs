@@ -0,0 +1,842 @@
+//! Execution traces: a record of every instruction run by a [`Computer`].
+//!
+//! Tracing is enabled with [`Computer::start_trace`], which sends a [`Step`] per executed
+//! instruction to a [`TraceSink`]. Traces can be stored in two formats, both readable
+//! back with [`TextReader`] and [`BinaryReader`] (or [`open`], which detects the format).
+//!
+//! # Text format
+//!
+//! The first line is `# intcode trace v1`, then each step is a line of space-separated
+//! fields:
+//!
+//! ```text
+//! 4: 1001 100 1 100 r100=5 w100=6 ; ADD [100], #1, [100]
+//! ```
+//!
+//! - `<ip>:` the address of the instruction
+//! - the instruction word followed by its raw parameter words
+//! - `r<addr>=<value>` for each data read, in execution order
+//! - `w<addr>=<value>` for the data write, if any
+//! - `rb=<value>` the relative base set by `RBO` or an extension, even if unchanged
+//! - `in=<value>` / `out=<value>` for consumed inputs and produced outputs
+//! - everything after `;` is a comment (the disassembly of the instruction)
+//!
+//! # Binary format
+//!
+//! The file starts with the magic bytes `ICTR` and a version byte (`1`). Each step then
+//! follows, with integers encoded as zigzag LEB128 varints:
+//!
+//! - `ip`, `word`, a byte with the number of parameters and the parameters
+//! - a byte with the number of reads, then `addr`, `value` pairs
+//! - a flags byte: `1` write, `2` relative base, `4` input, `8` output
+//! - the fields announced by the flags, in that order (`addr`, `value` for the write)
+//!
+//! [`Computer`]: super::Computer
+
+use super::{disasm::Line, Access, Computer, Instruction, Word};
+
+use std::{
+    convert::TryFrom,
+    fmt,
+    fs::File,
+    io::{self, prelude::*, BufReader, BufWriter},
+    path::Path,
+    sync::mpsc::Sender,
+};
+
+/// One executed instruction and its effects.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Step<W = i64> {
+    pub ip: i64,
+    /// The instruction word, opcode and parameter modes
+    pub word: W,
+    /// Raw parameter words, as they were before the instruction ran
+    pub params: Vec<W>,
+    /// Data reads as `(addr, value)` pairs, in execution order
+    pub reads: Vec<(i64, W)>,
+    /// Data write as an `(addr, value)` pair
+    pub write: Option<(i64, W)>,
+    /// Relative base set by the instruction, even if unchanged
+    pub rbo: Option<i64>,
+    pub input: Option<W>,
+    pub output: Option<W>,
+}
+
+impl Step {
+    /// Disassembly of the instruction, `None` if the word is not a valid instruction.
+    pub fn disasm(&self) -> Option<Line> {
+        let insn = Instruction::decode(self.word).ok()?;
+        let mut params = [0; 3];
+        for (param, &value) in params.iter_mut().zip(&self.params) {
+            *param = value;
+        }
+        Some(Line::Instruction {
+            addr: self.ip as usize,
+            insn,
+            params,
+        })
+    }
+}
+
+impl fmt::Display for Step {
+    /// Formats the step as a line of the text format, without the line break.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.ip, self.word)?;
+        for param in &self.params {
+            write!(f, " {}", param)?;
+        }
+        for (addr, value) in &self.reads {
+            write!(f, " r{}={}", addr, value)?;
+        }
+        if let Some((addr, value)) = self.write {
+            write!(f, " w{}={}", addr, value)?;
+        }
+        if let Some(rbo) = self.rbo {
+            write!(f, " rb={}", rbo)?;
+        }
+        if let Some(input) = self.input {
+            write!(f, " in={}", input)?;
+        }
+        if let Some(output) = self.output {
+            write!(f, " out={}", output)?;
+        }
+        if let Some(line) = self.disasm() {
+            let line = line.to_string();
+            // drop the address, it already starts the line
+            write!(f, " ; {}", line[line.find(": ").unwrap() + 2..].trim())?;
+        }
+        Ok(())
+    }
+}
+
+/// Destination of the steps recorded by a traced [`Computer`](super::Computer).
+///
+/// The trace file writers only accept `i64` steps.
+pub trait TraceSink<W = i64> {
+    fn record(&mut self, step: &Step<W>) -> io::Result<()>;
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<W: Clone> TraceSink<W> for Vec<Step<W>> {
+    #[inline]
+    fn record(&mut self, step: &Step<W>) -> io::Result<()> {
+        self.push(step.clone());
+        Ok(())
+    }
+}
+
+impl<W: Clone> TraceSink<W> for Sender<Step<W>> {
+    #[inline]
+    fn record(&mut self, step: &Step<W>) -> io::Result<()> {
+        self.send(step.clone())
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "trace receiver disconnected"))
+    }
+}
+
+const TEXT_HEADER: &str = "# intcode trace v1";
+const BINARY_MAGIC: &[u8; 4] = b"ICTR";
+const BINARY_VERSION: u8 = 1;
+
+const FLAG_WRITE: u8 = 1;
+const FLAG_RBO: u8 = 2;
+const FLAG_INPUT: u8 = 4;
+const FLAG_OUTPUT: u8 = 8;
+
+/// Writes steps in the text format.
+#[derive(Debug)]
+pub struct TextWriter<W: Write> {
+    out: W,
+}
+
+impl<W: Write> TextWriter<W> {
+    pub fn new(mut out: W) -> io::Result<TextWriter<W>> {
+        writeln!(out, "{}", TEXT_HEADER)?;
+        Ok(TextWriter { out })
+    }
+}
+
+impl TextWriter<BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        TextWriter::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<W: Write> TraceSink for TextWriter<W> {
+    #[inline]
+    fn record(&mut self, step: &Step) -> io::Result<()> {
+        writeln!(self.out, "{}", step)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Writes steps in the binary format.
+#[derive(Debug)]
+pub struct BinaryWriter<W: Write> {
+    out: W,
+}
+
+impl<W: Write> BinaryWriter<W> {
+    pub fn new(mut out: W) -> io::Result<BinaryWriter<W>> {
+        out.write_all(BINARY_MAGIC)?;
+        out.write_all(&[BINARY_VERSION])?;
+        Ok(BinaryWriter { out })
+    }
+
+    #[inline]
+    fn varint(&mut self, value: i64) -> io::Result<()> {
+        write_varint(&mut self.out, value)
+    }
+}
+
+impl BinaryWriter<BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        BinaryWriter::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+/// Number of parameters or reads of a step, stored in a byte.
+fn count(len: usize, what: &str) -> io::Result<u8> {
+    u8::try_from(len).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("too many {} in a step", what),
+        )
+    })
+}
+
+impl<W: Write> TraceSink for BinaryWriter<W> {
+    fn record(&mut self, step: &Step) -> io::Result<()> {
+        self.varint(step.ip)?;
+        self.varint(step.word)?;
+        self.out
+            .write_all(&[count(step.params.len(), "parameters")?])?;
+        for &param in &step.params {
+            self.varint(param)?;
+        }
+        self.out.write_all(&[count(step.reads.len(), "reads")?])?;
+        for &(addr, value) in &step.reads {
+            self.varint(addr)?;
+            self.varint(value)?;
+        }
+
+        let mut flags = 0;
+        for &(present, flag) in &[
+            (step.write.is_some(), FLAG_WRITE),
+            (step.rbo.is_some(), FLAG_RBO),
+            (step.input.is_some(), FLAG_INPUT),
+            (step.output.is_some(), FLAG_OUTPUT),
+        ] {
+            if present {
+                flags |= flag;
+            }
+        }
+        self.out.write_all(&[flags])?;
+        if let Some((addr, value)) = step.write {
+            self.varint(addr)?;
+            self.varint(value)?;
+        }
+        for value in step.rbo.iter().chain(&step.input).chain(&step.output) {
+            self.varint(*value)?;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Writes `value` as a zigzag LEB128 varint.
+pub(super) fn write_varint<W: Write>(out: &mut W, value: i64) -> io::Result<()> {
+    let mut n = ((value << 1) ^ (value >> 63)) as u64;
+    let mut buf = [0; 10];
+    let mut len = 0;
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            buf[len] = byte;
+            len += 1;
+            break;
+        }
+        buf[len] = byte | 0x80;
+        len += 1;
+    }
+    out.write_all(&buf[..len])
+}
+
+/// Reads a zigzag LEB128 varint, `None` on a clean end of file.
+pub(super) fn read_varint<R: Read>(input: &mut R) -> io::Result<Option<i64>> {
+    let mut n = 0u64;
+    let mut shift = 0;
+    loop {
+        let byte = match read_byte(input)? {
+            Some(byte) => byte,
+            None if shift == 0 => return Ok(None),
+            None => return Err(io::ErrorKind::UnexpectedEof.into()),
+        };
+        if shift >= 64 {
+            return Err(invalid_data("varint too long"));
+        }
+        n |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(Some((n >> 1) as i64 ^ -((n & 1) as i64)));
+        }
+        shift += 7;
+    }
+}
+
+fn read_byte<R: Read>(input: &mut R) -> io::Result<Option<u8>> {
+    let mut byte = [0];
+    loop {
+        match input.read(&mut byte) {
+            Ok(0) => return Ok(None),
+            Ok(_) => return Ok(Some(byte[0])),
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => (),
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+#[inline]
+pub(super) fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_owned())
+}
+
+/// Reads steps in the text format.
+#[derive(Debug)]
+pub struct TextReader<R> {
+    lines: io::Lines<R>,
+    line_no: usize,
+}
+
+impl<R: BufRead> TextReader<R> {
+    pub fn new(input: R) -> io::Result<TextReader<R>> {
+        let mut lines = input.lines();
+        match lines.next() {
+            Some(Ok(ref header)) if header.trim_end() == TEXT_HEADER => {
+                Ok(TextReader { lines, line_no: 1 })
+            }
+            Some(Err(err)) => Err(err),
+            _ => Err(invalid_data("missing text trace header")),
+        }
+    }
+
+    fn parse(&self, line: &str) -> io::Result<Step> {
+        let error = |msg: &str| invalid_data(&format!("line {}: {}", self.line_no, msg));
+        let number = |s: &str| s.parse::<i64>().map_err(|_| error("invalid number"));
+        let pair = |s: &str| {
+            let mut parts = s.splitn(2, '=');
+            let addr = number(parts.next().unwrap_or(""))?;
+            let value = number(parts.next().ok_or_else(|| error("missing value"))?)?;
+            Ok::<_, io::Error>((addr, value))
+        };
+
+        let line = line.split(';').next().unwrap_or("");
+        let mut fields = line.split_whitespace();
+        let ip = fields.next().ok_or_else(|| error("missing address"))?;
+        if !ip.ends_with(':') {
+            return Err(error("missing `:` after the address"));
+        }
+        let mut step = Step {
+            ip: number(&ip[..ip.len() - 1])?,
+            word: number(fields.next().ok_or_else(|| error("missing instruction"))?)?,
+            ..Step::default()
+        };
+        for field in fields {
+            if field.starts_with(|c: char| c == '-' || c.is_ascii_digit()) {
+                step.params.push(number(field)?);
+            } else if let Some(value) = field.strip_prefix("rb=") {
+                step.rbo = Some(number(value)?);
+            } else if let Some(value) = field.strip_prefix("in=") {
+                step.input = Some(number(value)?);
+            } else if let Some(value) = field.strip_prefix("out=") {
+                step.output = Some(number(value)?);
+            } else if let Some(access) = field.strip_prefix('r') {
+                step.reads.push(pair(access)?);
+            } else if let Some(access) = field.strip_prefix('w') {
+                step.write = Some(pair(access)?);
+            } else {
+                return Err(error(&format!("unknown field `{}`", field)));
+            }
+        }
+        Ok(step)
+    }
+}
+
+impl<R: BufRead> Iterator for TextReader<R> {
+    type Item = io::Result<Step>;
+
+    fn next(&mut self) -> Option<io::Result<Step>> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(err) => return Some(Err(err)),
+            };
+            self.line_no += 1;
+            if !line.trim().is_empty() && !line.starts_with('#') {
+                return Some(self.parse(&line));
+            }
+        }
+    }
+}
+
+/// Reads steps in the binary format.
+#[derive(Debug)]
+pub struct BinaryReader<R> {
+    input: R,
+}
+
+impl<R: Read> BinaryReader<R> {
+    pub fn new(mut input: R) -> io::Result<BinaryReader<R>> {
+        let mut header = [0; 5];
+        input.read_exact(&mut header)?;
+        if &header[..4] != BINARY_MAGIC {
+            return Err(invalid_data("not a binary trace"));
+        }
+        if header[4] != BINARY_VERSION {
+            return Err(invalid_data("unsupported binary trace version"));
+        }
+        Ok(BinaryReader { input })
+    }
+
+    fn varint(&mut self) -> io::Result<i64> {
+        read_varint(&mut self.input)?.ok_or_else(|| io::ErrorKind::UnexpectedEof.into())
+    }
+
+    fn byte(&mut self) -> io::Result<u8> {
+        read_byte(&mut self.input)?.ok_or_else(|| io::ErrorKind::UnexpectedEof.into())
+    }
+
+    fn read_step(&mut self, ip: i64) -> io::Result<Step> {
+        let mut step = Step {
+            ip,
+            word: self.varint()?,
+            ..Step::default()
+        };
+        for _ in 0..self.byte()? {
+            step.params.push(self.varint()?);
+        }
+        for _ in 0..self.byte()? {
+            step.reads.push((self.varint()?, self.varint()?));
+        }
+        let flags = self.byte()?;
+        if flags & !(FLAG_WRITE | FLAG_RBO | FLAG_INPUT | FLAG_OUTPUT) != 0 {
+            return Err(invalid_data("invalid step flags"));
+        }
+        if flags & FLAG_WRITE != 0 {
+            step.write = Some((self.varint()?, self.varint()?));
+        }
+        if flags & FLAG_RBO != 0 {
+            step.rbo = Some(self.varint()?);
+        }
+        if flags & FLAG_INPUT != 0 {
+            step.input = Some(self.varint()?);
+        }
+        if flags & FLAG_OUTPUT != 0 {
+            step.output = Some(self.varint()?);
+        }
+        Ok(step)
+    }
+}
+
+impl<R: Read> Iterator for BinaryReader<R> {
+    type Item = io::Result<Step>;
+
+    fn next(&mut self) -> Option<io::Result<Step>> {
+        match read_varint(&mut self.input) {
+            Ok(Some(ip)) => Some(self.read_step(ip)),
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+/// Opens a trace file of either format.
+pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Box<dyn Iterator<Item = io::Result<Step>>>> {
+    let mut input = BufReader::new(File::open(path)?);
+    if input.fill_buf()?.starts_with(BINARY_MAGIC) {
+        Ok(Box::new(BinaryReader::new(input)?))
+    } else {
+        Ok(Box::new(TextReader::new(input)?))
+    }
+}
+
+/// The first step at which two traces differ.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// Number of identical steps before the divergence
+    pub index: usize,
+    /// `None` if the left trace ended first
+    pub left: Option<Step>,
+    /// `None` if the right trace ended first
+    pub right: Option<Step>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "traces diverge after {} steps", self.index)?;
+        for (side, step) in &[("<", &self.left), (">", &self.right)] {
+            match step {
+                Some(step) => writeln!(f, "{} {}", side, step)?,
+                None => writeln!(f, "{} <end of trace>", side)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compares two traces, returns the first divergence or `None` if they are identical.
+pub fn diff<A, B>(left: A, right: B) -> io::Result<Option<Divergence>>
+where
+    A: IntoIterator<Item = io::Result<Step>>,
+    B: IntoIterator<Item = io::Result<Step>>,
+{
+    let mut left = left.into_iter();
+    let mut right = right.into_iter();
+    let mut index = 0;
+    loop {
+        let (l, r) = (left.next().transpose()?, right.next().transpose()?);
+        if l.is_none() && r.is_none() {
+            return Ok(None);
+        }
+        if l != r {
+            return Ok(Some(Divergence {
+                index,
+                left: l,
+                right: r,
+            }));
+        }
+        index += 1;
+    }
+}
+
+/// Tracing state of a [`Computer`].
+pub(super) struct Tracer<W> {
+    sink: Box<dyn TraceSink<W> + Send>,
+    /// Step of the instruction being run
+    step: Step<W>,
+}
+
+impl<W: fmt::Debug> fmt::Debug for Tracer<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Tracer").field("step", &self.step).finish()
+    }
+}
+
+impl<W: Word> Computer<W> {
+    /// Records every instruction executed from now on to `sink`, replacing the previous
+    /// sink if any.
+    pub fn start_trace<T>(&mut self, sink: T)
+    where
+        T: TraceSink<W> + Send + 'static,
+    {
+        self.tracer = Some(Tracer {
+            sink: Box::new(sink),
+            step: Step::default(),
+        });
+    }
+
+    /// Stops tracing, flushes and returns the sink.
+    pub fn stop_trace(&mut self) -> io::Result<Option<Box<dyn TraceSink<W> + Send>>> {
+        match self.tracer.take() {
+            Some(mut tracer) => {
+                tracer.sink.flush()?;
+                Ok(Some(tracer.sink))
+            }
+            None => Ok(None),
+        }
+    }
+
+    #[inline]
+    pub fn is_tracing(&self) -> bool {
+        self.tracer.is_some()
+    }
+
+    /// Starts recording the instruction at `ip`.
+    pub(super) fn trace_begin(&mut self) {
+        if self.tracer.is_none() {
+            return;
+        }
+        let word = self.read_raw(self.ip).unwrap_or_default();
+        let arity = word.to_i64().map_or(0, |word| self.arity_of(word));
+        let params = (1..=arity as i64)
+            .map(|i| self.read_raw(self.ip + i).unwrap_or_default())
+            .collect();
+        if let Some(tracer) = &mut self.tracer {
+            tracer.step = Step {
+                ip: self.ip,
+                word,
+                params,
+                ..Step::default()
+            };
+        }
+    }
+
+    /// Records a data access made by the current instruction.
+    #[inline]
+    pub(super) fn trace_access(&mut self, access: Access, addr: i64, value: &W) {
+        if let Some(tracer) = &mut self.tracer {
+            match access {
+                Access::Write => tracer.step.write = Some((addr, value.clone())),
+                _ => tracer.step.reads.push((addr, value.clone())),
+            }
+        }
+    }
+
+    #[inline]
+    pub(super) fn trace_with<F: FnOnce(&mut Step<W>)>(&mut self, f: F) {
+        if let Some(tracer) = &mut self.tracer {
+            f(&mut tracer.step);
+        }
+    }
+
+    /// Sends the step of the completed instruction to the sink.
+    pub(super) fn trace_end(&mut self) -> io::Result<()> {
+        match &mut self.tracer {
+            Some(tracer) => tracer.sink.record(&tracer.step),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::computer::{asm::assemble, examples};
+    use std::sync::mpsc;
+
+    /// Steps of the recursive Fibonacci program computing `fib(6)`.
+    fn fib_steps() -> Vec<Step> {
+        let (tx, rx) = mpsc::channel();
+        let mut computer = Computer::new(&assemble(examples::FIB).unwrap(), None);
+        computer.start_trace(tx);
+        assert_eq!(computer.resume_get(Some(6)).unwrap(), 8);
+        computer.stop_trace().unwrap();
+        rx.try_iter().collect()
+    }
+
+    fn text(steps: &[Step]) -> Vec<u8> {
+        let mut writer = TextWriter::new(Vec::new()).unwrap();
+        for step in steps {
+            writer.record(step).unwrap();
+        }
+        writer.out
+    }
+
+    fn binary(steps: &[Step]) -> Vec<u8> {
+        let mut writer = BinaryWriter::new(Vec::new()).unwrap();
+        for step in steps {
+            writer.record(step).unwrap();
+        }
+        writer.out
+    }
+
+    fn text_reader(text: &[u8]) -> TextReader<&[u8]> {
+        TextReader::new(text).unwrap()
+    }
+
+    #[test]
+    fn steps() {
+        let steps = fib_steps();
+        assert_eq!(
+            steps[0],
+            Step {
+                ip: 0,
+                word: 109,
+                params: vec![69],
+                rbo: Some(69),
+                ..Step::default()
+            }
+        );
+        assert_eq!(
+            steps[1],
+            Step {
+                ip: 2,
+                word: 203,
+                params: vec![1],
+                write: Some((70, 6)),
+                input: Some(6),
+                ..Step::default()
+            }
+        );
+        // `RBO #0` records the relative base it kept
+        let (tx, rx) = mpsc::channel();
+        let mut computer = Computer::new(&[109, 0, 99], None);
+        computer.start_trace(tx);
+        computer.run(None).unwrap();
+        computer.stop_trace().unwrap();
+        assert_eq!(rx.recv().unwrap().rbo, Some(0));
+
+        let last = steps.last().unwrap();
+        assert_eq!(last.output, Some(8));
+        assert_eq!(last.reads, vec![(71, 8)]);
+        assert_eq!(
+            last.to_string(),
+            format!("{}: 204 2 r71=8 out=8 ; OUT [rb+2]", last.ip)
+        );
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let steps = fib_steps();
+        let text = text(&steps);
+        assert!(text.starts_with(b"# intcode trace v1\n"));
+        let read: Vec<Step> = TextReader::new(&text[..])
+            .unwrap()
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(read, steps);
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let steps = fib_steps();
+        let binary = binary(&steps);
+        assert!(binary.starts_with(b"ICTR\x01"));
+        let read: Vec<Step> = BinaryReader::new(&binary[..])
+            .unwrap()
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(read, steps);
+        assert_eq!(
+            diff(
+                text_reader(&text(&steps)),
+                BinaryReader::new(&binary[..]).unwrap()
+            )
+            .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn varints() {
+        for &value in &[0, 1, -1, 63, -64, 64, 1 << 40, i64::MAX, i64::MIN] {
+            let mut buf = Vec::new();
+            write_varint(&mut buf, value).unwrap();
+            assert_eq!(read_varint(&mut &buf[..]).unwrap(), Some(value));
+        }
+        let mut buf = Vec::new();
+        write_varint(&mut buf, -1).unwrap();
+        assert_eq!(buf, [1]);
+        write_varint(&mut buf, 64).unwrap();
+        assert_eq!(buf, [1, 0x80, 1]);
+
+        assert_eq!(read_varint(&mut &[][..]).unwrap(), None);
+        let err = read_varint(&mut &[0x80][..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        let err = read_varint(&mut &[0xff; 11][..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn corrupt_binary() {
+        let err = BinaryReader::new(&b"ICTX\x01"[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = BinaryReader::new(&b"ICTR\x02"[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = BinaryReader::new(&b"ICT"[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        // every truncation inside a step is an error, not a shorter trace
+        let steps = fib_steps();
+        let binary = binary(&steps[..3]);
+        let boundaries: Vec<usize> = (0..=3).map(|n| self::binary(&steps[..n]).len()).collect();
+        for len in 5..binary.len() {
+            let read: io::Result<Vec<Step>> = BinaryReader::new(&binary[..len]).unwrap().collect();
+            match boundaries.iter().position(|&b| b == len) {
+                Some(n) => assert_eq!(read.unwrap(), &steps[..n]),
+                None => assert_eq!(read.unwrap_err().kind(), io::ErrorKind::UnexpectedEof),
+            }
+        }
+
+        let step = Step {
+            reads: vec![(0, 0); 256],
+            ..Step::default()
+        };
+        let err = BinaryWriter::new(Vec::new())
+            .unwrap()
+            .record(&step)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "too many reads in a step");
+
+        // `HLT` at 0 without parameters or reads, with an unknown flag
+        let err = BinaryReader::new(&b"ICTR\x01\x00\xc6\x01\x00\x00\x10"[..])
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(err.to_string(), "invalid step flags");
+    }
+
+    #[test]
+    fn corrupt_text() {
+        let error = |text: &str| {
+            TextReader::new(text.as_bytes())
+                .and_then(|reader| reader.collect::<io::Result<Vec<Step>>>())
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(error("0: 99\n"), "missing text trace header");
+        assert_eq!(error(""), "missing text trace header");
+        assert_eq!(
+            error("# intcode trace v1\n0 99"),
+            "line 2: missing `:` after the address"
+        );
+        assert_eq!(
+            error("# intcode trace v1\n\n0:"),
+            "line 3: missing instruction"
+        );
+        assert_eq!(error("# intcode trace v1\n0: 1x"), "line 2: invalid number");
+        assert_eq!(
+            error("# intcode trace v1\n0: 4 5 r5"),
+            "line 2: missing value"
+        );
+        assert_eq!(
+            error("# intcode trace v1\n0: 99 z=1"),
+            "line 2: unknown field `z=1`"
+        );
+
+        let steps: Vec<Step> =
+            TextReader::new(&b"# intcode trace v1\n# comment\n\n3: 99 ; HLT\n"[..])
+                .unwrap()
+                .collect::<io::Result<_>>()
+                .unwrap();
+        assert_eq!(
+            steps,
+            vec![Step {
+                ip: 3,
+                word: 99,
+                ..Step::default()
+            }]
+        );
+    }
+
+    #[test]
+    fn divergence() {
+        let steps = fib_steps();
+        let mut other = steps.clone();
+        other[5].reads[0].1 += 1;
+        other.truncate(10);
+        let ok = |steps: Vec<Step>| steps.into_iter().map(Ok);
+        let divergence = diff(ok(steps.clone()), ok(other.clone())).unwrap().unwrap();
+        assert_eq!(divergence.index, 5);
+        assert_eq!(divergence.left.as_ref(), Some(&steps[5]));
+        assert_eq!(divergence.right.as_ref(), Some(&other[5]));
+
+        let divergence = diff(ok(steps[..10].to_vec()), ok(steps[..8].to_vec()))
+            .unwrap()
+            .unwrap();
+        assert_eq!((divergence.index, divergence.right.as_ref()), (8, None));
+        assert!(divergence.to_string().contains("> <end of trace>"));
+    }
+}
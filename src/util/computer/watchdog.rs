@@ -0,0 +1,235 @@
+//! Instruction budget and infinite loop detection.
+
+use super::{Computer, ErrorKind, Memory, Result, Word};
+
+#[derive(Debug, Clone)]
+pub(super) struct Watchdog<W> {
+    /// Instructions left to run
+    budget: Option<u64>,
+    detector: Option<LoopDetector<W>>,
+}
+
+impl<W> Default for Watchdog<W> {
+    #[inline]
+    fn default() -> Self {
+        Watchdog {
+            budget: None,
+            detector: None,
+        }
+    }
+}
+
+/// Brent's cycle detection over the machine states seen since the last I/O.
+///
+/// A state is compared with a saved one, which is replaced after 1, 2, 4, 8... steps.
+/// Without I/O the machine is deterministic, so a repeated state means it loops forever.
+#[derive(Debug, Clone)]
+struct LoopDetector<W> {
+    mark: Option<Mark<W>>,
+    /// Steps since the mark was saved
+    steps: u64,
+    /// Steps before the mark is replaced
+    power: u64,
+}
+
+impl<W> Default for LoopDetector<W> {
+    #[inline]
+    fn default() -> Self {
+        LoopDetector {
+            mark: None,
+            steps: 0,
+            power: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Mark<W> {
+    ip: i64,
+    rbo: i64,
+    inputs: usize,
+    /// Shares its pages with the machine memory, so saving it is cheap
+    mem: Memory<W>,
+}
+
+impl<W: Word> Computer<W> {
+    /// Limits the number of instructions the machine may run, `None` removes the limit.
+    ///
+    /// Once the budget is spent, running another instruction fails with
+    /// [`ErrorKind::BudgetExhausted`].
+    #[inline]
+    pub fn set_budget(&mut self, budget: Option<u64>) {
+        self.watchdog.budget = budget;
+    }
+
+    /// Instructions left in the budget.
+    #[inline]
+    pub fn budget(&self) -> Option<u64> {
+        self.watchdog.budget
+    }
+
+    /// Enables or disables infinite loop detection.
+    ///
+    /// When the machine returns to the exact same state (registers, memory and pending
+    /// input) without any input or output in between, running fails with
+    /// [`ErrorKind::InfiniteLoop`]. Detection happens within about twice the period of
+    /// the loop after it was entered.
+    pub fn detect_loops(&mut self, enabled: bool) {
+        self.watchdog.detector = if enabled {
+            Some(LoopDetector::default())
+        } else {
+            None
+        };
+    }
+
+    #[inline]
+    pub(super) fn detects_loops(&self) -> bool {
+        self.watchdog.detector.is_some()
+    }
+
+    /// Spends one instruction of the budget, before it runs.
+    #[inline]
+    pub(super) fn watchdog_begin(&mut self) -> Result<(), W> {
+        match &mut self.watchdog.budget {
+            Some(0) => Err(self.error(ErrorKind::BudgetExhausted)),
+            Some(budget) => {
+                *budget -= 1;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Gives back the instruction spent on a read that blocked.
+    #[inline]
+    pub(super) fn watchdog_refund(&mut self) {
+        if let Some(budget) = &mut self.watchdog.budget {
+            *budget += 1;
+        }
+    }
+
+    /// Checks the state reached after an instruction, `io` tells whether it made I/O or
+    /// halted: a halt leaves the state unchanged but is no loop.
+    pub(super) fn watchdog_end(&mut self, io: bool) -> Result<(), W> {
+        let detector = match &mut self.watchdog.detector {
+            Some(detector) => detector,
+            None => return Ok(()),
+        };
+        let fresh = match &detector.mark {
+            Some(mark) => io || mark.inputs != self.inputs.len(),
+            None => true,
+        };
+        if fresh {
+            detector.mark = None;
+            detector.power = 1;
+        } else if let Some(mark) = &detector.mark {
+            detector.steps += 1;
+            if mark.ip == self.ip
+                && mark.rbo == self.rbo
+                && mark.mem.digest() == self.mem.digest()
+                && mark.mem == self.mem
+            {
+                let period = detector.steps;
+                return Err(self.error(ErrorKind::InfiniteLoop(period)));
+            }
+            if detector.steps < detector.power {
+                return Ok(());
+            }
+            detector.power *= 2;
+        }
+        detector.steps = 0;
+        detector.mark = Some(Mark {
+            ip: self.ip,
+            rbo: self.rbo,
+            inputs: self.inputs.len(),
+            mem: self.mem.clone(),
+        });
+        Ok(())
+    }
+
+    /// Forgets the states seen so far, after the machine state was changed from outside.
+    #[inline]
+    pub(super) fn watchdog_reset(&mut self) {
+        if let Some(detector) = &mut self.watchdog.detector {
+            *detector = LoopDetector::default();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::examples::{FIB, QUINE};
+    use super::super::{asm::assemble, Stopped};
+    use super::*;
+
+    #[test]
+    fn budget() {
+        // three instructions
+        let code = [1101, 1, 2, 5, 99, 0];
+        let mut computer = Computer::new(&code, None);
+        computer.set_budget(Some(3));
+        assert_eq!(computer.run(None).unwrap(), Stopped::Halted);
+        assert_eq!(computer.budget(), Some(1));
+
+        let mut computer = Computer::new(&code, None);
+        computer.set_budget(Some(1));
+        let err = computer.run(None).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::BudgetExhausted));
+        assert_eq!(err.location(), 4);
+        assert_eq!(computer.read_raw(5).unwrap(), 3);
+        computer.set_budget(None);
+        assert_eq!(computer.run(None).unwrap(), Stopped::Halted);
+    }
+
+    #[test]
+    fn blocked_reads_are_free() {
+        let mut computer = Computer::new(&[3, 5, 4, 5, 99, 0], None);
+        computer.set_budget(Some(2));
+        for _ in 0..5 {
+            assert_eq!(computer.run(None).unwrap(), Stopped::NeedsInput);
+        }
+        assert_eq!(computer.budget(), Some(2));
+        assert_eq!(computer.run(Some(7)).unwrap(), Stopped::Output(7));
+        let err = computer.run(None).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::BudgetExhausted));
+    }
+
+    #[test]
+    fn loops() {
+        // jumps to itself
+        let mut computer = Computer::new(&[1105, 1, 0], None);
+        computer.detect_loops(true);
+        let err = computer.run(None).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::InfiniteLoop(1)));
+
+        // flips the sign of a cell
+        let mut computer = Computer::new(&[1002, 7, -1, 7, 1105, 1, 0, 1], None);
+        computer.detect_loops(true);
+        assert!(computer.detects_loops());
+        let err = computer.run(None).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::InfiniteLoop(4)));
+        computer.detect_loops(false);
+        computer.set_budget(Some(100));
+        let err = computer.run(None).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::BudgetExhausted));
+    }
+
+    #[test]
+    fn terminating_programs() {
+        let mut computer = Computer::new(&assemble(FIB).unwrap(), None);
+        computer.detect_loops(true);
+        assert_eq!(computer.run(Some(10)).unwrap(), Stopped::Output(55));
+
+        // outputs the same state over and over, each output starts afresh
+        let mut computer = Computer::new(&[104, 1, 1105, 1, 0], None);
+        computer.detect_loops(true);
+        for _ in 0..100 {
+            assert_eq!(computer.run(None).unwrap(), Stopped::Output(1));
+        }
+
+        // halts right after the state it is compared with was saved
+        let mut computer = Computer::new(QUINE, None);
+        computer.detect_loops(true);
+        while computer.run(None).unwrap() != Stopped::Halted {}
+    }
+}
@@ -0,0 +1,161 @@
+use super::BigInt;
+
+use std::{
+    convert::TryFrom,
+    fmt::{Debug, Display},
+    hash::Hash,
+    str::FromStr,
+};
+
+/// Type of the memory cells and I/O values of a [`Computer`](super::Computer).
+///
+/// Addresses, the instruction pointer and the relative base stay `i64`: a word used as
+/// one of them must fit in an `i64`.
+pub trait Word:
+    Clone
+    + Default
+    + PartialEq
+    + Eq
+    + PartialOrd
+    + Ord
+    + Hash
+    + Debug
+    + Display
+    + FromStr
+    + From<i32>
+    + Send
+    + Sync
+    + 'static
+{
+    /// Returns the value as an `i64`, `None` if it does not fit.
+    fn to_i64(&self) -> Option<i64>;
+
+    #[inline]
+    fn is_zero(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Cheap hash of the value, used to maintain the memory digest.
+    fn fingerprint(&self) -> u64;
+
+    fn checked_add(&self, rhs: &Self) -> Option<Self>;
+    fn wrapping_add(&self, rhs: &Self) -> Self;
+    fn saturating_add(&self, rhs: &Self) -> Self;
+    fn checked_mul(&self, rhs: &Self) -> Option<Self>;
+    fn wrapping_mul(&self, rhs: &Self) -> Self;
+    fn saturating_mul(&self, rhs: &Self) -> Self;
+}
+
+macro_rules! impl_word {
+    ($($t:ty),*) => {$(
+        impl Word for $t {
+            #[inline]
+            fn to_i64(&self) -> Option<i64> {
+                i64::try_from(*self).ok()
+            }
+
+            #[inline]
+            fn is_zero(&self) -> bool {
+                *self == 0
+            }
+
+            #[inline]
+            fn fingerprint(&self) -> u64 {
+                let value = *self as i128 as u128;
+                (value as u64) ^ (value >> 64) as u64
+            }
+
+            #[inline]
+            fn checked_add(&self, rhs: &Self) -> Option<Self> {
+                <$t>::checked_add(*self, *rhs)
+            }
+
+            #[inline]
+            fn wrapping_add(&self, rhs: &Self) -> Self {
+                <$t>::wrapping_add(*self, *rhs)
+            }
+
+            #[inline]
+            fn saturating_add(&self, rhs: &Self) -> Self {
+                <$t>::saturating_add(*self, *rhs)
+            }
+
+            #[inline]
+            fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+                <$t>::checked_mul(*self, *rhs)
+            }
+
+            #[inline]
+            fn wrapping_mul(&self, rhs: &Self) -> Self {
+                <$t>::wrapping_mul(*self, *rhs)
+            }
+
+            #[inline]
+            fn saturating_mul(&self, rhs: &Self) -> Self {
+                <$t>::saturating_mul(*self, *rhs)
+            }
+        }
+    )*};
+}
+
+impl_word!(i32, i64, i128);
+
+/// Never overflows, every arithmetic policy gives the exact result.
+impl Word for BigInt {
+    #[inline]
+    fn to_i64(&self) -> Option<i64> {
+        BigInt::to_i64(self)
+    }
+
+    #[inline]
+    fn is_zero(&self) -> bool {
+        BigInt::is_zero(self)
+    }
+
+    fn fingerprint(&self) -> u64 {
+        self.limbs()
+            .iter()
+            .fold(self.is_negative() as u64, |acc, &limb| {
+                acc.rotate_left(32) ^ u64::from(limb)
+            })
+    }
+
+    #[inline]
+    fn checked_add(&self, rhs: &Self) -> Option<Self> {
+        Some(self + rhs)
+    }
+
+    #[inline]
+    fn wrapping_add(&self, rhs: &Self) -> Self {
+        self + rhs
+    }
+
+    #[inline]
+    fn saturating_add(&self, rhs: &Self) -> Self {
+        self + rhs
+    }
+
+    #[inline]
+    fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+        Some(self * rhs)
+    }
+
+    #[inline]
+    fn wrapping_mul(&self, rhs: &Self) -> Self {
+        self * rhs
+    }
+
+    #[inline]
+    fn saturating_mul(&self, rhs: &Self) -> Self {
+        self * rhs
+    }
+}
+
+/// Parses a comma-separated program, like the puzzle inputs.
+pub fn parse_code<W: Word>(source: &str) -> Option<Vec<W>> {
+    source
+        .trim()
+        .split(',')
+        .map(|n| n.trim().parse().ok())
+        .collect()
+}